@@ -1,28 +1,139 @@
-use std::sync::{atomic::AtomicBool, Arc};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
-use crate::keyboard::KeyboardEmulator;
+use serde::Serialize;
+
+use crate::{
+    config::{validate, Config, ConfigDto, ConfigManager, FieldError, OversizePaste, Validated},
+    countdown::Countdown,
+    debounce::Debounce,
+    error::{ClipboardError, PastaError, TypingError},
+    events::PasteTooLarge,
+    hooks::PasteHooks,
+    keyboard::{KeyboardEmulator, PasteMethod},
+    repeat::{self, Repeat},
+    schedule,
+    transform::{self, TransformConfig},
+    typing_state::TypingOutcome,
+};
 
 /// Trait for clipboard operations to allow mocking in tests
 pub trait ClipboardProvider: Send + Sync {
-    fn get_content(&self) -> Result<Option<String>, String>;
+    fn get_content(&self) -> Result<Option<String>, ClipboardError>;
 }
 
 /// Real implementation of ClipboardProvider using arboard
 pub struct SystemClipboard;
 
 impl ClipboardProvider for SystemClipboard {
-    fn get_content(&self) -> Result<Option<String>, String> {
+    fn get_content(&self) -> Result<Option<String>, ClipboardError> {
         crate::clipboard::get_clipboard_content()
     }
 }
 
+/// What a paste did, sent to frontends as `typing_finished`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteReport {
+    /// Characters actually typed
+    pub typed: usize,
+    /// Characters left untyped, e.g. after a cancel
+    pub skipped: usize,
+    pub duration_ms: u64,
+    /// Whether the transforms changed the text before typing
+    pub transformed: bool,
+    /// Whether the paste was cancelled before typing everything
+    pub cancelled: bool,
+}
+
+impl PasteReport {
+    /// Report for a paste the keyboard worker finished with
+    pub fn from_outcome(outcome: &TypingOutcome) -> Self {
+        Self {
+            typed: outcome.typed,
+            skipped: outcome.total.saturating_sub(outcome.typed),
+            duration_ms: outcome.duration.as_millis() as u64,
+            transformed: false,
+            cancelled: outcome.cancelled,
+        }
+    }
+
+    /// Report for a paste cancelled before any of its `chars` were typed
+    pub fn cancelled_before_typing(chars: usize) -> Self {
+        Self {
+            skipped: chars,
+            cancelled: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// How a single paste runs, read from the config when it is triggered
+#[derive(Default)]
+pub struct PasteOptions {
+    /// Queue behind running work instead of failing with `Busy`
+    pub queue: bool,
+    pub countdown: Countdown,
+    /// Drops the paste if the same one was just let through
+    pub debounce: Option<Debounce>,
+    pub hooks: PasteHooks,
+    /// Type the clipboard this many times instead of once
+    pub repeat: Option<Repeat>,
+    /// Whitespace clean-up applied to the clipboard before anything else
+    pub transforms: TransformConfig,
+    /// Type the text or press the paste shortcut; only a clipboard left as it
+    /// is can use the shortcut
+    pub paste_method: PasteMethod,
+    /// Characters above which `PasteMethod::Auto` uses the shortcut
+    pub keystroke_threshold: u64,
+    /// Longest text a typed paste may be, 0 for no limit
+    pub max_chars: usize,
+    /// Drop text over `max_chars` or cut it to the limit
+    pub oversize: OversizePaste,
+    /// Told about text over `max_chars` before it is dropped or cut
+    pub on_too_large: Option<Box<dyn Fn(&PasteTooLarge) + Send + Sync>>,
+}
+
 /// Business logic for paste clipboard operation
 /// This is extracted from the Tauri command to be testable
+///
+/// While another operation is queued or typing this fails with
+/// `PastaError::Busy`, unless `queue_pastes` is set. Otherwise it waits until
+/// the text is typed or cancelled; an empty clipboard is an empty report.
 pub async fn handle_paste_clipboard(
     clipboard: &dyn ClipboardProvider,
     keyboard_emulator: &Arc<KeyboardEmulator>,
     cancellation_flag: Arc<AtomicBool>,
-) -> Result<(), String> {
+    queue_pastes: bool,
+) -> Result<PasteReport, PastaError> {
+    let options = PasteOptions {
+        queue: queue_pastes,
+        ..PasteOptions::default()
+    };
+    handle_paste_clipboard_with(clipboard, keyboard_emulator, cancellation_flag, &options).await
+}
+
+/// Paste the clipboard as `options` say
+///
+/// Without `options.queue` a paste while the keyboard is busy fails with
+/// `Busy` before its countdown and hooks. Cancelling during the countdown or
+/// the pre-paste hook drops the paste without typing anything,
+/// as does the debounce when the same paste was just triggered. A repeated
+/// paste is built before the countdown and typed as one operation. The
+/// pre-paste hook runs right before typing and the post-paste hook once it is
+/// done. `options.transforms` clean up the clipboard before any of that; a
+/// clipboard they leave empty is an empty report. A paste sent with the
+/// platform shortcut can be cancelled during the countdown or while queued,
+/// but not once the shortcut is pressed. Typed text over `options.max_chars`
+/// fails with `TooLarge` or is cut to the limit, as `options.oversize` says.
+pub async fn handle_paste_clipboard_with(
+    clipboard: &dyn ClipboardProvider,
+    keyboard_emulator: &Arc<KeyboardEmulator>,
+    cancellation_flag: Arc<AtomicBool>,
+    options: &PasteOptions,
+) -> Result<PasteReport, PastaError> {
     log::info!("Paste clipboard logic triggered");
 
     // Get current clipboard content
@@ -30,24 +141,225 @@ pub async fn handle_paste_clipboard(
 
     match clipboard_result {
         Ok(Some(text)) => {
-            log::info!("Got clipboard content, typing text");
-            if let Err(e) = keyboard_emulator.type_text(&text, cancellation_flag).await {
-                log::error!("Failed to type text: {e:?}");
-                return Err(format!("Failed to type text: {e}"));
+            let (text, transformed) = transform_text(text, &options.transforms);
+            if !options.transforms.is_noop() && text.is_empty() {
+                log::info!("Clipboard is only whitespace, nothing to type");
+                return Ok(PasteReport::default());
+            }
+            if options
+                .debounce
+                .as_ref()
+                .is_some_and(|debounce| !debounce.accept(&text))
+            {
+                return Ok(PasteReport::default());
+            }
+            let text = match &options.repeat {
+                Some(repeat) => repeat.apply(&text)?,
+                None => text,
+            };
+            let chars = text.chars().count();
+            let keystroke = options
+                .paste_method
+                .uses_keystroke(chars, options.keystroke_threshold);
+            // The shortcut pastes the clipboard as it is, so rewritten text is typed
+            let keystroke = if keystroke && (transformed || options.repeat.is_some()) {
+                log::info!("Typing instead of using the paste shortcut, the text was rewritten");
+                false
+            } else {
+                keystroke
+            };
+            let (text, chars) = if keystroke {
+                (text, chars)
+            } else {
+                limit_length(text, chars, options)?
+            };
+            let state = keyboard_emulator.typing_state();
+            let busy = state.is_typing || state.queue_len > 0;
+            // Rejected right away, not after a countdown and hook for nothing
+            if busy && !options.queue {
+                log::info!("Keyboard is busy, rejecting the paste");
+                return Err(PastaError::Busy);
+            }
+            // A cancel left over from earlier work mustn't end this paste
+            if !busy {
+                cancellation_flag.store(false, Ordering::Relaxed);
+            }
+            let countdown = &options.countdown;
+            if !countdown.is_none() && !countdown.run(&cancellation_flag).await {
+                log::info!("Paste cancelled during the countdown");
+                return Ok(PasteReport::cancelled_before_typing(chars));
             }
-            Ok(())
+            options.hooks.before().await?;
+            // Typing clears the flag while the keyboard is idle, so a cancel
+            // during the countdown's last moment or the pre-paste hook is
+            // honoured here or not at all
+            if cancellation_flag.load(Ordering::Relaxed) {
+                log::info!("Paste cancelled before typing");
+                return Ok(PasteReport::cancelled_before_typing(chars));
+            }
+            let typing = if keystroke {
+                log::info!("Got clipboard content, pasting it with the paste shortcut");
+                keyboard_emulator
+                    .try_paste_keystroke(chars, cancellation_flag, options.queue)
+                    .await
+            } else {
+                log::info!("Got clipboard content, typing text");
+                keyboard_emulator
+                    .try_type_text(&text, cancellation_flag, options.queue)
+                    .await
+            };
+            let result = match typing {
+                Ok(typing) => match typing.finished().await {
+                    Ok(outcome) if outcome.secure_input => Err(PastaError::SecureInputActive {
+                        owner: keyboard_emulator.secure_input().owner(),
+                    }),
+                    Ok(outcome) if outcome.input_rejected => Err(TypingError::InputRejected.into()),
+                    Ok(outcome) => Ok(PasteReport {
+                        transformed,
+                        ..PasteReport::from_outcome(&outcome)
+                    }),
+                    Err(e) => Err(e.into()),
+                },
+                Err(e) => {
+                    log::error!("Failed to type text: {e:?}");
+                    Err(e.into())
+                }
+            };
+            options.hooks.after(&result);
+            result
         }
         Ok(None) => {
             log::info!("Clipboard is empty");
-            Ok(())
+            Ok(PasteReport::default())
         }
         Err(e) => {
             log::error!("Failed to get clipboard content: {e}");
-            Err(e)
+            Err(e.into())
         }
     }
 }
 
+/// `text` cleaned up by `transforms`, and whether that changed it
+fn transform_text(text: String, transforms: &TransformConfig) -> (String, bool) {
+    if transforms.is_noop() {
+        return (text, false);
+    }
+    let cleaned = transform::apply_transforms(&text, transforms);
+    let transformed = cleaned != text;
+    (cleaned, transformed)
+}
+
+/// `text` of `chars` characters held to `options.max_chars`
+///
+/// Over the limit `options.on_too_large` is told first, then the text fails
+/// with `TooLarge` or is cut to the limit, as `options.oversize` says.
+fn limit_length(
+    text: String,
+    chars: usize,
+    options: &PasteOptions,
+) -> Result<(String, usize), PastaError> {
+    let max = options.max_chars;
+    if max == 0 || chars <= max {
+        return Ok((text, chars));
+    }
+    let too_large = PasteTooLarge {
+        chars,
+        max,
+        truncated: options.oversize == OversizePaste::Truncate,
+    };
+    log::warn!("{}", too_large.message());
+    if let Some(on_too_large) = &options.on_too_large {
+        on_too_large(&too_large);
+    }
+    if !too_large.truncated {
+        return Err(PastaError::TooLarge { chars, max });
+    }
+    // Counted in chars, so multibyte text is cut at the same length
+    Ok((text.chars().take(max).collect(), max))
+}
+
+/// Business logic for the type_text command
+/// Types text supplied by a frontend through the same keyboard path as a paste,
+/// including its busy policy
+///
+/// Of `options` only `queue`, `transforms` and the `max_chars` limit apply,
+/// the text is typed right away without countdown, hooks or debounce. Text
+/// the transforms leave empty is `Empty`, like no text at all.
+pub async fn handle_type_text(
+    text: &str,
+    keyboard_emulator: &Arc<KeyboardEmulator>,
+    cancellation_flag: Arc<AtomicBool>,
+    options: &PasteOptions,
+) -> Result<(), PastaError> {
+    let (text, _) = transform_text(text.to_string(), &options.transforms);
+    if text.is_empty() {
+        log::info!("No text supplied, nothing to type");
+        return Err(PastaError::Empty);
+    }
+
+    let chars = text.chars().count();
+    let (text, chars) = limit_length(text, chars, options)?;
+    log::info!("Typing {chars} characters supplied by the UI");
+    // Returns once queued, the text is typed in the background
+    if let Err(e) = keyboard_emulator
+        .try_type_text(&text, cancellation_flag, options.queue)
+        .await
+    {
+        log::error!("Failed to type text: {e:?}");
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Business logic for the set_config command
+/// Validates the settings, persists them and applies them to the keyboard emulator
+///
+/// Any invalid field rejects the whole update unless `partial` is set, in which
+/// case the valid fields are saved and the errors are returned alongside them.
+pub async fn apply_config_update(
+    config_manager: &ConfigManager,
+    keyboard_emulator: &KeyboardEmulator,
+    dto: ConfigDto,
+    partial: bool,
+) -> Result<Validated, Vec<FieldError>> {
+    let Validated { config, errors } = validate::validate_dto(dto, config_manager.get());
+    if !errors.is_empty() && !partial {
+        return Err(errors);
+    }
+
+    if let Err(e) = config_manager.set(config.clone()) {
+        let e = PastaError::Config(e);
+        log::error!("{e}");
+        return Err(vec![FieldError::new("config", e.to_string())]);
+    }
+
+    if let Err(e) = apply_typing_settings(keyboard_emulator, &config).await {
+        log::error!("Failed to apply typing speed: {e}");
+        return Err(vec![FieldError::new(
+            "typingSpeed",
+            format!("Failed to apply typing speed: {e}"),
+        )]);
+    }
+
+    log::info!("Config updated: {config:?}");
+    Ok(Validated { config, errors })
+}
+
+/// Hand the config's speed, delay override, typing mode, chunking and jitter
+/// to the worker
+pub async fn apply_typing_settings(
+    keyboard_emulator: &KeyboardEmulator,
+    config: &Config,
+) -> Result<(), TypingError> {
+    keyboard_emulator.set_typing_speed(config.typing_speed);
+    keyboard_emulator.set_custom_delay(config.typing_delay_ms);
+    keyboard_emulator
+        .set_typing_mode(config.typing_mode, config.line_options())
+        .await?;
+    keyboard_emulator.set_chunking(config.chunking()).await?;
+    keyboard_emulator.set_jitter(config.jitter_percent).await
+}
+
 /// Menu structure data that can be tested independently of Tauri
 #[derive(Debug, Clone, PartialEq)]
 pub struct MenuStructure {
@@ -56,37 +368,172 @@ pub struct MenuStructure {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum MenuItem {
-    Action { id: String, label: String },
+    Action {
+        id: String,
+        label: String,
+        /// Greyed out and unclickable when false
+        enabled: bool,
+    },
+    Submenu {
+        label: String,
+        items: Vec<MenuItem>,
+    },
+    /// Toggles a setting, shown with a checkmark while `checked`
+    Check {
+        id: String,
+        label: String,
+        checked: bool,
+    },
     Separator,
 }
 
 /// Create the menu structure
 /// This is pure logic that can be tested without Tauri
-pub fn create_menu_structure() -> MenuStructure {
-    MenuStructure {
-        items: vec![
-            MenuItem::Action {
-                id: "paste".to_string(),
-                label: "Paste".to_string(),
-            },
-            MenuItem::Action {
-                id: "cancel_typing".to_string(),
-                label: "Cancel Typing".to_string(),
-            },
-            MenuItem::Separator,
-            MenuItem::Action {
-                id: "quit".to_string(),
-                label: "Quit".to_string(),
-            },
-        ],
+pub fn create_menu_structure(is_typing: bool) -> MenuStructure {
+    menu_structure(false, is_typing, PasteMethod::default(), false, &[])
+}
+
+/// Choices of the tray's "Paste method" submenu
+const PASTE_METHODS: [(PasteMethod, &str, &str); 3] = [
+    (PasteMethod::Type, "type", "Type it out"),
+    (PasteMethod::Keystroke, "keystroke", "Paste shortcut"),
+    (PasteMethod::Auto, "auto", "Shortcut for long text"),
+];
+
+/// The menu, with "Cancel scheduled paste" while a paste is `scheduled`
+///
+/// "Cancel Typing" is only enabled while `is_typing`, i.e. a paste is counting
+/// down, queued or typing. The current `paste_method` is ticked, and
+/// "Launch at Login" is checked while `launch_at_login`. A "Snippets" submenu
+/// lists `snippets` by name when there are any, with ids by position since
+/// names can hold anything.
+pub fn menu_structure(
+    scheduled: bool,
+    is_typing: bool,
+    paste_method: PasteMethod,
+    launch_at_login: bool,
+    snippets: &[String],
+) -> MenuStructure {
+    let mut items = vec![
+        MenuItem::Action {
+            id: "paste".to_string(),
+            label: "Paste".to_string(),
+            enabled: true,
+        },
+        MenuItem::Action {
+            id: "cancel_typing".to_string(),
+            label: "Cancel Typing".to_string(),
+            enabled: is_typing,
+        },
+        MenuItem::Action {
+            id: "pause_typing".to_string(),
+            label: "Pause Typing".to_string(),
+            enabled: true,
+        },
+        MenuItem::Action {
+            id: "resume_typing".to_string(),
+            label: "Resume Typing".to_string(),
+            enabled: true,
+        },
+        MenuItem::Submenu {
+            label: "Paste ×N…".to_string(),
+            items: repeat::TRAY_PRESETS
+                .iter()
+                .map(|count| MenuItem::Action {
+                    id: format!("paste_repeated_{count}"),
+                    label: format!("×{count}, Tab-separated"),
+                    enabled: true,
+                })
+                .collect(),
+        },
+        MenuItem::Submenu {
+            label: "Paste later…".to_string(),
+            items: schedule::TRAY_PRESETS_SECS
+                .iter()
+                .map(|secs| MenuItem::Action {
+                    id: format!("paste_later_{secs}"),
+                    label: format!("In {secs} seconds"),
+                    enabled: true,
+                })
+                .collect(),
+        },
+    ];
+    if !snippets.is_empty() {
+        items.push(MenuItem::Submenu {
+            label: "Snippets".to_string(),
+            items: snippets
+                .iter()
+                .enumerate()
+                .map(|(index, name)| MenuItem::Action {
+                    id: format!("snippet_{index}"),
+                    label: name.clone(),
+                    enabled: true,
+                })
+                .collect(),
+        });
+    }
+    if scheduled {
+        items.push(MenuItem::Action {
+            id: "cancel_scheduled".to_string(),
+            label: "Cancel scheduled paste".to_string(),
+            enabled: true,
+        });
     }
+    items.extend([
+        MenuItem::Separator,
+        MenuItem::Submenu {
+            label: "Paste method".to_string(),
+            items: PASTE_METHODS
+                .iter()
+                .map(|(method, id, label)| MenuItem::Action {
+                    id: format!("paste_method_{id}"),
+                    label: if *method == paste_method {
+                        format!("✓ {label}")
+                    } else {
+                        label.to_string()
+                    },
+                    enabled: true,
+                })
+                .collect(),
+        },
+        MenuItem::Check {
+            id: "launch_at_login".to_string(),
+            label: "Launch at Login".to_string(),
+            checked: launch_at_login,
+        },
+        MenuItem::Action {
+            id: "settings".to_string(),
+            label: "Settings…".to_string(),
+            enabled: true,
+        },
+        MenuItem::Action {
+            id: "quit".to_string(),
+            label: "Quit".to_string(),
+            enabled: true,
+        },
+    ]);
+    MenuStructure { items }
 }
 
 /// Menu action enum
 #[derive(Debug, PartialEq)]
 pub enum MenuAction {
     Paste,
+    /// Paste this many copies with the tray separator
+    PasteRepeated(u32),
+    /// Schedule a paste this many seconds from now
+    PasteLater(u64),
+    /// Type the snippet at this position in the menu
+    TypeSnippet(usize),
+    /// Switch `paste_method` in the config
+    SetPasteMethod(PasteMethod),
+    /// Flip `launch_at_login` and the login item with it
+    ToggleLaunchAtLogin,
     CancelTyping,
+    PauseTyping,
+    ResumeTyping,
+    CancelScheduled,
+    OpenSettings,
     Quit,
     None,
 }
@@ -96,20 +543,54 @@ pub fn handle_menu_event(event_id: &str) -> MenuAction {
     match event_id {
         "paste" => MenuAction::Paste,
         "cancel_typing" => MenuAction::CancelTyping,
+        "pause_typing" => MenuAction::PauseTyping,
+        "resume_typing" => MenuAction::ResumeTyping,
+        "cancel_scheduled" => MenuAction::CancelScheduled,
+        "launch_at_login" => MenuAction::ToggleLaunchAtLogin,
+        "settings" => MenuAction::OpenSettings,
         "quit" => MenuAction::Quit,
-        _ => MenuAction::None,
+        id => {
+            if let Some(count) = id.strip_prefix("paste_repeated_") {
+                count
+                    .parse()
+                    .map_or(MenuAction::None, MenuAction::PasteRepeated)
+            } else if let Some(method) = id.strip_prefix("paste_method_") {
+                PASTE_METHODS
+                    .iter()
+                    .find(|(_, method_id, _)| *method_id == method)
+                    .map_or(MenuAction::None, |(method, _, _)| {
+                        MenuAction::SetPasteMethod(*method)
+                    })
+            } else if let Some(secs) = id.strip_prefix("paste_later_") {
+                secs.parse()
+                    .map_or(MenuAction::None, MenuAction::PasteLater)
+            } else if let Some(index) = id.strip_prefix("snippet_") {
+                index
+                    .parse()
+                    .map_or(MenuAction::None, MenuAction::TypeSnippet)
+            } else {
+                MenuAction::None
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Mutex;
+    use std::{
+        sync::{atomic::AtomicUsize, Mutex},
+        time::Duration,
+    };
 
     use super::*;
+    use crate::{
+        config::{Config, SettingsCloseBehavior},
+        keyboard::TypingSpeed,
+    };
 
     /// Mock clipboard for testing
     struct MockClipboard {
-        content: Arc<Mutex<Result<Option<String>, String>>>,
+        content: Arc<Mutex<Result<Option<String>, ClipboardError>>>,
     }
 
     impl MockClipboard {
@@ -125,15 +606,15 @@ mod tests {
             }
         }
 
-        fn new_with_error(error: &str) -> Self {
+        fn new_with_error(error: ClipboardError) -> Self {
             Self {
-                content: Arc::new(Mutex::new(Err(error.to_string()))),
+                content: Arc::new(Mutex::new(Err(error))),
             }
         }
     }
 
     impl ClipboardProvider for MockClipboard {
-        fn get_content(&self) -> Result<Option<String>, String> {
+        fn get_content(&self) -> Result<Option<String>, ClipboardError> {
             self.content.lock().unwrap().clone()
         }
     }
@@ -146,9 +627,12 @@ mod tests {
         let keyboard_emulator = Arc::new(KeyboardEmulator::new().unwrap());
         let cancellation_flag = Arc::new(AtomicBool::new(false));
 
-        let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag).await;
-        assert!(result.is_ok());
+        let report =
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, false)
+                .await
+                .unwrap();
+        assert_eq!((report.typed, report.skipped), (13, 0));
+        assert!(!report.cancelled);
     }
 
     #[tokio::test]
@@ -160,22 +644,26 @@ mod tests {
         let cancellation_flag = Arc::new(AtomicBool::new(false));
 
         let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag).await;
-        assert!(result.is_ok());
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, false).await;
+        assert_eq!(result, Ok(PasteReport::default()));
     }
 
     #[tokio::test]
     #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
     #[cfg(not(tarpaulin))]
     async fn test_handle_paste_clipboard_error() {
-        let clipboard = MockClipboard::new_with_error("Clipboard access failed");
+        let clipboard = MockClipboard::new_with_error(ClipboardError::Read(
+            "Clipboard access failed".to_string(),
+        ));
         let keyboard_emulator = Arc::new(KeyboardEmulator::new().unwrap());
         let cancellation_flag = Arc::new(AtomicBool::new(false));
 
         let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag).await;
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Clipboard access failed");
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, false).await;
+        assert_eq!(
+            result.unwrap_err(),
+            PastaError::Clipboard(ClipboardError::Read("Clipboard access failed".to_string()))
+        );
     }
 
     #[tokio::test]
@@ -187,7 +675,7 @@ mod tests {
         let cancellation_flag = Arc::new(AtomicBool::new(true)); // Pre-cancelled
 
         let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag).await;
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, false).await;
         assert!(result.is_ok()); // Should complete but text might be cut short
     }
 
@@ -201,19 +689,616 @@ mod tests {
         let cancellation_flag = Arc::new(AtomicBool::new(false));
 
         let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag).await;
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, false).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
+    #[cfg(not(tarpaulin))]
+    async fn test_handle_type_text_with_content() {
+        let keyboard_emulator = Arc::new(KeyboardEmulator::new().unwrap());
+        let cancellation_flag = Arc::new(AtomicBool::new(false));
+
+        let result = handle_type_text(
+            "Hello\nWorld",
+            &keyboard_emulator,
+            cancellation_flag,
+            &PasteOptions::default(),
+        )
+        .await;
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_paste_cancelled_during_countdown_never_types() {
+        let clipboard = MockClipboard::new_with_content("secret");
+        let (keyboard_emulator, _) = crate::typing_harness::recording_keyboard();
+        let cancellation_flag = Arc::new(AtomicBool::new(false));
+        let flag = cancellation_flag.clone();
+        let countdown = Countdown::new(3, move |_| flag.store(true, Ordering::Relaxed));
+
+        let options = PasteOptions {
+            countdown,
+            ..PasteOptions::default()
+        };
+        let result = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            cancellation_flag,
+            &options,
+        )
+        .await;
+        assert_eq!(result, Ok(PasteReport::cancelled_before_typing(6)));
+        assert_eq!(keyboard_emulator.typing_state(), Default::default());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_paste_is_one_operation() {
+        let clipboard = MockClipboard::new_with_content("cell");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            repeat: Some(Repeat::new(3, "{TAB}")),
+            ..PasteOptions::default()
+        };
+
+        let report = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!((report.typed, report.skipped), (14, 0));
+        recording.assert_typed("cell\tcell\tcell");
+    }
+
+    #[tokio::test]
+    async fn test_long_paste_uses_the_shortcut_in_auto_mode() {
+        let clipboard = MockClipboard::new_with_content("long enough");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            paste_method: PasteMethod::Auto,
+            keystroke_threshold: 5,
+            ..PasteOptions::default()
+        };
+
+        let report = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.typed, 11);
+        recording.assert_typed("");
+        assert_eq!(recording.events().len(), 3);
+
+        // Below the threshold it is typed
+        let options = PasteOptions {
+            keystroke_threshold: 100,
+            ..options
+        };
+        handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        recording.assert_typed("long enough");
+    }
+
+    #[tokio::test]
+    async fn test_oversize_paste_is_aborted_or_truncated() {
+        let clipboard = MockClipboard::new_with_content("héllo wörld");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let options = PasteOptions {
+            max_chars: 5,
+            on_too_large: Some(Box::new(move |too_large| {
+                seen_clone.lock().unwrap().push(*too_large)
+            })),
+            ..PasteOptions::default()
+        };
+
+        let result = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await;
+        assert_eq!(result, Err(PastaError::TooLarge { chars: 11, max: 5 }));
+        assert!(recording.events().is_empty());
+
+        let options = PasteOptions {
+            oversize: OversizePaste::Truncate,
+            ..options
+        };
+        let report = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.typed, 5);
+        recording.assert_typed("héllo");
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                PasteTooLarge {
+                    chars: 11,
+                    max: 5,
+                    truncated: false,
+                },
+                PasteTooLarge {
+                    chars: 11,
+                    max: 5,
+                    truncated: true,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paste_limit_counts_chars_and_zero_is_unlimited() {
+        // Five characters, ten bytes
+        let clipboard = MockClipboard::new_with_content("ééééé");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+
+        for max_chars in [5, 0] {
+            let options = PasteOptions {
+                max_chars,
+                ..PasteOptions::default()
+            };
+            handle_paste_clipboard_with(
+                &clipboard,
+                &keyboard_emulator,
+                Arc::new(AtomicBool::new(false)),
+                &options,
+            )
+            .await
+            .unwrap();
+        }
+        recording.assert_typed("éééééééééé");
+    }
+
+    #[tokio::test]
+    async fn test_rewritten_paste_is_typed_not_pasted() {
+        let clipboard = MockClipboard::new_with_content("cell");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            repeat: Some(Repeat::new(2, "{TAB}")),
+            paste_method: PasteMethod::Keystroke,
+            ..PasteOptions::default()
+        };
+
+        handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        recording.assert_typed("cell\tcell");
+    }
+
+    #[tokio::test]
+    async fn test_paste_applies_whitespace_transforms() {
+        let clipboard = MockClipboard::new_with_content("name  \r\n\r\nvalue\r\n");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            transforms: TransformConfig {
+                trim_trailing_whitespace: true,
+                normalize_line_endings: true,
+                strip_final_newline: true,
+            },
+            ..PasteOptions::default()
+        };
+
+        let report = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        recording.assert_typed("name\n\nvalue");
+        assert!(report.transformed);
+
+        // Text the transforms leave as it is isn't reported as transformed
+        let clipboard = MockClipboard::new_with_content("value");
+        let report = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        assert!(!report.transformed);
+    }
+
+    #[tokio::test]
+    async fn test_busy_paste_is_rejected_before_countdown_and_hook() {
+        let clipboard = MockClipboard::new_with_content("second");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("hook_ran");
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticked = ticks.clone();
+        let options = PasteOptions {
+            countdown: Countdown::new(3, move |_| {
+                ticked.fetch_add(1, Ordering::Relaxed);
+            }),
+            hooks: PasteHooks {
+                pre: Some(format!("touch {}", marker.display())),
+                ..PasteHooks::default()
+            },
+            ..PasteOptions::default()
+        };
+
+        // Drive the tracker the way the worker does, as if a paste were typing
+        let tracker = keyboard_emulator.tracker();
+        tracker.start(20);
+        let result = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await;
+        tracker.finish(false);
+
+        assert_eq!(result, Err(PastaError::Busy));
+        assert_eq!(ticks.load(Ordering::Relaxed), 0);
+        assert!(!marker.exists());
+        assert!(recording.events().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_during_pre_paste_hook_types_nothing() {
+        let clipboard = MockClipboard::new_with_content("secret");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let cancellation_flag = Arc::new(AtomicBool::new(false));
+        let options = PasteOptions {
+            hooks: PasteHooks {
+                pre: Some("sleep 1".to_string()),
+                ..PasteHooks::default()
+            },
+            ..PasteOptions::default()
+        };
+
+        let flag = cancellation_flag.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            flag.store(true, Ordering::Relaxed);
+        });
+        let report = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            cancellation_flag,
+            &options,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report, PasteReport::cancelled_before_typing(6));
+        assert!(recording.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_secure_input_fails_the_paste_before_typing() {
+        let clipboard = MockClipboard::new_with_content("password");
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard_with_probe(
+            crate::typing_harness::SecureInputAfter::new(0, "Terminal"),
+        );
+
+        let result = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &PasteOptions::default(),
+        )
+        .await;
+        assert_eq!(
+            result,
+            Err(PastaError::SecureInputActive {
+                owner: Some("Terminal".to_string())
+            })
+        );
+        assert_eq!(recording.typed_text(), "");
+    }
+
+    /// Paste "first", then "second" while the first is still typing
+    async fn overlapping_pastes(
+        queue: bool,
+    ) -> (
+        Result<PasteReport, PastaError>,
+        Result<PasteReport, PastaError>,
+        String,
+    ) {
+        // Held after one character long enough for the second paste to arrive
+        let (keyboard_emulator, recording) =
+            crate::typing_harness::pausing_keyboard(1, Duration::from_millis(200));
+        let first = handle_paste_clipboard(
+            &MockClipboard::new_with_content("first"),
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            queue,
+        );
+        let second = async {
+            while !keyboard_emulator.typing_state().is_typing {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            handle_paste_clipboard(
+                &MockClipboard::new_with_content("second"),
+                &keyboard_emulator,
+                Arc::new(AtomicBool::new(false)),
+                queue,
+            )
+            .await
+        };
+        let (first, second) = tokio::join!(first, second);
+        (first, second, recording.typed_text())
+    }
+
+    #[tokio::test]
+    async fn test_paste_while_typing_is_busy() {
+        let (first, second, typed) = overlapping_pastes(false).await;
+        assert_eq!(first.unwrap().typed, 5);
+        assert_eq!(second, Err(PastaError::Busy));
+        assert_eq!(typed, "first");
+    }
+
+    #[tokio::test]
+    async fn test_paste_while_typing_queues_when_asked() {
+        let (first, second, typed) = overlapping_pastes(true).await;
+        assert_eq!(first.unwrap().typed, 5);
+        assert_eq!(second.unwrap().typed, 6);
+        assert_eq!(typed, "firstsecond");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_input_fails_the_paste() {
+        let clipboard = MockClipboard::new_with_content(&"x".repeat(50));
+        let (backend, recording) = crate::typing_harness::RecordingBackend::new();
+        let keyboard_emulator = crate::typing_harness::keyboard_with(backend.reject_from(5));
+
+        let result = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &PasteOptions::default(),
+        )
+        .await;
+        let error = result.unwrap_err();
+        assert_eq!(error, PastaError::Keyboard(TypingError::InputRejected));
+        assert!(error
+            .to_string()
+            .contains("rejected 10 keystrokes in a row"));
+        assert_eq!(recording.typed_text(), "xxxxx");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_repeated_paste_types_nothing() {
+        let clipboard = MockClipboard::new_with_content(&"a".repeat(60_000));
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            repeat: Some(Repeat::new(2, "")),
+            ..PasteOptions::default()
+        };
+
+        let result = handle_paste_clipboard_with(
+            &clipboard,
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(PastaError::TooLarge { chars: 120_000, .. })
+        ));
+        assert!(recording.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_handle_type_text_rejects_empty() {
+        let (keyboard_emulator, _) = crate::typing_harness::recording_keyboard();
+        let cancellation_flag = Arc::new(AtomicBool::new(false));
+
+        let result = handle_type_text(
+            "",
+            &keyboard_emulator,
+            cancellation_flag,
+            &PasteOptions::default(),
+        )
+        .await;
+        assert_eq!(result.unwrap_err(), PastaError::Empty);
+        assert_eq!(keyboard_emulator.typing_state().queue_len, 0);
+    }
+
+    /// Wait until everything queued on `keyboard_emulator` so far is typed
+    async fn typed_so_far(keyboard_emulator: &KeyboardEmulator) {
+        keyboard_emulator
+            .type_text("", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap()
+            .finished()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_type_text_applies_transforms() {
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            transforms: TransformConfig {
+                trim_trailing_whitespace: true,
+                normalize_line_endings: true,
+                strip_final_newline: true,
+            },
+            ..PasteOptions::default()
+        };
+
+        handle_type_text(
+            "name  \r\nvalue\r\n",
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        typed_so_far(&keyboard_emulator).await;
+        recording.assert_typed("name\nvalue");
+
+        let result = handle_type_text(
+            "  \n",
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await;
+        assert_eq!(result, Err(PastaError::Empty));
+    }
+
+    #[tokio::test]
+    async fn test_handle_type_text_is_held_to_max_chars() {
+        let (keyboard_emulator, recording) = crate::typing_harness::recording_keyboard();
+        let options = PasteOptions {
+            max_chars: 5,
+            ..PasteOptions::default()
+        };
+
+        let result = handle_type_text(
+            "héllo wörld",
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await;
+        assert_eq!(result, Err(PastaError::TooLarge { chars: 11, max: 5 }));
+        assert_eq!(keyboard_emulator.typing_state().queue_len, 0);
+
+        let options = PasteOptions {
+            oversize: OversizePaste::Truncate,
+            ..options
+        };
+        handle_type_text(
+            "héllo wörld",
+            &keyboard_emulator,
+            Arc::new(AtomicBool::new(false)),
+            &options,
+        )
+        .await
+        .unwrap();
+        typed_so_far(&keyboard_emulator).await;
+        recording.assert_typed("héllo");
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_update_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let config_manager = ConfigManager::with_path(path.clone());
+        let (keyboard_emulator, _) = crate::typing_harness::recording_keyboard();
+
+        let dto = ConfigDto {
+            typing_speed: "fast".to_string(),
+            settings_close_behavior: None,
+            theme: None,
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+        let Validated { config, errors } =
+            apply_config_update(&config_manager, &keyboard_emulator, dto, false)
+                .await
+                .unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(config.typing_speed, TypingSpeed::Fast);
+        assert_eq!(config_manager.get(), config);
+        assert_eq!(ConfigManager::with_path(path).get(), config);
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_update_rejects_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let config_manager = ConfigManager::with_path(path.clone());
+        let (keyboard_emulator, _) = crate::typing_harness::recording_keyboard();
+
+        let dto = ConfigDto {
+            typing_speed: "warp".to_string(),
+            settings_close_behavior: None,
+            theme: None,
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+        let errors = apply_config_update(&config_manager, &keyboard_emulator, dto, false)
+            .await
+            .unwrap_err();
+
+        assert_eq!(errors[0].path, "typingSpeed");
+        assert_eq!(config_manager.get(), Config::default());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_apply_config_update_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let config_manager = ConfigManager::with_path(path.clone());
+        let (keyboard_emulator, _) = crate::typing_harness::recording_keyboard();
+
+        let dto = ConfigDto {
+            typing_speed: "warp".to_string(),
+            settings_close_behavior: Some("destroy".to_string()),
+            theme: Some("sepia".to_string()),
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+        let Validated { config, errors } =
+            apply_config_update(&config_manager, &keyboard_emulator, dto, true)
+                .await
+                .unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path, "typingSpeed");
+        assert_eq!(errors[1].path, "theme");
+        assert_eq!(
+            config.settings_close_behavior,
+            SettingsCloseBehavior::Destroy
+        );
+        assert_eq!(config.typing_speed, TypingSpeed::Normal);
+        assert_eq!(ConfigManager::with_path(path).get(), config);
+    }
+
     #[test]
     fn test_create_menu_structure() {
-        let menu = create_menu_structure();
+        let menu = create_menu_structure(false);
 
         // Check structure
-        assert_eq!(menu.items.len(), 4); // paste, cancel_typing, separator, quit
+        // paste, cancel_typing, pause_typing, resume_typing, paste ×N, paste later,
+        // separator, paste method, launch at login, settings, quit
+        assert_eq!(menu.items.len(), 11);
 
         // Check paste item
-        if let MenuItem::Action { id, label } = &menu.items[0] {
+        if let MenuItem::Action { id, label, .. } = &menu.items[0] {
             assert_eq!(id, "paste");
             assert_eq!(label, "Paste");
         } else {
@@ -221,18 +1306,92 @@ mod tests {
         }
 
         // Check cancel typing item
-        if let MenuItem::Action { id, label } = &menu.items[1] {
+        if let MenuItem::Action { id, label, .. } = &menu.items[1] {
             assert_eq!(id, "cancel_typing");
             assert_eq!(label, "Cancel Typing");
         } else {
             panic!("Second item should be cancel_typing action");
         }
 
+        // Check pause and resume items
+        if let MenuItem::Action { id, label, .. } = &menu.items[2] {
+            assert_eq!(id, "pause_typing");
+            assert_eq!(label, "Pause Typing");
+        } else {
+            panic!("Third item should be pause_typing action");
+        }
+        if let MenuItem::Action { id, label, .. } = &menu.items[3] {
+            assert_eq!(id, "resume_typing");
+            assert_eq!(label, "Resume Typing");
+        } else {
+            panic!("Fourth item should be resume_typing action");
+        }
+
+        // Check the repeated paste presets
+        if let MenuItem::Submenu { label, items } = &menu.items[4] {
+            assert_eq!(label, "Paste ×N…");
+            let ids: Vec<&str> = items
+                .iter()
+                .filter_map(|item| match item {
+                    MenuItem::Action { id, .. } => Some(id.as_str()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(
+                ids,
+                ["paste_repeated_2", "paste_repeated_5", "paste_repeated_10"]
+            );
+        } else {
+            panic!("Fifth item should be the paste ×N submenu");
+        }
+
+        // Check the paste later presets
+        if let MenuItem::Submenu { label, items } = &menu.items[5] {
+            assert_eq!(label, "Paste later…");
+            assert_eq!(items.len(), 3);
+            assert!(matches!(&items[1], MenuItem::Action { id, .. } if id == "paste_later_30"));
+        } else {
+            panic!("Sixth item should be the paste later submenu");
+        }
+
         // Check separator
-        assert!(matches!(menu.items[2], MenuItem::Separator));
+        assert!(matches!(menu.items[6], MenuItem::Separator));
+
+        // Check the paste method choices, the current one ticked
+        if let MenuItem::Submenu { label, items } = &menu.items[7] {
+            assert_eq!(label, "Paste method");
+            assert!(matches!(
+                &items[0],
+                MenuItem::Action { id, label, .. } if id == "paste_method_type" && label == "✓ Type it out"
+            ));
+            assert!(matches!(
+                &items[2],
+                MenuItem::Action { label, .. } if label == "Shortcut for long text"
+            ));
+        } else {
+            panic!("Eighth item should be the paste method submenu");
+        }
+
+        // Check the launch at login toggle, off by default
+        assert_eq!(
+            menu.items[8],
+            MenuItem::Check {
+                id: "launch_at_login".to_string(),
+                label: "Launch at Login".to_string(),
+                checked: false,
+            }
+        );
+
+        // Check settings item
+        if let MenuItem::Action { id, label, .. } = &menu.items[9] {
+            assert_eq!(id, "settings");
+            assert_eq!(label, "Settings…");
+        } else {
+            panic!("Tenth item should be settings action");
+        }
 
         // Check quit item
-        if let MenuItem::Action { id, label } = &menu.items[3] {
+        if let MenuItem::Action { id, label, .. } = &menu.items[10] {
             assert_eq!(id, "quit");
             assert_eq!(label, "Quit");
         } else {
@@ -250,11 +1409,144 @@ mod tests {
         assert_eq!(handle_menu_event("cancel_typing"), MenuAction::CancelTyping);
     }
 
+    #[test]
+    fn test_handle_menu_event_pause_and_resume() {
+        assert_eq!(handle_menu_event("pause_typing"), MenuAction::PauseTyping);
+        assert_eq!(handle_menu_event("resume_typing"), MenuAction::ResumeTyping);
+    }
+
+    #[test]
+    fn test_handle_menu_event_settings() {
+        assert_eq!(handle_menu_event("settings"), MenuAction::OpenSettings);
+    }
+
     #[test]
     fn test_handle_menu_event_quit() {
         assert_eq!(handle_menu_event("quit"), MenuAction::Quit);
     }
 
+    #[test]
+    fn test_handle_menu_event_paste_repeated() {
+        assert_eq!(
+            handle_menu_event("paste_repeated_5"),
+            MenuAction::PasteRepeated(5)
+        );
+        assert_eq!(handle_menu_event("paste_repeated_"), MenuAction::None);
+        assert_eq!(handle_menu_event("paste_repeated_x"), MenuAction::None);
+    }
+
+    #[test]
+    fn test_handle_menu_event_paste_later() {
+        assert_eq!(
+            handle_menu_event("paste_later_30"),
+            MenuAction::PasteLater(30)
+        );
+        assert_eq!(
+            handle_menu_event("cancel_scheduled"),
+            MenuAction::CancelScheduled
+        );
+        assert_eq!(handle_menu_event("paste_later_soon"), MenuAction::None);
+    }
+
+    #[test]
+    fn test_launch_at_login_is_checked() {
+        let menu = menu_structure(false, false, PasteMethod::Type, true, &[]);
+        assert!(menu.items.iter().any(|item| matches!(
+            item,
+            MenuItem::Check { id, checked: true, .. } if id == "launch_at_login"
+        )));
+        assert_eq!(
+            handle_menu_event("launch_at_login"),
+            MenuAction::ToggleLaunchAtLogin
+        );
+    }
+
+    #[test]
+    fn test_handle_menu_event_paste_method() {
+        assert_eq!(
+            handle_menu_event("paste_method_keystroke"),
+            MenuAction::SetPasteMethod(PasteMethod::Keystroke)
+        );
+        assert_eq!(
+            handle_menu_event("paste_method_auto"),
+            MenuAction::SetPasteMethod(PasteMethod::Auto)
+        );
+        assert_eq!(handle_menu_event("paste_method_ctrl_v"), MenuAction::None);
+
+        let menu = menu_structure(false, false, PasteMethod::Auto, false, &[]);
+        let ticked: Vec<&str> = menu
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                MenuItem::Submenu { items, .. } => Some(items),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                MenuItem::Action { id, label, .. } if label.starts_with('✓') => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(ticked, ["paste_method_auto"]);
+    }
+
+    #[test]
+    fn test_snippets_submenu() {
+        assert!(!create_menu_structure(false)
+            .items
+            .iter()
+            .any(|item| matches!(item, MenuItem::Submenu { label, .. } if label == "Snippets")));
+
+        let snippets = ["sig".to_string(), "a \"quoted\" name".to_string()];
+        let menu = menu_structure(false, false, PasteMethod::Type, false, &snippets);
+        let items = menu
+            .items
+            .iter()
+            .find_map(|item| match item {
+                MenuItem::Submenu { label, items } if label == "Snippets" => Some(items),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            items[1],
+            MenuItem::Action {
+                id: "snippet_1".to_string(),
+                label: "a \"quoted\" name".to_string(),
+                enabled: true,
+            }
+        );
+
+        assert_eq!(handle_menu_event("snippet_1"), MenuAction::TypeSnippet(1));
+        assert_eq!(handle_menu_event("snippet_sig"), MenuAction::None);
+    }
+
+    #[test]
+    fn test_cancel_scheduled_only_while_pending() {
+        let has_cancel = |menu: MenuStructure| {
+            menu.items
+                .iter()
+                .any(|item| matches!(item, MenuItem::Action { id, .. } if id == "cancel_scheduled"))
+        };
+        assert!(!has_cancel(menu_structure(
+            false,
+            false,
+            PasteMethod::Type,
+            false,
+            &[]
+        )));
+        assert!(has_cancel(menu_structure(
+            true,
+            false,
+            PasteMethod::Type,
+            false,
+            &[]
+        )));
+        assert_eq!(
+            menu_structure(false, false, PasteMethod::Type, false, &[]),
+            create_menu_structure(false)
+        );
+    }
+
     #[test]
     fn test_handle_menu_event_unknown() {
         assert_eq!(handle_menu_event("unknown"), MenuAction::None);
@@ -263,7 +1555,7 @@ mod tests {
 
     #[test]
     fn test_menu_structure_all_items_present() {
-        let menu = create_menu_structure();
+        let menu = create_menu_structure(false);
 
         let mut has_paste = false;
         let mut has_cancel = false;
@@ -278,6 +1570,7 @@ mod tests {
                     "quit" => has_quit = true,
                     _ => {}
                 },
+                MenuItem::Submenu { .. } | MenuItem::Check { .. } => {}
                 MenuItem::Separator => has_separator = true,
             }
         }
@@ -290,7 +1583,7 @@ mod tests {
 
     #[test]
     fn test_menu_structure_has_cancel_typing() {
-        let menu = create_menu_structure();
+        let menu = create_menu_structure(false);
 
         let cancel_item = menu.items.iter().find(|item| {
             if let MenuItem::Action { id, .. } = item {
@@ -306,9 +1599,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cancel_typing_enabled_only_while_typing() {
+        let enabled = |menu: MenuStructure, wanted: &str| {
+            menu.items.iter().find_map(|item| match item {
+                MenuItem::Action { id, enabled, .. } if id == wanted => Some(*enabled),
+                _ => None,
+            })
+        };
+        assert_eq!(
+            enabled(create_menu_structure(false), "cancel_typing"),
+            Some(false)
+        );
+        assert_eq!(
+            enabled(create_menu_structure(true), "cancel_typing"),
+            Some(true)
+        );
+        assert_eq!(
+            enabled(
+                menu_structure(true, false, PasteMethod::Type, false, &[]),
+                "cancel_scheduled"
+            ),
+            Some(true)
+        );
+        assert_eq!(enabled(create_menu_structure(false), "paste"), Some(true));
+    }
+
     #[test]
     fn test_cancel_typing_menu_position() {
-        let menu = create_menu_structure();
+        let menu = create_menu_structure(false);
 
         // Cancel typing should be after paste and before separator
         if let MenuItem::Action { id, .. } = &menu.items[1] {
@@ -326,16 +1645,19 @@ mod tests {
 
     #[test]
     fn test_mock_clipboard_error() {
-        let clipboard = MockClipboard::new_with_error("Test error");
+        let clipboard = MockClipboard::new_with_error(ClipboardError::Read("Test error".into()));
         let result = clipboard.get_content();
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Test error");
+        assert_eq!(
+            result.unwrap_err(),
+            ClipboardError::Read("Test error".to_string())
+        );
     }
 
     #[test]
     fn test_menu_action_debug() {
         assert_eq!(format!("{:?}", MenuAction::Paste), "Paste");
         assert_eq!(format!("{:?}", MenuAction::CancelTyping), "CancelTyping");
+        assert_eq!(format!("{:?}", MenuAction::OpenSettings), "OpenSettings");
         assert_eq!(format!("{:?}", MenuAction::Quit), "Quit");
         assert_eq!(format!("{:?}", MenuAction::None), "None");
     }
@@ -345,6 +1667,7 @@ mod tests {
         let action = MenuItem::Action {
             id: "test".to_string(),
             label: "Test".to_string(),
+            enabled: true,
         };
         let debug_str = format!("{:?}", action);
         assert!(debug_str.contains("Action"));
@@ -357,7 +1680,7 @@ mod tests {
 
     #[test]
     fn test_menu_structure_debug() {
-        let menu = create_menu_structure();
+        let menu = create_menu_structure(false);
         let debug_str = format!("{:?}", menu);
         assert!(debug_str.contains("MenuStructure"));
         assert!(debug_str.contains("items"));
@@ -365,8 +1688,8 @@ mod tests {
 
     #[test]
     fn test_menu_structure_equality() {
-        let menu1 = create_menu_structure();
-        let menu2 = create_menu_structure();
+        let menu1 = create_menu_structure(false);
+        let menu2 = create_menu_structure(false);
         assert_eq!(menu1, menu2);
     }
 }
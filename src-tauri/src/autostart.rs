@@ -0,0 +1,225 @@
+/// Starting Pasta when the user logs in
+///
+/// Each platform gets a single login item pointing at the running executable:
+/// a LaunchAgent plist on macOS, a value under the `Run` key on Windows and
+/// an XDG autostart entry on Linux. Enabling writes it again and disabling
+/// removes it, so both can be repeated safely.
+use std::path::Path;
+
+/// Names the LaunchAgent, same as the bundle identifier
+#[cfg(any(target_os = "macos", test))]
+const LABEL: &str = "com.pasta.app";
+
+/// Add or remove the login item for the running executable
+///
+/// Errors are worded for the user, e.g. a read-only home directory.
+pub fn set_enabled(enabled: bool) -> Result<(), String> {
+    let exe =
+        std::env::current_exe().map_err(|e| format!("Couldn't find the Pasta executable: {e}"))?;
+    apply(enabled, &exe)
+}
+
+#[cfg(not(windows))]
+fn apply(enabled: bool, exe: &Path) -> Result<(), String> {
+    login_file(exe)
+        .ok_or_else(|| "No home directory to add a login item to".to_string())?
+        .apply(enabled)
+}
+
+#[cfg(target_os = "macos")]
+fn login_file(exe: &Path) -> Option<LoginFile> {
+    Some(LoginFile {
+        path: dirs::home_dir()?
+            .join("Library/LaunchAgents")
+            .join(format!("{LABEL}.plist")),
+        contents: launch_agent(exe),
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn login_file(exe: &Path) -> Option<LoginFile> {
+    Some(LoginFile {
+        path: dirs::config_dir()?.join("autostart/pasta.desktop"),
+        contents: desktop_entry(exe),
+    })
+}
+
+/// A file whose presence makes the system start Pasta at login
+#[cfg(any(not(windows), test))]
+struct LoginFile {
+    path: std::path::PathBuf,
+    contents: String,
+}
+
+#[cfg(any(not(windows), test))]
+impl LoginFile {
+    fn apply(&self, enabled: bool) -> Result<(), String> {
+        let failed = |e: std::io::Error| {
+            format!(
+                "Couldn't update the login item {}: {e}",
+                self.path.display()
+            )
+        };
+        if !enabled {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(failed(e)),
+                _ => Ok(()),
+            };
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(failed)?;
+        }
+        std::fs::write(&self.path, &self.contents).map_err(failed)
+    }
+}
+
+/// LaunchAgent that runs `exe` once at login
+#[cfg(any(target_os = "macos", test))]
+fn launch_agent(exe: &Path) -> String {
+    let exe = exe
+        .display()
+        .to_string()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#
+    )
+}
+
+/// XDG autostart entry that runs `exe`
+#[cfg(any(all(unix, not(target_os = "macos")), test))]
+fn desktop_entry(exe: &Path) -> String {
+    // Quoted as the Desktop Entry spec asks, `%` is a field code otherwise
+    let mut quoted = String::new();
+    for c in exe.display().to_string().chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => quoted.extend(['\\', c]),
+            '%' => quoted.push_str("%%"),
+            c => quoted.push(c),
+        }
+    }
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Pasta\n\
+         Comment=Clipboard to keyboard\n\
+         Exec=\"{quoted}\"\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n"
+    )
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{os::windows::process::CommandExt, process::Command};
+
+    use super::Path;
+
+    const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+    const VALUE: &str = "Pasta";
+    /// Keeps `reg` from flashing a console window
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    fn reg(args: &[&str]) -> Result<std::process::Output, String> {
+        Command::new("reg")
+            .args(args)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map_err(|e| format!("Couldn't run reg: {e}"))
+    }
+
+    pub fn apply(enabled: bool, exe: &Path) -> Result<(), String> {
+        let output = if enabled {
+            let command = format!("\"{}\"", exe.display());
+            reg(&[
+                "add", RUN_KEY, "/v", VALUE, "/t", "REG_SZ", "/d", &command, "/f",
+            ])?
+        } else {
+            // Deleting a value that isn't there fails
+            if !reg(&["query", RUN_KEY, "/v", VALUE])?.status.success() {
+                return Ok(());
+            }
+            reg(&["delete", RUN_KEY, "/v", VALUE, "/f"])?
+        };
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Couldn't update the login item: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
+
+#[cfg(windows)]
+use self::windows::apply;
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_login_file_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let file = LoginFile {
+            path: dir.path().join("autostart/pasta.desktop"),
+            contents: "entry".to_string(),
+        };
+
+        file.apply(true).unwrap();
+        file.apply(true).unwrap();
+        assert_eq!(std::fs::read_to_string(&file.path).unwrap(), "entry");
+
+        file.apply(false).unwrap();
+        assert!(!file.path.exists());
+        file.apply(false).unwrap();
+    }
+
+    #[test]
+    fn test_unwritable_login_file_is_an_error() {
+        let dir = tempdir().unwrap();
+        // A file where the directory should be, which not even root can write into
+        let blocker = dir.path().join("autostart");
+        std::fs::write(&blocker, "").unwrap();
+        let file = LoginFile {
+            path: blocker.join("pasta.desktop"),
+            contents: "entry".to_string(),
+        };
+
+        let error = file.apply(true).unwrap_err();
+        assert!(error.contains("pasta.desktop"), "{error}");
+    }
+
+    #[test]
+    fn test_launch_agent_escapes_the_path() {
+        let plist = launch_agent(Path::new("/Applications/A&B <Pasta>.app/pasta"));
+        assert!(plist.contains("<string>/Applications/A&amp;B &lt;Pasta&gt;.app/pasta</string>"));
+        assert!(plist.contains("<string>com.pasta.app</string>"));
+        assert!(plist.contains("<key>RunAtLoad</key>\n    <true/>"));
+    }
+
+    #[test]
+    fn test_desktop_entry_quotes_the_path() {
+        let entry = desktop_entry(Path::new("/opt/my $apps/100%/pasta"));
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Exec=\"/opt/my \\$apps/100%%/pasta\"\n"));
+    }
+}
@@ -0,0 +1,392 @@
+/// Command line verbs, forwarded to the running instance
+///
+/// `pasta paste`, `pasta cancel`, `pasta status [--json]`, `pasta quit` and
+/// `pasta type --stdin | --text <text>`. Verbs travel as launch arguments over
+/// the single-instance socket; the reply is a JSON `CliReply` line.
+use std::{
+    io::Read,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_logic::{self, PasteOptions},
+    config::ConfigManager,
+    error::PastaError,
+    keyboard::KeyboardEmulator,
+    single_instance::MAX_MESSAGE_BYTES,
+    typing_state::TypingState,
+};
+
+pub const USAGE: &str =
+    "Usage: pasta [--headless | paste | cancel | status [--json] | quit | type (--stdin | --text <text>)]";
+
+/// Launch flag that starts Pasta without the tray and windows
+pub const HEADLESS_FLAG: &str = "--headless";
+
+/// Exit codes of the command line verbs
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_ERROR: i32 = 1;
+pub const EXIT_BUSY: i32 = 2;
+
+/// A verb given on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    Paste,
+    Cancel,
+    Status {
+        json: bool,
+    },
+    Quit,
+    /// `--stdin` is resolved to the text before forwarding
+    Type {
+        source: TextSource,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextSource {
+    Stdin,
+    Text(String),
+}
+
+/// Whether a plain launch asked for headless mode
+pub fn headless_requested(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == HEADLESS_FLAG)
+}
+
+/// Parse launch arguments, `Ok(None)` for a plain launch without a verb
+pub fn parse_args(args: &[String]) -> Result<Option<CliCommand>, String> {
+    let Some((verb, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+    // Flags without a verb are for the app itself (or the OS), not for us
+    if verb.starts_with('-') {
+        return Ok(None);
+    }
+
+    let rest: Vec<&str> = rest.iter().map(String::as_str).collect();
+    let command = match (verb.as_str(), rest.as_slice()) {
+        ("paste", []) => CliCommand::Paste,
+        ("cancel", []) => CliCommand::Cancel,
+        ("status", []) => CliCommand::Status { json: false },
+        ("status", ["--json"]) => CliCommand::Status { json: true },
+        ("quit", []) => CliCommand::Quit,
+        ("type", ["--stdin"]) => CliCommand::Type {
+            source: TextSource::Stdin,
+        },
+        ("type", ["--text", text]) => CliCommand::Type {
+            source: TextSource::Text(text.to_string()),
+        },
+        _ => {
+            return Err(format!(
+                "Unrecognized arguments: {}\n{USAGE}",
+                args.join(" ")
+            ))
+        }
+    };
+    Ok(Some(command))
+}
+
+/// Arguments that reproduce `command`, with stdin already read into `--text`
+pub fn to_args(command: &CliCommand) -> Vec<String> {
+    let args: &[&str] = match command {
+        CliCommand::Paste => &["paste"],
+        CliCommand::Cancel => &["cancel"],
+        CliCommand::Status { json: false } => &["status"],
+        CliCommand::Status { json: true } => &["status", "--json"],
+        CliCommand::Quit => &["quit"],
+        CliCommand::Type {
+            source: TextSource::Text(text),
+        } => return vec!["type".to_string(), "--text".to_string(), text.clone()],
+        CliCommand::Type {
+            source: TextSource::Stdin,
+        } => &["type", "--stdin"],
+    };
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Replace `--stdin` with the text read from standard input
+pub fn read_stdin(command: CliCommand) -> Result<CliCommand, String> {
+    if command
+        != (CliCommand::Type {
+            source: TextSource::Stdin,
+        })
+    {
+        return Ok(command);
+    }
+
+    let mut text = String::new();
+    std::io::stdin()
+        .take(MAX_MESSAGE_BYTES + 1)
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read standard input: {e}"))?;
+    if text.len() as u64 > MAX_MESSAGE_BYTES {
+        return Err(format!(
+            "Standard input is larger than {MAX_MESSAGE_BYTES} bytes"
+        ));
+    }
+    Ok(CliCommand::Type {
+        source: TextSource::Text(text),
+    })
+}
+
+/// Run a verb without a running instance
+///
+/// Only `type` works headless; the other verbs need the app's state.
+pub fn run_headless(command: &CliCommand) -> CliReply {
+    match command {
+        CliCommand::Type {
+            source: TextSource::Text(text),
+        } => type_headless(text),
+        _ => CliReply::error("Pasta is not running"),
+    }
+}
+
+/// Type `text` with a one-shot keyboard worker and wait until it is done
+fn type_headless(text: &str) -> CliReply {
+    let keyboard = match KeyboardEmulator::new() {
+        Ok(keyboard) => Arc::new(keyboard),
+        Err(e) => return CliReply::error(format!("Failed to initialize keyboard: {e}")),
+    };
+    let config = ConfigManager::new()
+        .map(|config_manager| config_manager.get())
+        .unwrap_or_default();
+
+    let result = tauri::async_runtime::block_on(async {
+        keyboard.set_typing_speed(config.typing_speed);
+        keyboard.set_custom_delay(config.typing_delay_ms);
+        keyboard
+            .set_typing_mode(config.typing_mode, config.line_options())
+            .await?;
+        let options = PasteOptions {
+            transforms: config.transforms(),
+            max_chars: usize::try_from(config.max_paste_chars).unwrap_or(usize::MAX),
+            oversize: config.oversize_paste,
+            ..PasteOptions::default()
+        };
+        app_logic::handle_type_text(text, &keyboard, Arc::new(AtomicBool::new(false)), &options)
+            .await
+    });
+    if result.is_ok() {
+        let busy = |state: TypingState| state.is_typing || state.queue_len > 0;
+        while busy(keyboard.tracker().snapshot()) {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+    CliReply::from_result(result, "Typed text")
+}
+
+/// How a forwarded verb went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CliOutcome {
+    Ok,
+    Busy,
+    Error,
+}
+
+/// Reply the running instance sends for a verb
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliReply {
+    pub outcome: CliOutcome,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<TypingState>,
+}
+
+impl CliReply {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            outcome: CliOutcome::Ok,
+            message: message.into(),
+            state: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            outcome: CliOutcome::Error,
+            message: message.into(),
+            state: None,
+        }
+    }
+
+    /// Reply for a verb that went through the guarded paste paths
+    pub fn from_result(result: Result<(), PastaError>, success: &str) -> Self {
+        match result {
+            Ok(()) => Self::ok(success),
+            Err(e @ PastaError::Busy) => Self {
+                outcome: CliOutcome::Busy,
+                message: e.to_string(),
+                state: None,
+            },
+            Err(e) => Self::error(e.to_string()),
+        }
+    }
+
+    /// Reply for `status`
+    pub fn status(state: TypingState) -> Self {
+        let message = if state.is_typing {
+            format!("Typing {} / {} characters", state.typed, state.total)
+        } else if state.queue_len > 0 {
+            format!("{} operations queued", state.queue_len)
+        } else {
+            "Idle".to_string()
+        };
+        Self {
+            state: Some(state),
+            ..Self::ok(message)
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Decode a reply line; anything else is reported as an error message
+    pub fn decode(line: &str) -> Self {
+        serde_json::from_str(line).unwrap_or_else(|_| Self::error(line))
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self.outcome {
+            CliOutcome::Ok => EXIT_OK,
+            CliOutcome::Busy => EXIT_BUSY,
+            CliOutcome::Error => EXIT_ERROR,
+        }
+    }
+
+    /// What the command line prints for this reply
+    pub fn render(&self, command: &CliCommand) -> String {
+        match (command, &self.state) {
+            (CliCommand::Status { json: true }, Some(state)) => {
+                serde_json::to_string(state).unwrap_or_default()
+            }
+            _ => self.message.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_verbs() {
+        assert_eq!(parse_args(&[]), Ok(None));
+        assert_eq!(parse_args(&args(&["paste"])), Ok(Some(CliCommand::Paste)));
+        assert_eq!(parse_args(&args(&["cancel"])), Ok(Some(CliCommand::Cancel)));
+        assert_eq!(parse_args(&args(&["quit"])), Ok(Some(CliCommand::Quit)));
+        assert_eq!(
+            parse_args(&args(&["status", "--json"])),
+            Ok(Some(CliCommand::Status { json: true }))
+        );
+        assert_eq!(
+            parse_args(&args(&["type", "--stdin"])),
+            Ok(Some(CliCommand::Type {
+                source: TextSource::Stdin
+            }))
+        );
+        assert_eq!(
+            parse_args(&args(&["type", "--text", "hi there"])),
+            Ok(Some(CliCommand::Type {
+                source: TextSource::Text("hi there".to_string())
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_arguments() {
+        assert!(parse_args(&args(&["paste", "--now"])).is_err());
+        assert!(parse_args(&args(&["type"])).is_err());
+        assert!(parse_args(&args(&["frobnicate"]))
+            .unwrap_err()
+            .contains(USAGE));
+    }
+
+    #[test]
+    fn test_plain_flags_are_not_verbs() {
+        assert_eq!(parse_args(&args(&["--some-os-flag"])), Ok(None));
+        assert_eq!(parse_args(&args(&[HEADLESS_FLAG])), Ok(None));
+    }
+
+    #[test]
+    fn test_headless_flag() {
+        assert!(headless_requested(&args(&[HEADLESS_FLAG])));
+        assert!(headless_requested(&args(&[
+            "--some-os-flag",
+            HEADLESS_FLAG
+        ])));
+        assert!(!headless_requested(&args(&[])));
+        assert!(!headless_requested(&args(&["status"])));
+    }
+
+    #[test]
+    fn test_args_round_trip() {
+        for command in [
+            CliCommand::Paste,
+            CliCommand::Cancel,
+            CliCommand::Status { json: false },
+            CliCommand::Status { json: true },
+            CliCommand::Quit,
+            CliCommand::Type {
+                source: TextSource::Text("a\nb".to_string()),
+            },
+        ] {
+            assert_eq!(parse_args(&to_args(&command)), Ok(Some(command)));
+        }
+    }
+
+    #[test]
+    fn test_reply_round_trip_and_exit_codes() {
+        let busy = CliReply::from_result(Err(PastaError::Busy), "Pasting");
+        let decoded = CliReply::decode(&busy.encode());
+        assert_eq!(decoded, busy);
+        assert_eq!(decoded.exit_code(), EXIT_BUSY);
+
+        assert_eq!(
+            CliReply::from_result(Ok(()), "Pasting").exit_code(),
+            EXIT_OK
+        );
+        assert_eq!(
+            CliReply::from_result(Err(PastaError::Empty), "Pasting").exit_code(),
+            EXIT_ERROR
+        );
+    }
+
+    #[test]
+    fn test_unparseable_reply_is_an_error() {
+        let reply = CliReply::decode("Opened settings in the running instance");
+        assert_eq!(reply.outcome, CliOutcome::Error);
+        assert_eq!(reply.message, "Opened settings in the running instance");
+    }
+
+    #[test]
+    fn test_status_json_output() {
+        let state = TypingState {
+            is_typing: true,
+            typed: 3,
+            total: 10,
+            ..TypingState::default()
+        };
+        let reply = CliReply::decode(&CliReply::status(state).encode());
+        assert_eq!(reply.message, "Typing 3 / 10 characters");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&reply.render(&CliCommand::Status { json: true })).unwrap();
+        assert_eq!(json["isTyping"], true);
+        assert_eq!(json["typed"], 3);
+        assert_eq!(
+            reply.render(&CliCommand::Status { json: false }),
+            reply.message
+        );
+    }
+}
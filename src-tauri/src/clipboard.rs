@@ -1,11 +1,13 @@
 use arboard::Clipboard;
 use log::error;
 
+use crate::error::ClipboardError;
+
 /// Get the current clipboard content as text
-pub fn get_clipboard_content() -> Result<Option<String>, String> {
+pub fn get_clipboard_content() -> Result<Option<String>, ClipboardError> {
     let mut clipboard = match Clipboard::new() {
         Ok(c) => c,
-        Err(e) => return Err(format!("Failed to create clipboard: {e}")),
+        Err(e) => return Err(ClipboardError::Unavailable(e.to_string())),
     };
 
     match clipboard.get_text() {
@@ -18,7 +20,7 @@ pub fn get_clipboard_content() -> Result<Option<String>, String> {
         }
         Err(e) => {
             error!("Failed to read clipboard: {e:?}");
-            Err(format!("Failed to read clipboard: {e}"))
+            Err(ClipboardError::Read(e.to_string()))
         }
     }
 }
@@ -127,11 +129,11 @@ mod tests {
     #[test]
     fn test_error_string_formatting() {
         // Test error message formatting
-        let error_msg = format!("Failed to create clipboard: {}", "test error");
+        let error_msg = ClipboardError::Unavailable("test error".to_string()).to_string();
         assert!(error_msg.contains("Failed to create clipboard"));
         assert!(error_msg.contains("test error"));
 
-        let error_msg2 = format!("Failed to read clipboard: {}", "another error");
+        let error_msg2 = ClipboardError::Read("another error".to_string()).to_string();
         assert!(error_msg2.contains("Failed to read clipboard"));
         assert!(error_msg2.contains("another error"));
     }
@@ -1,25 +1,25 @@
 #[cfg(test)]
 mod clipboard_error_tests {
     // Testing clipboard error scenarios without direct clipboard access
+    use crate::error::{ClipboardError, PastaError};
 
     #[test]
     fn test_clipboard_error_messages() {
         // Test error message formatting for clipboard operations
 
         // Test clipboard creation error message
-        let create_error = format!("Failed to create clipboard: {}", "Permission denied");
-        assert!(create_error.contains("Failed to create clipboard"));
-        assert!(create_error.contains("Permission denied"));
+        let create_error = ClipboardError::Unavailable("Permission denied".to_string());
+        assert_eq!(
+            create_error.to_string(),
+            "Failed to create clipboard: Permission denied"
+        );
 
         // Test clipboard read error message
-        let read_error = format!("Failed to read clipboard: {}", "Invalid format");
-        assert!(read_error.contains("Failed to read clipboard"));
-        assert!(read_error.contains("Invalid format"));
-
-        // Test empty clipboard error message
-        let empty_error = format!("Failed to read clipboard: {}", "Clipboard is empty");
-        assert!(empty_error.contains("Failed to read clipboard"));
-        assert!(empty_error.contains("Clipboard is empty"));
+        let read_error = ClipboardError::Read("Invalid format".to_string());
+        assert_eq!(
+            read_error.to_string(),
+            "Failed to read clipboard: Invalid format"
+        );
     }
 
     #[test]
@@ -27,17 +27,17 @@ mod clipboard_error_tests {
         // Test different error result types that clipboard operations can return
 
         // Test error result type
-        let error_result: Result<Option<String>, String> = Err("Test error".to_string());
-        assert!(error_result.is_err());
-        assert_eq!(error_result.unwrap_err(), "Test error");
+        let error_result: Result<Option<String>, ClipboardError> =
+            Err(ClipboardError::Read("Test error".to_string()));
+        assert!(matches!(error_result, Err(ClipboardError::Read(_))));
 
         // Test success with None
-        let none_result: Result<Option<String>, String> = Ok(None);
+        let none_result: Result<Option<String>, ClipboardError> = Ok(None);
         assert!(none_result.is_ok());
         assert!(none_result.unwrap().is_none());
 
         // Test success with Some
-        let some_result: Result<Option<String>, String> = Ok(Some("content".to_string()));
+        let some_result: Result<Option<String>, ClipboardError> = Ok(Some("content".to_string()));
         assert!(some_result.is_ok());
         assert_eq!(some_result.unwrap(), Some("content".to_string()));
     }
@@ -66,17 +66,22 @@ mod clipboard_error_tests {
     fn test_clipboard_error_propagation() {
         // Test error propagation patterns
 
-        fn mock_clipboard_operation() -> Result<Option<String>, String> {
-            Err("Mock error".to_string())
+        fn mock_clipboard_operation() -> Result<Option<String>, ClipboardError> {
+            Err(ClipboardError::Unavailable("Mock error".to_string()))
         }
 
-        let result = mock_clipboard_operation();
-        assert!(result.is_err());
+        fn mock_paste() -> Result<(), PastaError> {
+            mock_clipboard_operation()?;
+            Ok(())
+        }
 
-        // Test error mapping
-        let mapped_error = result.map_err(|e| format!("Wrapped: {}", e));
-        assert!(mapped_error.is_err());
-        assert!(mapped_error.unwrap_err().contains("Wrapped"));
+        // The clipboard error is wrapped, not flattened into a string
+        assert_eq!(
+            mock_paste(),
+            Err(PastaError::Clipboard(ClipboardError::Unavailable(
+                "Mock error".to_string()
+            )))
+        );
     }
 
     #[test]
@@ -84,16 +89,17 @@ mod clipboard_error_tests {
         // Test distinction between empty clipboard and error
 
         // Empty clipboard should return Ok(None)
-        let empty_clipboard: Result<Option<String>, String> = Ok(None);
+        let empty_clipboard: Result<Option<String>, ClipboardError> = Ok(None);
         assert!(empty_clipboard.is_ok());
         assert!(empty_clipboard.unwrap().is_none());
 
         // Error should return Err
-        let error_clipboard: Result<Option<String>, String> = Err("Error".to_string());
+        let error_clipboard: Result<Option<String>, ClipboardError> =
+            Err(ClipboardError::Read("Error".to_string()));
         assert!(error_clipboard.is_err());
 
         // Empty string should return Ok(None) based on the implementation
-        let empty_string_result: Result<Option<String>, String> = if "".is_empty() {
+        let empty_string_result: Result<Option<String>, ClipboardError> = if "".is_empty() {
             Ok(None)
         } else {
             Ok(Some("".to_string()))
@@ -109,21 +115,21 @@ mod clipboard_error_tests {
         #[cfg(target_os = "macos")]
         {
             let macos_error = "NSPasteboardCommunicationError";
-            let error_msg = format!("Failed to read clipboard: {}", macos_error);
+            let error_msg = ClipboardError::Read(macos_error.to_string()).to_string();
             assert!(error_msg.contains("NSPasteboardCommunicationError"));
         }
 
         #[cfg(target_os = "windows")]
         {
             let windows_error = "OpenClipboard failed";
-            let error_msg = format!("Failed to read clipboard: {}", windows_error);
+            let error_msg = ClipboardError::Read(windows_error.to_string()).to_string();
             assert!(error_msg.contains("OpenClipboard failed"));
         }
 
         #[cfg(target_os = "linux")]
         {
             let linux_error = "X11 connection error";
-            let error_msg = format!("Failed to read clipboard: {}", linux_error);
+            let error_msg = ClipboardError::Read(linux_error.to_string()).to_string();
             assert!(error_msg.contains("X11 connection error"));
         }
     }
@@ -1,15 +1,16 @@
 #[cfg(test)]
 mod clipboard_mock_tests {
+    use crate::error::ClipboardError;
 
     #[test]
     fn test_clipboard_error_formatting() {
         // Test error message formatting for clipboard creation failure
-        let error_msg = format!("Failed to create clipboard: {}", "Test error");
+        let error_msg = ClipboardError::Unavailable("Test error".to_string()).to_string();
         assert!(error_msg.contains("Failed to create clipboard"));
         assert!(error_msg.contains("Test error"));
 
         // Test error message formatting for clipboard read failure
-        let read_error = format!("Failed to read clipboard: {}", "Read error");
+        let read_error = ClipboardError::Read("Read error".to_string()).to_string();
         assert!(read_error.contains("Failed to read clipboard"));
         assert!(read_error.contains("Read error"));
     }
@@ -35,19 +36,23 @@ mod clipboard_mock_tests {
         // Test the different result patterns that get_clipboard_content can return
 
         // Success with content
-        let success_with_content: Result<Option<String>, String> = Ok(Some("content".to_string()));
+        let success_with_content: Result<Option<String>, ClipboardError> =
+            Ok(Some("content".to_string()));
         assert!(success_with_content.is_ok());
         assert_eq!(success_with_content.unwrap(), Some("content".to_string()));
 
         // Success with no content
-        let success_no_content: Result<Option<String>, String> = Ok(None);
+        let success_no_content: Result<Option<String>, ClipboardError> = Ok(None);
         assert!(success_no_content.is_ok());
         assert_eq!(success_no_content.unwrap(), None);
 
         // Error case
-        let error_case: Result<Option<String>, String> = Err("Error".to_string());
-        assert!(error_case.is_err());
-        assert_eq!(error_case.unwrap_err(), "Error");
+        let error_case: Result<Option<String>, ClipboardError> =
+            Err(ClipboardError::Read("Error".to_string()));
+        assert_eq!(
+            error_case.unwrap_err(),
+            ClipboardError::Read("Error".to_string())
+        );
     }
 
     #[test]
@@ -0,0 +1,845 @@
+/// User configuration persisted to `config.toml`
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+pub use self::validate::{FieldError, Validated};
+use crate::{
+    debounce::DEFAULT_PASTE_DEBOUNCE_MS,
+    error::StorageError,
+    hooks::HookFailure,
+    http_api::HttpApiConfig,
+    keyboard::{
+        Chunking, LineOptions, PasteMethod, TypingMode, TypingSpeed, DEFAULT_CHUNK_DELAY_MS,
+        DEFAULT_CHUNK_SIZE, DEFAULT_KEYSTROKE_THRESHOLD,
+    },
+    logging::LogLevel,
+    theme::ThemePreference,
+    transform::TransformConfig,
+};
+
+pub mod validate;
+
+/// Settings persisted between launches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub typing_speed: TypingSpeed,
+    /// Milliseconds between keystrokes, overriding `typing_speed` when set
+    pub typing_delay_ms: Option<u64>,
+    /// Type everything at once or a line at a time
+    pub typing_mode: TypingMode,
+    /// Wait after each line in `line_by_line` mode
+    pub line_delay_ms: u64,
+    /// Press Return after the last line in `line_by_line` mode
+    pub final_newline: bool,
+    /// Characters typed between pauses, smaller for slow remote sessions
+    pub chunk_size: usize,
+    /// Pause between chunks
+    pub chunk_delay_ms: u64,
+    /// Percent each key delay randomly varies by, for targets that drop
+    /// perfectly even input, 0 for none
+    pub jitter_percent: u8,
+    /// Type pastes, or press the platform paste shortcut for them
+    pub paste_method: PasteMethod,
+    /// Characters above which `paste_method = "auto"` uses the shortcut
+    pub paste_keystroke_threshold: u64,
+    /// Longest clipboard a paste types, in characters, 0 for no limit
+    pub max_paste_chars: u64,
+    /// Whether a longer clipboard is dropped or cut to `max_paste_chars`
+    pub oversize_paste: OversizePaste,
+    /// What closing the settings window does
+    pub settings_close_behavior: SettingsCloseBehavior,
+    /// Theme override for Pasta's windows
+    pub theme: ThemePreference,
+    /// Queue pastes triggered while typing instead of rejecting them
+    pub queue_pastes: bool,
+    /// Seconds to wait before typing a paste, to focus the target field
+    pub paste_delay_secs: u64,
+    /// Repeats of the same paste within this window are dropped, 0 to allow all
+    pub paste_debounce_ms: u64,
+    /// Drop spaces and tabs at the end of every pasted line
+    pub trim_trailing_whitespace: bool,
+    /// Type `\r\n` and lone `\r` in pastes as `\n`
+    pub normalize_line_endings: bool,
+    /// Drop the line break at the end of a paste
+    pub strip_final_newline: bool,
+    /// Accept JSON control commands on the instance socket
+    pub control_socket: bool,
+    /// Also write logs to a rotating `pasta.log` in the app data dir
+    pub log_to_file: bool,
+    pub log_level: LogLevel,
+    /// Type even when the target window is elevated (Windows)
+    pub skip_elevation_check: bool,
+    /// Run without the tray and windows, like `--headless`
+    pub headless: bool,
+    /// Shell command run before typing a paste
+    pub pre_paste_command: Option<String>,
+    /// Shell command run after a paste, with `PASTA_CHARS` and `PASTA_RESULT`
+    pub post_paste_command: Option<String>,
+    /// Whether a failing `pre_paste_command` drops the paste
+    pub pre_paste_failure: HookFailure,
+    /// Check GitHub for a newer release once a week
+    pub update_check: bool,
+    /// Play a short sound when typing starts, finishes, is cancelled or fails
+    pub sound_feedback: bool,
+    /// Start Pasta when the user logs in
+    pub launch_at_login: bool,
+    /// Show what a paste would type and wait for Type or Cancel
+    pub confirm_before_typing: bool,
+    /// Cancel typing when the user presses a key or clicks (Windows, macOS)
+    pub abort_on_user_input: bool,
+    /// Text typed by name from the tray or `type_snippet`, listed by name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub snippets: BTreeMap<String, String>,
+    /// Opt-in localhost endpoint for triggering pastes over HTTP
+    pub http_api: HttpApiConfig,
+    /// Last settings window geometry, restored when the window is created
+    /// Kept last because TOML tables must follow plain values
+    pub window_state: Option<WindowState>,
+}
+
+impl Config {
+    /// Pacing of `TypingMode::LineByLine`
+    pub fn line_options(&self) -> LineOptions {
+        LineOptions {
+            line_delay: Duration::from_millis(self.line_delay_ms),
+            final_newline: self.final_newline,
+        }
+    }
+
+    /// How long texts are split up while typing
+    pub fn chunking(&self) -> Chunking {
+        Chunking {
+            size: self.chunk_size,
+            pause: Duration::from_millis(self.chunk_delay_ms),
+        }
+    }
+
+    /// Whitespace clean-up applied to pastes
+    pub fn transforms(&self) -> TransformConfig {
+        TransformConfig {
+            trim_trailing_whitespace: self.trim_trailing_whitespace,
+            normalize_line_endings: self.normalize_line_endings,
+            strip_final_newline: self.strip_final_newline,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            typing_speed: TypingSpeed::default(),
+            typing_delay_ms: None,
+            typing_mode: TypingMode::default(),
+            line_delay_ms: 500,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            chunk_delay_ms: DEFAULT_CHUNK_DELAY_MS,
+            jitter_percent: 0,
+            final_newline: true,
+            paste_method: PasteMethod::default(),
+            paste_keystroke_threshold: DEFAULT_KEYSTROKE_THRESHOLD,
+            max_paste_chars: DEFAULT_MAX_PASTE_CHARS,
+            oversize_paste: OversizePaste::default(),
+            settings_close_behavior: SettingsCloseBehavior::default(),
+            theme: ThemePreference::default(),
+            queue_pastes: false,
+            paste_delay_secs: 0,
+            paste_debounce_ms: DEFAULT_PASTE_DEBOUNCE_MS,
+            trim_trailing_whitespace: false,
+            normalize_line_endings: false,
+            strip_final_newline: false,
+            control_socket: false,
+            log_to_file: false,
+            log_level: LogLevel::default(),
+            skip_elevation_check: false,
+            headless: false,
+            pre_paste_command: None,
+            post_paste_command: None,
+            pre_paste_failure: HookFailure::default(),
+            update_check: false,
+            sound_feedback: false,
+            launch_at_login: false,
+            confirm_before_typing: false,
+            abort_on_user_input: false,
+            snippets: BTreeMap::new(),
+            http_api: HttpApiConfig::default(),
+            window_state: None,
+        }
+    }
+}
+
+/// What a paste longer than `max_paste_chars` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizePaste {
+    /// Type nothing and say why
+    #[default]
+    Abort,
+    /// Type the first `max_paste_chars` characters
+    Truncate,
+}
+
+/// What happens when the settings window is closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingsCloseBehavior {
+    /// Keep the webview around so reopening is instant
+    #[default]
+    Hide,
+    /// Free the webview; it is recreated on next open
+    Destroy,
+}
+
+/// Outer position and size of a window in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Characters a paste types at most unless `max_paste_chars` says otherwise
+pub const DEFAULT_MAX_PASTE_CHARS: u64 = 100_000;
+/// Largest `max_paste_chars` accepted, other than 0 for no limit
+pub const MAX_PASTE_CHARS_LIMIT: u64 = 1_000_000_000;
+
+/// How often `ConfigManager::start_watching` looks for hand edits
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Owns the in-memory config and the file it is persisted to
+pub struct ConfigManager {
+    config: Mutex<Config>,
+    config_path: PathBuf,
+    /// True until a config file exists, i.e. Pasta has never saved settings
+    first_run: AtomicBool,
+}
+
+impl ConfigManager {
+    /// Create a manager backed by `<config dir>/pasta/config.toml`
+    pub fn new() -> Result<Self, StorageError> {
+        let config_dir = dirs::config_dir()
+            .ok_or(StorageError::NoConfigDir)?
+            .join("pasta");
+        Ok(Self::with_path(config_dir.join("config.toml")))
+    }
+
+    /// Create a manager backed by a specific file, loading it if it exists
+    pub fn with_path(config_path: PathBuf) -> Self {
+        let first_run = !config_path.exists();
+        let config = match Self::load_from(&config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!(
+                    "Failed to load config from {}: {e}, using defaults",
+                    config_path.display()
+                );
+                Config::default()
+            }
+        };
+
+        Self {
+            config: Mutex::new(config),
+            config_path,
+            first_run: AtomicBool::new(first_run),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Config, StorageError> {
+        if !path.exists() {
+            info!("No config file at {}, using defaults", path.display());
+            return Ok(Config::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        let Validated { config, errors } = validate::validate_toml(&contents)?;
+        for error in &errors {
+            warn!("Ignoring invalid setting in {}: {error}", path.display());
+        }
+        Ok(config)
+    }
+
+    /// Get a snapshot of the current config
+    pub fn get(&self) -> Config {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Replace the current config and persist it
+    pub fn set(&self, config: Config) -> Result<(), StorageError> {
+        Self::write_to(&self.config_path, &config)?;
+        self.first_run.store(false, Ordering::SeqCst);
+        *self.config.lock().unwrap() = config;
+        Ok(())
+    }
+
+    /// Modify the current config in place and persist it
+    pub fn update<F: FnOnce(&mut Config)>(&self, f: F) -> Result<(), StorageError> {
+        let mut config = self.get();
+        f(&mut config);
+        self.set(config)
+    }
+
+    /// Persist the current config
+    pub fn save(&self) -> Result<(), StorageError> {
+        Self::write_to(&self.config_path, &self.get())?;
+        self.first_run.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Re-read the file, e.g. after it was edited by hand
+    ///
+    /// A file that can't be read or parsed, or has rejected settings, is
+    /// logged and leaves the current config alone. Returns the new config if
+    /// anything changed.
+    pub fn reload(&self) -> Option<Config> {
+        match fs::read_to_string(&self.config_path) {
+            Ok(contents) => self.reload_from(&contents),
+            Err(e) => {
+                error!(
+                    "Failed to read {}: {e}, keeping the current config",
+                    self.config_path.display()
+                );
+                None
+            }
+        }
+    }
+
+    fn reload_from(&self, contents: &str) -> Option<Config> {
+        let path = self.config_path.display();
+        let Validated { config, errors } = match validate::validate_toml(contents) {
+            Ok(validated) => validated,
+            Err(e) => {
+                error!("Ignoring edit to {path}, not valid TOML: {e}");
+                return None;
+            }
+        };
+        if !errors.is_empty() {
+            for error in &errors {
+                error!("Ignoring edit to {path}, invalid setting {error}");
+            }
+            return None;
+        }
+
+        let mut current = self.config.lock().unwrap();
+        if *current == config {
+            return None;
+        }
+        *current = config.clone();
+        info!("Reloaded config from {path}");
+        Some(config)
+    }
+
+    /// Reload the file whenever its contents change, checking every `interval`
+    ///
+    /// `on_change` gets every config `reload` accepted, not the manager's own
+    /// saves. The thread stops once the manager is dropped.
+    pub fn start_watching<F>(self: &Arc<Self>, interval: Duration, on_change: F)
+    where
+        F: Fn(&Config) + Send + 'static,
+    {
+        let manager = Arc::downgrade(self);
+        let path = self.config_path.clone();
+        let mut last = fs::read_to_string(&path).ok();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let Some(manager) = manager.upgrade() else {
+                break;
+            };
+            // A deleted file keeps the current config until it is back
+            let contents = fs::read_to_string(&path).ok();
+            if contents.is_none() || contents == last {
+                continue;
+            }
+            if let Some(config) = contents.as_deref().and_then(|c| manager.reload_from(c)) {
+                on_change(&config);
+            }
+            last = contents;
+        });
+        info!("Watching {} for changes", self.config_path.display());
+    }
+
+    /// Whether no config file existed at launch and none has been saved since
+    pub fn is_first_run(&self) -> bool {
+        self.first_run.load(Ordering::SeqCst)
+    }
+
+    /// Only the current user may read the file, as it can hold the HTTP API token
+    fn write_to(path: &Path, config: &Config) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string_pretty(config)?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        // `mode` only applies to new files, so narrow one saved before too
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(contents.as_bytes())?;
+        info!("Saved config to {}", path.display());
+        Ok(())
+    }
+
+    /// Path of the backing config file
+    pub fn path(&self) -> &Path {
+        &self.config_path
+    }
+}
+
+/// Config as exchanged with the settings window
+///
+/// Values are kept loosely typed so that bad input is reported per field
+/// instead of failing deserialization of the whole payload.
+/// Optional fields that are missing keep their current value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDto {
+    pub typing_speed: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings_close_behavior: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_pastes: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_socket: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paste_delay_secs: Option<u64>,
+}
+
+impl From<Config> for ConfigDto {
+    fn from(config: Config) -> Self {
+        Self {
+            typing_speed: typing_speed_name(config.typing_speed).to_string(),
+            settings_close_behavior: Some(
+                close_behavior_name(config.settings_close_behavior).to_string(),
+            ),
+            theme: Some(theme_name(config.theme).to_string()),
+            queue_pastes: Some(config.queue_pastes),
+            control_socket: Some(config.control_socket),
+            paste_delay_secs: Some(config.paste_delay_secs),
+        }
+    }
+}
+
+impl ConfigDto {
+    /// Validate every field and convert to a `Config`
+    pub fn into_config(self) -> Result<Config, Vec<FieldError>> {
+        self.apply_to(Config::default())
+    }
+
+    /// Validate every field and apply them on top of `base`
+    /// Settings that aren't exposed to the frontend are kept from `base`
+    pub fn apply_to(self, base: Config) -> Result<Config, Vec<FieldError>> {
+        let Validated { config, errors } = validate::validate_dto(self, base);
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn typing_speed_name(speed: TypingSpeed) -> &'static str {
+    match speed {
+        TypingSpeed::Slow => "slow",
+        TypingSpeed::Normal => "normal",
+        TypingSpeed::Fast => "fast",
+    }
+}
+
+fn close_behavior_name(behavior: SettingsCloseBehavior) -> &'static str {
+    match behavior {
+        SettingsCloseBehavior::Hide => "hide",
+        SettingsCloseBehavior::Destroy => "destroy",
+    }
+}
+
+fn theme_name(theme: ThemePreference) -> &'static str {
+    match theme {
+        ThemePreference::Auto => "auto",
+        ThemePreference::Light => "light",
+        ThemePreference::Dark => "dark",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert_eq!(config.typing_speed, TypingSpeed::Normal);
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let dir = tempdir().unwrap();
+        let manager = ConfigManager::with_path(dir.path().join("config.toml"));
+        assert_eq!(manager.get(), Config::default());
+    }
+
+    #[test]
+    fn test_set_persists_and_reloads() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta").join("config.toml");
+
+        let manager = ConfigManager::with_path(path.clone());
+        manager
+            .set(Config {
+                typing_speed: TypingSpeed::Fast,
+                ..Config::default()
+            })
+            .unwrap();
+        assert_eq!(manager.get().typing_speed, TypingSpeed::Fast);
+        assert!(path.exists());
+
+        let reloaded = ConfigManager::with_path(path);
+        assert_eq!(reloaded.get().typing_speed, TypingSpeed::Fast);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_config_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        ConfigManager::with_path(path.clone())
+            .set(Config::default())
+            .unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let fresh = dir.path().join("fresh").join("config.toml");
+        ConfigManager::with_path(fresh.clone())
+            .set(Config::default())
+            .unwrap();
+        let mode = fs::metadata(&fresh).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_reload_keeps_config_on_bad_edits() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let manager = ConfigManager::with_path(path.clone());
+        manager
+            .update(|c| c.typing_speed = TypingSpeed::Fast)
+            .unwrap();
+
+        // Our own save is no change
+        assert_eq!(manager.reload(), None);
+
+        for malformed in ["typing_speed = [", "typing_speed = \"warp\""] {
+            fs::write(&path, malformed).unwrap();
+            assert_eq!(manager.reload(), None);
+            assert_eq!(manager.get().typing_speed, TypingSpeed::Fast);
+        }
+
+        fs::write(&path, "typing_speed = \"slow\"").unwrap();
+        let reloaded = manager.reload().unwrap();
+        assert_eq!(reloaded.typing_speed, TypingSpeed::Slow);
+        assert_eq!(manager.get(), reloaded);
+    }
+
+    #[test]
+    fn test_watching_picks_up_edits() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let manager = Arc::new(ConfigManager::with_path(path.clone()));
+        manager.save().unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        manager.start_watching(Duration::from_millis(10), move |config| {
+            let _ = tx.send(config.typing_speed);
+        });
+
+        fs::write(&path, "typing_speed = \"fast\"\n").unwrap();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            TypingSpeed::Fast
+        );
+        assert_eq!(manager.get().typing_speed, TypingSpeed::Fast);
+    }
+
+    #[test]
+    fn test_unwritable_path_is_an_io_error() {
+        let dir = tempdir().unwrap();
+        let blocker = dir.path().join("pasta");
+        fs::write(&blocker, "").unwrap();
+
+        let manager = ConfigManager::with_path(blocker.join("config.toml"));
+        assert!(matches!(manager.save(), Err(StorageError::Io(_))));
+        assert!(manager.is_first_run());
+    }
+
+    #[test]
+    fn test_first_run_until_saved() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let manager = ConfigManager::with_path(path.clone());
+        assert!(manager.is_first_run());
+
+        manager.save().unwrap();
+        assert!(!manager.is_first_run());
+
+        let reloaded = ConfigManager::with_path(path);
+        assert!(!reloaded.is_first_run());
+    }
+
+    #[test]
+    fn test_malformed_file_is_not_first_run() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "typing_speed = [not valid").unwrap();
+
+        let manager = ConfigManager::with_path(path);
+        assert!(!manager.is_first_run());
+    }
+
+    #[test]
+    fn test_malformed_file_uses_defaults() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "typing_speed = [not valid").unwrap();
+
+        let manager = ConfigManager::with_path(path);
+        assert_eq!(manager.get(), Config::default());
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "").unwrap();
+
+        let manager = ConfigManager::with_path(path);
+        assert_eq!(manager.get(), Config::default());
+    }
+
+    #[test]
+    fn test_config_toml_format() {
+        let config = Config {
+            typing_speed: TypingSpeed::Slow,
+            ..Config::default()
+        };
+        let toml = toml::to_string_pretty(&config).unwrap();
+        assert!(toml.contains("typing_speed = \"slow\""));
+        assert!(!toml.contains("window_state"));
+        assert!(!toml.contains("snippets"));
+    }
+
+    #[test]
+    fn test_window_state_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let state = WindowState {
+            x: -1200,
+            y: 40,
+            width: 400,
+            height: 300,
+        };
+
+        let manager = ConfigManager::with_path(path.clone());
+        manager.update(|c| c.window_state = Some(state)).unwrap();
+
+        let reloaded = ConfigManager::with_path(path);
+        assert_eq!(reloaded.get().window_state, Some(state));
+    }
+
+    #[test]
+    fn test_snippets_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let snippets = BTreeMap::from([
+            ("sig".to_string(), "Best,\nPasta".to_string()),
+            ("header".to_string(), "#!/bin/sh\n".to_string()),
+        ]);
+
+        let manager = ConfigManager::with_path(path.clone());
+        manager
+            .update(|c| {
+                c.snippets = snippets.clone();
+                c.window_state = Some(WindowState {
+                    x: 0,
+                    y: 0,
+                    width: 400,
+                    height: 300,
+                });
+            })
+            .unwrap();
+
+        let reloaded = ConfigManager::with_path(path);
+        assert_eq!(reloaded.get().snippets, snippets);
+    }
+
+    #[test]
+    fn test_dto_apply_keeps_window_state() {
+        let state = WindowState {
+            x: 10,
+            y: 20,
+            width: 400,
+            height: 300,
+        };
+        let base = Config {
+            window_state: Some(state),
+            ..Config::default()
+        };
+        let dto = ConfigDto {
+            typing_speed: "slow".to_string(),
+            settings_close_behavior: None,
+            theme: None,
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+
+        let config = dto.apply_to(base).unwrap();
+        assert_eq!(config.typing_speed, TypingSpeed::Slow);
+        assert_eq!(config.window_state, Some(state));
+    }
+
+    #[test]
+    fn test_dto_uses_camel_case() {
+        let dto = ConfigDto::from(Config::default());
+        let json = serde_json::to_string(&dto).unwrap();
+        assert_eq!(
+            json,
+            r#"{"typingSpeed":"normal","settingsCloseBehavior":"hide","theme":"auto","queuePastes":false}"#
+        );
+    }
+
+    #[test]
+    fn test_dto_close_behavior() {
+        let dto: ConfigDto =
+            serde_json::from_str(r#"{"typingSpeed":"normal","settingsCloseBehavior":"destroy"}"#)
+                .unwrap();
+        assert_eq!(
+            dto.into_config().unwrap().settings_close_behavior,
+            SettingsCloseBehavior::Destroy
+        );
+    }
+
+    #[test]
+    fn test_dto_missing_close_behavior_keeps_base() {
+        let base = Config {
+            settings_close_behavior: SettingsCloseBehavior::Destroy,
+            ..Config::default()
+        };
+        let dto: ConfigDto = serde_json::from_str(r#"{"typingSpeed":"fast"}"#).unwrap();
+        let config = dto.apply_to(base).unwrap();
+        assert_eq!(
+            config.settings_close_behavior,
+            SettingsCloseBehavior::Destroy
+        );
+        assert_eq!(config.typing_speed, TypingSpeed::Fast);
+    }
+
+    #[test]
+    fn test_dto_rejects_unknown_close_behavior() {
+        let dto = ConfigDto {
+            typing_speed: "normal".to_string(),
+            settings_close_behavior: Some("minimize".to_string()),
+            theme: None,
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+        let errors = dto.into_config().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "settingsCloseBehavior");
+    }
+
+    #[test]
+    fn test_dto_theme() {
+        let dto = ConfigDto {
+            theme: Some("Dark".to_string()),
+            ..ConfigDto::from(Config::default())
+        };
+        assert_eq!(dto.into_config().unwrap().theme, ThemePreference::Dark);
+
+        let dto = ConfigDto {
+            theme: Some("sepia".to_string()),
+            ..ConfigDto::from(Config::default())
+        };
+        let errors = dto.into_config().unwrap_err();
+        assert_eq!(errors[0].path, "theme");
+    }
+
+    #[test]
+    fn test_close_behavior_toml_format() {
+        let config = Config {
+            settings_close_behavior: SettingsCloseBehavior::Destroy,
+            ..Config::default()
+        };
+        let toml = toml::to_string_pretty(&config).unwrap();
+        assert!(toml.contains("settings_close_behavior = \"destroy\""));
+    }
+
+    #[test]
+    fn test_dto_round_trip() {
+        for speed in [TypingSpeed::Slow, TypingSpeed::Normal, TypingSpeed::Fast] {
+            let config = Config {
+                typing_speed: speed,
+                ..Config::default()
+            };
+            let dto = ConfigDto::from(config.clone());
+            assert_eq!(dto.into_config().unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn test_dto_accepts_mixed_case_speed() {
+        let dto = ConfigDto {
+            typing_speed: " Fast ".to_string(),
+            settings_close_behavior: None,
+            theme: None,
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+        assert_eq!(dto.into_config().unwrap().typing_speed, TypingSpeed::Fast);
+    }
+
+    #[test]
+    fn test_dto_rejects_unknown_speed() {
+        let dto = ConfigDto {
+            typing_speed: "ludicrous".to_string(),
+            settings_close_behavior: None,
+            theme: None,
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        };
+        let errors = dto.into_config().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "typingSpeed");
+        assert!(errors[0].message.contains("ludicrous"));
+    }
+}
@@ -0,0 +1,1000 @@
+/// Field-level validation shared by `set_config` and config file loading
+use std::fmt;
+
+use serde::Serialize;
+
+use super::{
+    Config, ConfigDto, OversizePaste, SettingsCloseBehavior, WindowState, MAX_PASTE_CHARS_LIMIT,
+};
+use crate::{
+    countdown::MAX_PASTE_DELAY_SECS,
+    debounce::MAX_PASTE_DEBOUNCE_MS,
+    hooks::HookFailure,
+    http_api::HttpApiConfig,
+    keyboard::{
+        clamp_typing_delay, PasteMethod, TypingMode, TypingSpeed, MAX_CHUNK_DELAY_MS,
+        MAX_CHUNK_SIZE, MAX_JITTER_PERCENT, MAX_KEYSTROKE_THRESHOLD, MAX_LINE_DELAY_MS,
+    },
+    logging::LogLevel,
+    theme::ThemePreference,
+};
+
+/// Validation failure for a single settings field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// Field name as the caller spelled it, e.g. `typingSpeed` or `typing_speed`
+    pub path: String,
+    pub message: String,
+    /// The rejected value, empty when there is none
+    pub received: String,
+}
+
+impl FieldError {
+    pub fn new(path: &str, message: impl Into<String>) -> Self {
+        Self::invalid(path, "", message)
+    }
+
+    pub fn invalid(path: &str, received: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            message: message.into(),
+            received: received.into(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Config built from the valid fields, plus what was rejected
+///
+/// Rejected fields keep their value from the base config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validated {
+    pub config: Config,
+    pub errors: Vec<FieldError>,
+}
+
+/// Validate settings from the settings window on top of `base`
+pub fn validate_dto(dto: ConfigDto, base: Config) -> Validated {
+    let mut errors = Vec::new();
+
+    let typing_speed = check(
+        &mut errors,
+        "typingSpeed",
+        Some(&dto.typing_speed),
+        base.typing_speed,
+    );
+    let settings_close_behavior = check(
+        &mut errors,
+        "settingsCloseBehavior",
+        dto.settings_close_behavior.as_deref(),
+        base.settings_close_behavior,
+    );
+    let theme = check(&mut errors, "theme", dto.theme.as_deref(), base.theme);
+    let queue_pastes = dto.queue_pastes.unwrap_or(base.queue_pastes);
+    let control_socket = dto.control_socket.unwrap_or(base.control_socket);
+    let paste_delay_secs = check_range(
+        &mut errors,
+        "pasteDelaySecs",
+        dto.paste_delay_secs
+            .map(|secs| i64::try_from(secs).unwrap_or(i64::MAX)),
+        base.paste_delay_secs,
+        &PASTE_DELAY,
+    );
+
+    Validated {
+        config: Config {
+            typing_speed,
+            settings_close_behavior,
+            theme,
+            queue_pastes,
+            control_socket,
+            paste_delay_secs,
+            ..base
+        },
+        errors,
+    }
+}
+
+/// Parse `config.toml`, keeping defaults for fields that fail validation
+///
+/// Only a file that isn't valid TOML is an error; bad values are reported
+/// so one typo doesn't reset every other setting.
+pub fn validate_toml(contents: &str) -> Result<Validated, toml::de::Error> {
+    let table: toml::Table = contents.parse()?;
+    let base = Config::default();
+    let mut errors = Vec::new();
+
+    let mut string_field = |key: &str| match table.get(key) {
+        None => None,
+        Some(toml::Value::String(value)) => Some(value.clone()),
+        Some(other) => {
+            errors.push(FieldError::invalid(
+                key,
+                other.to_string(),
+                "expected a string",
+            ));
+            None
+        }
+    };
+    let typing_speed = string_field("typing_speed");
+    let typing_mode = string_field("typing_mode");
+    let paste_method = string_field("paste_method");
+    let oversize_paste = string_field("oversize_paste");
+    let settings_close_behavior = string_field("settings_close_behavior");
+    let theme = string_field("theme");
+    let log_level = string_field("log_level");
+    let pre_paste_failure = string_field("pre_paste_failure");
+    // A blank command is the same as none
+    let mut command_field = |key: &str| string_field(key).filter(|c| !c.trim().is_empty());
+    let pre_paste_command = command_field("pre_paste_command");
+    let post_paste_command = command_field("post_paste_command");
+
+    let mut bool_field = |key: &str, default: bool| match table.get(key) {
+        None => default,
+        Some(toml::Value::Boolean(value)) => *value,
+        Some(other) => {
+            errors.push(FieldError::invalid(
+                key,
+                other.to_string(),
+                "expected true or false",
+            ));
+            default
+        }
+    };
+    let queue_pastes = bool_field("queue_pastes", base.queue_pastes);
+    let control_socket = bool_field("control_socket", base.control_socket);
+    let log_to_file = bool_field("log_to_file", base.log_to_file);
+    let skip_elevation_check = bool_field("skip_elevation_check", base.skip_elevation_check);
+    let headless = bool_field("headless", base.headless);
+    let update_check = bool_field("update_check", base.update_check);
+    let sound_feedback = bool_field("sound_feedback", base.sound_feedback);
+    let launch_at_login = bool_field("launch_at_login", base.launch_at_login);
+    let confirm_before_typing = bool_field("confirm_before_typing", base.confirm_before_typing);
+    let abort_on_user_input = bool_field("abort_on_user_input", base.abort_on_user_input);
+    let final_newline = bool_field("final_newline", base.final_newline);
+    let trim_trailing_whitespace =
+        bool_field("trim_trailing_whitespace", base.trim_trailing_whitespace);
+    let normalize_line_endings = bool_field("normalize_line_endings", base.normalize_line_endings);
+    let strip_final_newline = bool_field("strip_final_newline", base.strip_final_newline);
+    let paste_delay_secs = toml_range(
+        &mut errors,
+        &table,
+        "paste_delay_secs",
+        base.paste_delay_secs,
+        &PASTE_DELAY,
+    );
+    let paste_debounce_ms = toml_range(
+        &mut errors,
+        &table,
+        "paste_debounce_ms",
+        base.paste_debounce_ms,
+        &PASTE_DEBOUNCE,
+    );
+    let line_delay_ms = toml_range(
+        &mut errors,
+        &table,
+        "line_delay_ms",
+        base.line_delay_ms,
+        &LINE_DELAY,
+    );
+    let chunk_size = toml_range(
+        &mut errors,
+        &table,
+        "chunk_size",
+        base.chunk_size as u64,
+        &CHUNK_SIZE,
+    ) as usize;
+    let chunk_delay_ms = toml_range(
+        &mut errors,
+        &table,
+        "chunk_delay_ms",
+        base.chunk_delay_ms,
+        &CHUNK_DELAY,
+    );
+    let jitter_percent = toml_range(
+        &mut errors,
+        &table,
+        "jitter_percent",
+        u64::from(base.jitter_percent),
+        &JITTER,
+    ) as u8;
+    let paste_keystroke_threshold = toml_range(
+        &mut errors,
+        &table,
+        "paste_keystroke_threshold",
+        base.paste_keystroke_threshold,
+        &KEYSTROKE_THRESHOLD,
+    );
+    let max_paste_chars = toml_range(
+        &mut errors,
+        &table,
+        "max_paste_chars",
+        base.max_paste_chars,
+        &PASTE_CHARS,
+    );
+    // Out of range delays are clamped rather than dropped, since the user
+    // clearly wanted a custom speed
+    let typing_delay_ms = match table.get("typing_delay_ms") {
+        None => None,
+        Some(toml::Value::Integer(value)) => Some(clamp_typing_delay(*value)),
+        Some(other) => {
+            errors.push(FieldError::invalid(
+                "typing_delay_ms",
+                other.to_string(),
+                "expected a number of milliseconds",
+            ));
+            None
+        }
+    };
+
+    let config = Config {
+        typing_speed: check(
+            &mut errors,
+            "typing_speed",
+            typing_speed.as_deref(),
+            base.typing_speed,
+        ),
+        typing_delay_ms,
+        typing_mode: check(
+            &mut errors,
+            "typing_mode",
+            typing_mode.as_deref(),
+            base.typing_mode,
+        ),
+        line_delay_ms,
+        final_newline,
+        chunk_size,
+        chunk_delay_ms,
+        jitter_percent,
+        paste_method: check(
+            &mut errors,
+            "paste_method",
+            paste_method.as_deref(),
+            base.paste_method,
+        ),
+        paste_keystroke_threshold,
+        max_paste_chars,
+        oversize_paste: check(
+            &mut errors,
+            "oversize_paste",
+            oversize_paste.as_deref(),
+            base.oversize_paste,
+        ),
+        settings_close_behavior: check(
+            &mut errors,
+            "settings_close_behavior",
+            settings_close_behavior.as_deref(),
+            base.settings_close_behavior,
+        ),
+        theme: check(&mut errors, "theme", theme.as_deref(), base.theme),
+        queue_pastes,
+        paste_delay_secs,
+        paste_debounce_ms,
+        trim_trailing_whitespace,
+        normalize_line_endings,
+        strip_final_newline,
+        control_socket,
+        log_to_file,
+        log_level: check(
+            &mut errors,
+            "log_level",
+            log_level.as_deref(),
+            base.log_level,
+        ),
+        skip_elevation_check,
+        headless,
+        pre_paste_command,
+        post_paste_command,
+        pre_paste_failure: check(
+            &mut errors,
+            "pre_paste_failure",
+            pre_paste_failure.as_deref(),
+            base.pre_paste_failure,
+        ),
+        update_check,
+        sound_feedback,
+        launch_at_login,
+        confirm_before_typing,
+        abort_on_user_input,
+        snippets: match table.get("snippets") {
+            None => base.snippets,
+            Some(toml::Value::Table(snippets)) => snippets
+                .iter()
+                .filter_map(|(name, text)| match text {
+                    toml::Value::String(text) => Some((name.clone(), text.clone())),
+                    other => {
+                        errors.push(FieldError::invalid(
+                            &format!("snippets.{name}"),
+                            other.to_string(),
+                            "expected the snippet's text",
+                        ));
+                        None
+                    }
+                })
+                .collect(),
+            Some(other) => {
+                errors.push(FieldError::invalid(
+                    "snippets",
+                    other.to_string(),
+                    "expected a table of names and text",
+                ));
+                base.snippets
+            }
+        },
+        http_api: match table.get("http_api") {
+            None => base.http_api,
+            Some(value) => match value.clone().try_into::<HttpApiConfig>() {
+                Ok(http_api) => http_api,
+                Err(e) => {
+                    errors.push(FieldError::invalid(
+                        "http_api",
+                        value.to_string(),
+                        e.message(),
+                    ));
+                    base.http_api
+                }
+            },
+        },
+        window_state: match table.get("window_state") {
+            None => None,
+            Some(value) => match value.clone().try_into::<WindowState>() {
+                Ok(state) => Some(state),
+                Err(e) => {
+                    errors.push(FieldError::invalid(
+                        "window_state",
+                        value.to_string(),
+                        e.message(),
+                    ));
+                    None
+                }
+            },
+        },
+    };
+
+    Ok(Validated { config, errors })
+}
+
+/// A setting that is exchanged as a string
+trait Field: Sized {
+    /// What kind of value this is, used in error messages
+    const NAME: &'static str;
+
+    fn parse(value: &str) -> Option<Self>;
+}
+
+impl Field for TypingSpeed {
+    const NAME: &'static str = "typing speed";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "slow" => Some(TypingSpeed::Slow),
+            "normal" => Some(TypingSpeed::Normal),
+            "fast" => Some(TypingSpeed::Fast),
+            _ => None,
+        }
+    }
+}
+
+impl Field for TypingMode {
+    const NAME: &'static str = "typing mode";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "continuous" => Some(TypingMode::Continuous),
+            "line_by_line" => Some(TypingMode::LineByLine),
+            _ => None,
+        }
+    }
+}
+
+impl Field for PasteMethod {
+    const NAME: &'static str = "paste method";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "type" => Some(PasteMethod::Type),
+            "keystroke" => Some(PasteMethod::Keystroke),
+            "auto" => Some(PasteMethod::Auto),
+            _ => None,
+        }
+    }
+}
+
+impl Field for OversizePaste {
+    const NAME: &'static str = "oversize paste behavior";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "abort" => Some(OversizePaste::Abort),
+            "truncate" => Some(OversizePaste::Truncate),
+            _ => None,
+        }
+    }
+}
+
+impl Field for SettingsCloseBehavior {
+    const NAME: &'static str = "close behavior";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "hide" => Some(SettingsCloseBehavior::Hide),
+            "destroy" => Some(SettingsCloseBehavior::Destroy),
+            _ => None,
+        }
+    }
+}
+
+impl Field for ThemePreference {
+    const NAME: &'static str = "theme";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" | "system" => Some(ThemePreference::Auto),
+            "light" => Some(ThemePreference::Light),
+            "dark" => Some(ThemePreference::Dark),
+            _ => None,
+        }
+    }
+}
+
+impl Field for LogLevel {
+    const NAME: &'static str = "log level";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+}
+
+impl Field for HookFailure {
+    const NAME: &'static str = "hook failure policy";
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warn" => Some(HookFailure::Warn),
+            "abort" => Some(HookFailure::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an optional field, recording an error and keeping `current` if it is invalid
+fn check<T: Field>(errors: &mut Vec<FieldError>, path: &str, value: Option<&str>, current: T) -> T {
+    let Some(value) = value else {
+        return current;
+    };
+    T::parse(value).unwrap_or_else(|| {
+        errors.push(FieldError::invalid(
+            path,
+            value,
+            format!("unknown {} '{value}'", T::NAME),
+        ));
+        current
+    })
+}
+
+/// Bounds and unit of a numeric setting
+struct Range {
+    min: u64,
+    max: u64,
+    unit: &'static str,
+}
+
+const PASTE_DELAY: Range = Range {
+    min: 0,
+    max: MAX_PASTE_DELAY_SECS,
+    unit: "seconds",
+};
+
+const PASTE_DEBOUNCE: Range = Range {
+    min: 0,
+    max: MAX_PASTE_DEBOUNCE_MS,
+    unit: "milliseconds",
+};
+
+const LINE_DELAY: Range = Range {
+    min: 0,
+    max: MAX_LINE_DELAY_MS,
+    unit: "milliseconds",
+};
+
+const KEYSTROKE_THRESHOLD: Range = Range {
+    min: 0,
+    max: MAX_KEYSTROKE_THRESHOLD,
+    unit: "characters",
+};
+
+const CHUNK_SIZE: Range = Range {
+    min: 1,
+    max: MAX_CHUNK_SIZE,
+    unit: "characters",
+};
+
+const CHUNK_DELAY: Range = Range {
+    min: 0,
+    max: MAX_CHUNK_DELAY_MS,
+    unit: "milliseconds",
+};
+
+const JITTER: Range = Range {
+    min: 0,
+    max: MAX_JITTER_PERCENT,
+    unit: "percent",
+};
+
+const PASTE_CHARS: Range = Range {
+    min: 0,
+    max: MAX_PASTE_CHARS_LIMIT,
+    unit: "characters",
+};
+
+/// Check a number against `range`, keeping `current` if it is out of range
+fn check_range(
+    errors: &mut Vec<FieldError>,
+    path: &str,
+    value: Option<i64>,
+    current: u64,
+    range: &Range,
+) -> u64 {
+    let Some(value) = value else {
+        return current;
+    };
+    match u64::try_from(value) {
+        Ok(number) if (range.min..=range.max).contains(&number) => number,
+        _ => {
+            errors.push(FieldError::invalid(
+                path,
+                value.to_string(),
+                format!("expected {} to {} {}", range.min, range.max, range.unit),
+            ));
+            current
+        }
+    }
+}
+
+/// Read a numeric setting from the config file
+fn toml_range(
+    errors: &mut Vec<FieldError>,
+    table: &toml::Table,
+    key: &str,
+    current: u64,
+    range: &Range,
+) -> u64 {
+    match table.get(key) {
+        None => current,
+        Some(toml::Value::Integer(number)) => {
+            check_range(errors, key, Some(*number), current, range)
+        }
+        Some(other) => {
+            errors.push(FieldError::invalid(
+                key,
+                other.to_string(),
+                format!("expected a number of {}", range.unit),
+            ));
+            current
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::keyboard::Chunking;
+
+    fn dto(typing_speed: &str, close: Option<&str>, theme: Option<&str>) -> ConfigDto {
+        ConfigDto {
+            typing_speed: typing_speed.to_string(),
+            settings_close_behavior: close.map(str::to_string),
+            theme: theme.map(str::to_string),
+            queue_pastes: None,
+            control_socket: None,
+            paste_delay_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_every_invalid_field_is_reported() {
+        let result = validate_dto(
+            dto("warp", Some("minimize"), Some("sepia")),
+            Config::default(),
+        );
+
+        let paths: Vec<&str> = result.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["typingSpeed", "settingsCloseBehavior", "theme"]);
+        assert_eq!(result.errors[0].received, "warp");
+        assert_eq!(result.errors[1].received, "minimize");
+        assert_eq!(result.errors[2].message, "unknown theme 'sepia'");
+    }
+
+    #[test]
+    fn test_valid_fields_are_applied_alongside_errors() {
+        let base = Config {
+            theme: ThemePreference::Light,
+            ..Config::default()
+        };
+        let result = validate_dto(dto("fast", None, Some("sepia")), base);
+
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.config.typing_speed, TypingSpeed::Fast);
+        assert_eq!(result.config.theme, ThemePreference::Light);
+    }
+
+    #[test]
+    fn test_toml_typing_delay_is_clamped() {
+        let delay = |toml: &str| validate_toml(toml).unwrap().config.typing_delay_ms;
+        assert_eq!(delay(""), None);
+        assert_eq!(delay("typing_delay_ms = 40"), Some(40));
+        assert_eq!(delay("typing_delay_ms = 0"), Some(1));
+        assert_eq!(delay("typing_delay_ms = 99999"), Some(5000));
+
+        let result = validate_toml("typing_delay_ms = \"fast\"").unwrap();
+        assert_eq!(result.config.typing_delay_ms, None);
+        assert_eq!(result.errors[0].path, "typing_delay_ms");
+    }
+
+    #[test]
+    fn test_toml_line_by_line_settings() {
+        let result = validate_toml(
+            "typing_mode = \"line_by_line\"\nline_delay_ms = 800\nfinal_newline = false\n",
+        )
+        .unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.config.typing_mode, TypingMode::LineByLine);
+        assert_eq!(result.config.line_delay_ms, 800);
+        assert!(!result.config.final_newline);
+
+        let result =
+            validate_toml("typing_mode = \"paragraphs\"\nline_delay_ms = 60000\n").unwrap();
+        assert_eq!(result.config.typing_mode, TypingMode::Continuous);
+        assert_eq!(result.config.line_delay_ms, 500);
+        let paths: Vec<&str> = result.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["line_delay_ms", "typing_mode"]);
+    }
+
+    #[test]
+    fn test_toml_bad_values_keep_defaults() {
+        let result = validate_toml(
+            "typing_speed = \"fast\"\ntheme = 3\nsettings_close_behavior = \"minimize\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(result.config.typing_speed, TypingSpeed::Fast);
+        assert_eq!(result.config.theme, ThemePreference::Auto);
+        assert_eq!(
+            result.config.settings_close_behavior,
+            SettingsCloseBehavior::Hide
+        );
+        let paths: Vec<&str> = result.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["theme", "settings_close_behavior"]);
+        assert_eq!(result.errors[0].message, "expected a string");
+    }
+
+    #[test]
+    fn test_toml_queue_pastes() {
+        let result = validate_toml("queue_pastes = true\n").unwrap();
+        assert!(result.config.queue_pastes);
+        assert!(result.errors.is_empty());
+
+        let result = validate_toml("queue_pastes = \"yes\"\n").unwrap();
+        assert!(!result.config.queue_pastes);
+        assert_eq!(result.errors[0].path, "queue_pastes");
+    }
+
+    #[test]
+    fn test_toml_control_socket() {
+        let result = validate_toml("control_socket = true\n").unwrap();
+        assert!(result.config.control_socket);
+
+        let result = validate_toml("control_socket = 1\n").unwrap();
+        assert!(!result.config.control_socket);
+        assert_eq!(result.errors[0].path, "control_socket");
+    }
+
+    #[test]
+    fn test_toml_logging() {
+        let result = validate_toml("log_to_file = true\nlog_level = \"debug\"\n").unwrap();
+        assert!(result.config.log_to_file);
+        assert_eq!(result.config.log_level, LogLevel::Debug);
+
+        let result = validate_toml("log_level = \"chatty\"\n").unwrap();
+        assert_eq!(result.config.log_level, LogLevel::Info);
+        assert_eq!(result.errors[0].message, "unknown log level 'chatty'");
+    }
+
+    #[test]
+    fn test_paste_delay_range() {
+        let result = validate_toml("paste_delay_secs = 3\n").unwrap();
+        assert_eq!(result.config.paste_delay_secs, 3);
+
+        for contents in ["paste_delay_secs = -1\n", "paste_delay_secs = 31\n"] {
+            let result = validate_toml(contents).unwrap();
+            assert_eq!(result.config.paste_delay_secs, 0, "{contents}");
+            assert_eq!(result.errors[0].message, "expected 0 to 30 seconds");
+        }
+
+        let result = validate_toml("paste_delay_secs = \"3\"\n").unwrap();
+        assert_eq!(result.errors[0].path, "paste_delay_secs");
+
+        let result = validate_dto(
+            ConfigDto {
+                paste_delay_secs: Some(100),
+                ..dto("normal", None, None)
+            },
+            Config::default(),
+        );
+        assert_eq!(result.errors[0].path, "pasteDelaySecs");
+    }
+
+    #[test]
+    fn test_toml_paste_debounce() {
+        assert_eq!(validate_toml("").unwrap().config.paste_debounce_ms, 300);
+        let result = validate_toml("paste_debounce_ms = 0\n").unwrap();
+        assert_eq!(result.config.paste_debounce_ms, 0);
+
+        let result = validate_toml("paste_debounce_ms = 60000\n").unwrap();
+        assert_eq!(result.config.paste_debounce_ms, 300);
+        assert_eq!(result.errors[0].message, "expected 0 to 5000 milliseconds");
+    }
+
+    #[test]
+    fn test_toml_chunking() {
+        let config = validate_toml("").unwrap().config;
+        assert_eq!(config.chunking(), Chunking::default());
+
+        let result = validate_toml("chunk_size = 20\nchunk_delay_ms = 750\n").unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(
+            result.config.chunking(),
+            Chunking {
+                size: 20,
+                pause: Duration::from_millis(750),
+            }
+        );
+
+        let result = validate_toml("chunk_size = 0\nchunk_delay_ms = 60000\n").unwrap();
+        assert_eq!(result.config, Config::default());
+        assert_eq!(result.errors[0].path, "chunk_size");
+        assert_eq!(result.errors[0].message, "expected 1 to 100000 characters");
+        assert_eq!(result.errors[1].path, "chunk_delay_ms");
+    }
+
+    #[test]
+    fn test_toml_jitter_percent() {
+        assert_eq!(validate_toml("").unwrap().config.jitter_percent, 0);
+
+        let result = validate_toml("jitter_percent = 25\n").unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.config.jitter_percent, 25);
+
+        let result = validate_toml("jitter_percent = 80\n").unwrap();
+        assert_eq!(result.config.jitter_percent, 0);
+        assert_eq!(result.errors[0].path, "jitter_percent");
+        assert_eq!(result.errors[0].message, "expected 0 to 50 percent");
+    }
+
+    #[test]
+    fn test_toml_max_paste_chars() {
+        let config = validate_toml("").unwrap().config;
+        assert_eq!(config.max_paste_chars, 100_000);
+        assert_eq!(config.oversize_paste, OversizePaste::Abort);
+
+        let result = validate_toml("max_paste_chars = 0\noversize_paste = \"truncate\"\n").unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.config.max_paste_chars, 0);
+        assert_eq!(result.config.oversize_paste, OversizePaste::Truncate);
+
+        let result = validate_toml("max_paste_chars = -1\noversize_paste = \"cut\"\n").unwrap();
+        assert_eq!(result.config, Config::default());
+        let paths: Vec<&str> = result.errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["max_paste_chars", "oversize_paste"]);
+    }
+
+    #[test]
+    fn test_toml_paste_method() {
+        let config = validate_toml("").unwrap().config;
+        assert_eq!(config.paste_method, PasteMethod::Type);
+
+        let result =
+            validate_toml("paste_method = \"auto\"\npaste_keystroke_threshold = 5000\n").unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.config.paste_method, PasteMethod::Auto);
+        assert_eq!(result.config.paste_keystroke_threshold, 5000);
+
+        let result = validate_toml("paste_method = \"ctrl_v\"\n").unwrap();
+        assert_eq!(result.config.paste_method, PasteMethod::Type);
+        assert_eq!(result.errors[0].path, "paste_method");
+    }
+
+    #[test]
+    fn test_toml_whitespace_transforms() {
+        let config = validate_toml("").unwrap().config;
+        assert!(config.transforms().is_noop());
+
+        let result = validate_toml(
+            "trim_trailing_whitespace = true\nnormalize_line_endings = true\n\
+             strip_final_newline = \"yes\"\n",
+        )
+        .unwrap();
+        let transforms = result.config.transforms();
+        assert!(transforms.trim_trailing_whitespace);
+        assert!(transforms.normalize_line_endings);
+        assert!(!transforms.strip_final_newline);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].path, "strip_final_newline");
+    }
+
+    #[test]
+    fn test_toml_skip_elevation_check() {
+        assert!(!validate_toml("").unwrap().config.skip_elevation_check);
+        let result = validate_toml("skip_elevation_check = true\n").unwrap();
+        assert!(result.config.skip_elevation_check);
+    }
+
+    #[test]
+    fn test_toml_paste_hooks() {
+        let result = validate_toml(
+            "pre_paste_command = \"wmctrl -s 2\"\npost_paste_command = \"  \"\npre_paste_failure = \"Abort\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            result.config.pre_paste_command.as_deref(),
+            Some("wmctrl -s 2")
+        );
+        assert_eq!(result.config.post_paste_command, None);
+        assert_eq!(result.config.pre_paste_failure, HookFailure::Abort);
+
+        let result = validate_toml("pre_paste_failure = \"retry\"\n").unwrap();
+        assert_eq!(result.config.pre_paste_failure, HookFailure::Warn);
+        assert_eq!(
+            result.errors[0].message,
+            "unknown hook failure policy 'retry'"
+        );
+    }
+
+    #[test]
+    fn test_toml_headless() {
+        assert!(!validate_toml("").unwrap().config.headless);
+        let result = validate_toml("headless = \"yes\"\n").unwrap();
+        assert!(!result.config.headless);
+        assert_eq!(result.errors[0].path, "headless");
+        assert!(validate_toml("headless = true\n").unwrap().config.headless);
+    }
+
+    #[test]
+    fn test_toml_update_check() {
+        assert!(!validate_toml("").unwrap().config.update_check);
+        assert!(
+            validate_toml("update_check = true\n")
+                .unwrap()
+                .config
+                .update_check
+        );
+        let result = validate_toml("update_check = 1\n").unwrap();
+        assert_eq!(result.errors[0].path, "update_check");
+    }
+
+    #[test]
+    fn test_toml_sound_feedback() {
+        assert!(!validate_toml("").unwrap().config.sound_feedback);
+        let result = validate_toml("sound_feedback = true\n").unwrap();
+        assert!(result.config.sound_feedback);
+        let result = validate_toml("sound_feedback = \"loud\"\n").unwrap();
+        assert!(!result.config.sound_feedback);
+        assert_eq!(result.errors[0].path, "sound_feedback");
+    }
+
+    #[test]
+    fn test_toml_launch_at_login() {
+        assert!(!validate_toml("").unwrap().config.launch_at_login);
+        let result = validate_toml("launch_at_login = true\n").unwrap();
+        assert!(result.config.launch_at_login);
+        let result = validate_toml("launch_at_login = \"yes\"\n").unwrap();
+        assert!(!result.config.launch_at_login);
+        assert_eq!(result.errors[0].path, "launch_at_login");
+    }
+
+    #[test]
+    fn test_toml_confirm_before_typing() {
+        assert!(!validate_toml("").unwrap().config.confirm_before_typing);
+        let result = validate_toml("confirm_before_typing = true\n").unwrap();
+        assert!(result.config.confirm_before_typing);
+        let result = validate_toml("confirm_before_typing = 1\n").unwrap();
+        assert!(!result.config.confirm_before_typing);
+        assert_eq!(result.errors[0].path, "confirm_before_typing");
+    }
+
+    #[test]
+    fn test_toml_snippets() {
+        let result = validate_toml(
+            r#"
+[snippets]
+sig = "Best,\nPasta"
+"shell header" = '''
+#!/bin/sh
+set -eu
+'''
+bad = 3
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result.config.snippets.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "shell header".to_string(),
+                    "#!/bin/sh\nset -eu\n".to_string()
+                ),
+                ("sig".to_string(), "Best,\nPasta".to_string()),
+            ]
+        );
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].path, "snippets.bad");
+
+        let result = validate_toml("snippets = \"sig\"\n").unwrap();
+        assert!(result.config.snippets.is_empty());
+        assert_eq!(result.errors[0].path, "snippets");
+    }
+
+    #[test]
+    fn test_toml_abort_on_user_input() {
+        assert!(!validate_toml("").unwrap().config.abort_on_user_input);
+        let result = validate_toml("abort_on_user_input = true\n").unwrap();
+        assert!(result.config.abort_on_user_input);
+        let result = validate_toml("abort_on_user_input = \"yes\"\n").unwrap();
+        assert!(!result.config.abort_on_user_input);
+        assert_eq!(result.errors[0].path, "abort_on_user_input");
+    }
+
+    #[test]
+    fn test_toml_http_api() {
+        let result = validate_toml("[http_api]\nenabled = true\ntoken = \"abc\"\n").unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(
+            result.config.http_api,
+            HttpApiConfig {
+                enabled: true,
+                token: "abc".to_string(),
+                ..HttpApiConfig::default()
+            }
+        );
+
+        let result = validate_toml("[http_api]\nenabled = true\nport = 99999\n").unwrap();
+        assert!(!result.config.http_api.enabled);
+        assert_eq!(result.errors[0].path, "http_api");
+    }
+
+    #[test]
+    fn test_toml_bad_window_state() {
+        let result = validate_toml("[window_state]\nx = 1\n").unwrap();
+        assert_eq!(result.config.window_state, None);
+        assert_eq!(result.errors[0].path, "window_state");
+    }
+
+    #[test]
+    fn test_toml_syntax_error() {
+        assert!(validate_toml("typing_speed = [not valid").is_err());
+    }
+
+    #[test]
+    fn test_field_error_display_and_serialization() {
+        let error = FieldError::invalid("typingSpeed", "x", "unknown typing speed 'x'");
+        assert_eq!(error.to_string(), "typingSpeed: unknown typing speed 'x'");
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"path":"typingSpeed","message":"unknown typing speed 'x'","received":"x"}"#
+        );
+    }
+}
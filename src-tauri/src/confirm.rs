@@ -0,0 +1,114 @@
+/// Pastes held back until the user confirms them
+///
+/// With `confirm_before_typing` set, a `paste_clipboard` request reads the
+/// clipboard and waits here while a small window shows what it would type.
+/// Confirming types that snapshot, so copying something else meanwhile
+/// changes nothing. A newer request replaces the one waiting.
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::{events::PasteRequest, helpers::group_thousands};
+
+/// Characters of the clipboard the confirmation window shows
+pub const PREVIEW_CHARS: usize = 500;
+
+/// A paste waiting for Type or Cancel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingPaste {
+    pub text: String,
+    /// How the paste was asked for, applied once it is confirmed
+    pub request: PasteRequest,
+}
+
+/// What the confirmation window shows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PastePreview {
+    /// The first `PREVIEW_CHARS` characters
+    pub preview: String,
+    pub chars: usize,
+    /// `chars` grouped for display, e.g. `12,345`
+    pub chars_label: String,
+    /// Whether `preview` is only the start of the text
+    pub truncated: bool,
+}
+
+impl PendingPaste {
+    pub fn preview(&self) -> PastePreview {
+        let chars = self.text.chars().count();
+        PastePreview {
+            preview: self.text.chars().take(PREVIEW_CHARS).collect(),
+            chars,
+            chars_label: group_thousands(chars),
+            truncated: chars > PREVIEW_CHARS,
+        }
+    }
+}
+
+/// The one paste waiting for confirmation, if any
+#[derive(Default)]
+pub struct PendingConfirmation {
+    pending: Mutex<Option<PendingPaste>>,
+}
+
+impl PendingConfirmation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold `paste` until it is confirmed, returning whether it replaced another
+    pub fn hold(&self, paste: PendingPaste) -> bool {
+        self.pending.lock().unwrap().replace(paste).is_some()
+    }
+
+    /// The waiting paste, which is then no longer waiting
+    pub fn take(&self) -> Option<PendingPaste> {
+        self.pending.lock().unwrap().take()
+    }
+
+    pub fn preview(&self) -> Option<PastePreview> {
+        self.pending
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(PendingPaste::preview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paste(text: &str) -> PendingPaste {
+        PendingPaste {
+            text: text.to_string(),
+            request: PasteRequest::default(),
+        }
+    }
+
+    #[test]
+    fn test_newer_paste_replaces_the_waiting_one() {
+        let pending = PendingConfirmation::new();
+        assert!(!pending.hold(paste("first")));
+        assert!(pending.hold(paste("second")));
+
+        assert_eq!(pending.take(), Some(paste("second")));
+        assert_eq!(pending.take(), None);
+        assert_eq!(pending.preview(), None);
+    }
+
+    #[test]
+    fn test_preview_counts_characters() {
+        let short = paste("héllo").preview();
+        assert_eq!(short.preview, "héllo");
+        assert_eq!(short.chars, 5);
+        assert!(!short.truncated);
+
+        let long = paste(&"é".repeat(12_345)).preview();
+        assert_eq!(long.preview.chars().count(), PREVIEW_CHARS);
+        assert_eq!(long.chars, 12_345);
+        assert_eq!(long.chars_label, "12,345");
+        assert!(long.truncated);
+    }
+}
@@ -0,0 +1,264 @@
+/// Control commands for scripts and window-manager keybindings
+///
+/// With `control_socket = true` the instance socket also accepts one JSON
+/// object per line, e.g. `{"command":"type","text":"hi"}`, and answers each
+/// with a `CliReply` line. Commands go through the same guarded paths as the
+/// Tauri commands.
+use serde::Deserialize;
+
+use crate::{
+    cli::{CliCommand, CliReply, TextSource},
+    error::PastaError,
+    typing_state::{CancelReport, TypingState},
+};
+
+/// A command received on the control socket
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ControlRequest {
+    Paste,
+    Cancel,
+    Pause,
+    Resume,
+    Status,
+    Quit,
+    Type { text: String },
+}
+
+impl ControlRequest {
+    /// The control command a command line verb stands for
+    pub fn from_cli(command: &CliCommand) -> Option<Self> {
+        Some(match command {
+            CliCommand::Paste => ControlRequest::Paste,
+            CliCommand::Cancel => ControlRequest::Cancel,
+            CliCommand::Status { .. } => ControlRequest::Status,
+            CliCommand::Quit => ControlRequest::Quit,
+            CliCommand::Type {
+                source: TextSource::Text(text),
+            } => ControlRequest::Type { text: text.clone() },
+            // The forwarding launch reads stdin before connecting
+            CliCommand::Type {
+                source: TextSource::Stdin,
+            } => return None,
+        })
+    }
+}
+
+/// What control commands act on, `AppState` in the app
+pub trait ControlTarget {
+    fn paste(&self) -> Result<(), PastaError>;
+    fn type_text(&self, text: &str) -> Result<(), PastaError>;
+    fn cancel(&self) -> CancelReport;
+    /// Hold the running operation, returning whether one was running
+    fn pause(&self) -> bool;
+    /// Continue a paused operation, returning whether one was paused
+    fn resume(&self) -> bool;
+    fn status(&self) -> TypingState;
+    /// Exit once the reply is on its way, running the shutdown sequence
+    fn quit(&self);
+}
+
+/// Run one command against `target`
+pub fn handle_request(target: &dyn ControlTarget, request: ControlRequest) -> CliReply {
+    match request {
+        ControlRequest::Paste => CliReply::from_result(target.paste(), "Pasting clipboard"),
+        ControlRequest::Type { text } => {
+            CliReply::from_result(target.type_text(&text), "Typing text")
+        }
+        ControlRequest::Cancel => {
+            if target.cancel().was_typing {
+                CliReply::ok("Cancelled typing")
+            } else {
+                CliReply::ok("Nothing was typing")
+            }
+        }
+        ControlRequest::Status => CliReply::status(target.status()),
+        ControlRequest::Quit => {
+            target.quit();
+            CliReply::ok("Quitting Pasta")
+        }
+        ControlRequest::Pause => {
+            if target.pause() {
+                CliReply::ok("Paused typing")
+            } else {
+                CliReply::ok("Nothing was typing")
+            }
+        }
+        ControlRequest::Resume => {
+            if target.resume() {
+                CliReply::ok("Resumed typing")
+            } else {
+                CliReply::ok("Nothing was paused")
+            }
+        }
+    }
+}
+
+/// Decode a request line and run it
+pub fn handle_line(target: &dyn ControlTarget, line: &str) -> CliReply {
+    match serde_json::from_str(line) {
+        Ok(request) => handle_request(target, request),
+        Err(e) => CliReply::error(format!("Invalid control command: {e}")),
+    }
+}
+
+/// Records calls and answers like an idle or busy `AppState`, for the
+/// control socket and HTTP tests
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct FakeTarget {
+    pub busy: bool,
+    pub calls: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl ControlTarget for FakeTarget {
+    fn paste(&self) -> Result<(), PastaError> {
+        self.calls.lock().unwrap().push("paste".to_string());
+        if self.busy {
+            Err(PastaError::Busy)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn type_text(&self, text: &str) -> Result<(), PastaError> {
+        self.calls.lock().unwrap().push(format!("type {text}"));
+        if text.is_empty() {
+            Err(PastaError::Empty)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn cancel(&self) -> CancelReport {
+        self.calls.lock().unwrap().push("cancel".to_string());
+        CancelReport::from_state(&self.status())
+    }
+
+    fn pause(&self) -> bool {
+        self.calls.lock().unwrap().push("pause".to_string());
+        self.busy
+    }
+
+    fn resume(&self) -> bool {
+        self.calls.lock().unwrap().push("resume".to_string());
+        self.busy
+    }
+
+    fn status(&self) -> TypingState {
+        TypingState {
+            is_typing: self.busy,
+            ..TypingState::default()
+        }
+    }
+
+    fn quit(&self) {
+        self.calls.lock().unwrap().push("quit".to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::CliOutcome;
+
+    #[test]
+    fn test_commands_reach_the_target() {
+        let target = FakeTarget::default();
+        handle_line(&target, r#"{"command":"paste"}"#);
+        handle_line(&target, r#"{"command":"type","text":"hi"}"#);
+        handle_line(&target, r#"{"command":"cancel"}"#);
+        handle_line(&target, r#"{"command":"pause"}"#);
+        handle_line(&target, r#"{"command":"resume"}"#);
+        handle_line(&target, r#"{"command":"quit"}"#);
+
+        assert_eq!(
+            *target.calls.lock().unwrap(),
+            ["paste", "type hi", "cancel", "pause", "resume", "quit"]
+        );
+    }
+
+    #[test]
+    fn test_replies_reflect_outcome() {
+        let busy = FakeTarget {
+            busy: true,
+            ..FakeTarget::default()
+        };
+        assert_eq!(
+            handle_line(&busy, r#"{"command":"paste"}"#).outcome,
+            CliOutcome::Busy
+        );
+        assert_eq!(
+            handle_line(&busy, r#"{"command":"cancel"}"#).message,
+            "Cancelled typing"
+        );
+        assert_eq!(
+            handle_line(&busy, r#"{"command":"pause"}"#).message,
+            "Paused typing"
+        );
+        let status = handle_line(&busy, r#"{"command":"status"}"#);
+        assert!(status.state.unwrap().is_typing);
+
+        let idle = FakeTarget::default();
+        assert_eq!(
+            handle_line(&idle, r#"{"command":"type","text":""}"#).outcome,
+            CliOutcome::Error
+        );
+        assert_eq!(
+            handle_line(&idle, r#"{"command":"resume"}"#).message,
+            "Nothing was paused"
+        );
+    }
+
+    #[test]
+    fn test_invalid_commands_are_rejected() {
+        let target = FakeTarget::default();
+        for line in ["{}", r#"{"command":"format"}"#, r#"{"command":"type"}"#] {
+            let reply = handle_line(&target, line);
+            assert_eq!(reply.outcome, CliOutcome::Error, "{line}");
+            assert!(reply.message.starts_with("Invalid control command"));
+        }
+        assert!(target.calls.lock().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_protocol_over_the_socket() {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            os::unix::net::UnixStream,
+            sync::Arc,
+        };
+
+        use tempfile::tempdir;
+
+        use crate::single_instance::{self, Instance, Request};
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.sock");
+        let Instance::Primary(listener) = single_instance::acquire(&path, &[]).unwrap() else {
+            panic!("first launch should be primary");
+        };
+        let target = Arc::new(FakeTarget::default());
+        let served = target.clone();
+        listener.serve(move |request| match request {
+            Request::Control(line) => handle_line(&*served, &line).encode(),
+            Request::Launch(_) => "launch".to_string(),
+        });
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"{\"command\":\"type\",\"text\":\"a\\nb\"}\n{\"command\":\"status\"}\n")
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(CliReply::decode(line.trim_end()).message, "Typing text");
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(CliReply::decode(line.trim_end()).message, "Idle");
+
+        assert_eq!(*target.calls.lock().unwrap(), ["type a\nb"]);
+    }
+}
@@ -0,0 +1,212 @@
+/// Countdown between triggering a paste and typing, to focus the target field
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Longest countdown `paste_delay_secs` accepts
+pub const MAX_PASTE_DELAY_SECS: u64 = 30;
+
+/// How often the cancellation flag is checked while counting down
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time source for the countdown, faked in tests
+pub trait Clock: Send + Sync {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+/// Sleeps on the tokio timer
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+type TickListener = Box<dyn Fn(u64) + Send + Sync>;
+type FinishListener = Box<dyn Fn() + Send + Sync>;
+
+/// Waits `secs` seconds, telling `on_tick` how many are left each second
+pub struct Countdown {
+    secs: u64,
+    clock: Arc<dyn Clock>,
+    on_tick: TickListener,
+    on_finish: Option<FinishListener>,
+}
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl Countdown {
+    pub fn new<F>(secs: u64, on_tick: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        Self {
+            secs,
+            clock: Arc::new(TokioClock),
+            on_tick: Box::new(on_tick),
+            on_finish: None,
+        }
+    }
+
+    /// Also call `on_finish` once the countdown ends, run out or cancelled
+    pub fn on_finish<F>(mut self, on_finish: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_finish = Some(Box::new(on_finish));
+        self
+    }
+
+    /// No delay at all
+    pub fn none() -> Self {
+        Self::new(0, |_| {})
+    }
+
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.secs == 0
+    }
+
+    /// Count down, returning `false` if `cancellation_flag` was set meanwhile
+    ///
+    /// A zero delay returns immediately without touching the clock.
+    pub async fn run(&self, cancellation_flag: &AtomicBool) -> bool {
+        if self.is_none() {
+            return true;
+        }
+        let finished = self.count_down(cancellation_flag).await;
+        if let Some(on_finish) = &self.on_finish {
+            on_finish();
+        }
+        finished
+    }
+
+    async fn count_down(&self, cancellation_flag: &AtomicBool) -> bool {
+        let polls_per_tick =
+            (Duration::from_secs(1).as_millis() / CANCEL_POLL_INTERVAL.as_millis()) as u32;
+
+        for remaining in (1..=self.secs).rev() {
+            (self.on_tick)(remaining);
+            for _ in 0..polls_per_tick {
+                if cancellation_flag.load(Ordering::Relaxed) {
+                    return false;
+                }
+                self.clock.sleep(CANCEL_POLL_INTERVAL).await;
+            }
+        }
+        !cancellation_flag.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Returns at once, recording the time slept; can cancel after a while
+    #[derive(Default)]
+    struct FakeClock {
+        slept: Mutex<Duration>,
+        cancel_after: Option<(Duration, Arc<AtomicBool>)>,
+    }
+
+    impl FakeClock {
+        fn slept(&self) -> Duration {
+            *self.slept.lock().unwrap()
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn sleep(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+            let mut slept = self.slept.lock().unwrap();
+            *slept += duration;
+            if let Some((after, flag)) = &self.cancel_after {
+                if *slept >= *after {
+                    flag.store(true, Ordering::Relaxed);
+                }
+            }
+            Box::pin(std::future::ready(()))
+        }
+    }
+
+    fn recording_countdown(secs: u64, clock: Arc<FakeClock>) -> (Countdown, Arc<Mutex<Vec<u64>>>) {
+        let ticks = Arc::new(Mutex::new(Vec::new()));
+        let recorded = ticks.clone();
+        let countdown = Countdown::new(secs, move |remaining| {
+            recorded.lock().unwrap().push(remaining);
+        })
+        .with_clock(clock);
+        (countdown, ticks)
+    }
+
+    #[tokio::test]
+    async fn test_ticks_every_second() {
+        let clock = Arc::new(FakeClock::default());
+        let (countdown, ticks) = recording_countdown(3, clock.clone());
+
+        assert!(countdown.run(&AtomicBool::new(false)).await);
+        assert_eq!(*ticks.lock().unwrap(), [3, 2, 1]);
+        assert_eq!(clock.slept(), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_stops_the_countdown() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let clock = Arc::new(FakeClock {
+            cancel_after: Some((Duration::from_millis(1500), flag.clone())),
+            ..FakeClock::default()
+        });
+        let (countdown, ticks) = recording_countdown(5, clock.clone());
+
+        assert!(!countdown.run(&flag).await);
+        assert_eq!(*ticks.lock().unwrap(), [5, 4]);
+        assert_eq!(clock.slept(), Duration::from_millis(1500));
+    }
+
+    #[tokio::test]
+    async fn test_finish_listener_runs_once_either_way() {
+        for cancel in [false, true] {
+            let finished = Arc::new(Mutex::new(0));
+            let counter = finished.clone();
+            let (countdown, _ticks) = recording_countdown(2, Arc::new(FakeClock::default()));
+            let countdown = countdown.on_finish(move || *counter.lock().unwrap() += 1);
+
+            assert_eq!(countdown.run(&AtomicBool::new(cancel)).await, !cancel);
+            assert_eq!(*finished.lock().unwrap(), 1);
+        }
+
+        // Nothing to finish without a delay
+        let finished = Arc::new(Mutex::new(0));
+        let counter = finished.clone();
+        let countdown = Countdown::none().on_finish(move || *counter.lock().unwrap() += 1);
+        assert!(countdown.run(&AtomicBool::new(false)).await);
+        assert_eq!(*finished.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_delay_is_bypassed() {
+        let clock = Arc::new(FakeClock::default());
+        let (countdown, ticks) = recording_countdown(0, clock.clone());
+
+        assert!(countdown.run(&AtomicBool::new(true)).await);
+        assert!(ticks.lock().unwrap().is_empty());
+        assert_eq!(clock.slept(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,168 @@
+/// Coalescing of paste triggers that fire twice in quick succession
+///
+/// A sticky hotkey or a double-emitted tray event would otherwise type the
+/// clipboard twice, back to back when pastes are queued.
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+/// Default `paste_debounce_ms`
+pub const DEFAULT_PASTE_DEBOUNCE_MS: u64 = 300;
+
+/// Longest window `paste_debounce_ms` accepts
+pub const MAX_PASTE_DEBOUNCE_MS: u64 = 5000;
+
+/// Where a paste was triggered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasteSource {
+    #[default]
+    Tray,
+    Command,
+    Control,
+    /// A scheduled paste that came due
+    Schedule,
+}
+
+/// The last paste that was let through
+#[derive(Debug, Clone, Copy)]
+struct Trigger {
+    source: PasteSource,
+    content: u64,
+    at: Instant,
+}
+
+/// Lets a paste through unless the same one was just let through
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    last: Mutex<Option<Trigger>>,
+    coalesced: AtomicU64,
+}
+
+impl Debouncer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a paste of `content` from `source` at `now` should go ahead
+    ///
+    /// Only a repeat of the same source and content within `window` of the
+    /// last accepted paste is dropped, so distinct pastes still queue.
+    pub fn accept(
+        &self,
+        source: PasteSource,
+        content: &str,
+        window: Duration,
+        now: Instant,
+    ) -> bool {
+        let trigger = Trigger {
+            source,
+            content: content_hash(content),
+            at: now,
+        };
+        let mut last = self.last.lock().unwrap();
+        if let Some(previous) = *last {
+            let repeat = previous.source == trigger.source && previous.content == trigger.content;
+            if repeat && now.saturating_duration_since(previous.at) < window {
+                let coalesced = self.coalesced.fetch_add(1, Ordering::Relaxed) + 1;
+                info!("Dropped a repeated {source:?} paste ({coalesced} so far)");
+                return false;
+            }
+        }
+        *last = Some(trigger);
+        true
+    }
+
+    /// How many pastes were dropped as repeats
+    #[cfg(test)]
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+/// A paste trigger to check against `debouncer`
+pub struct Debounce {
+    pub debouncer: Arc<Debouncer>,
+    pub source: PasteSource,
+    pub window: Duration,
+}
+
+impl Debounce {
+    /// Whether a paste of `content` should go ahead right now
+    pub fn accept(&self, content: &str) -> bool {
+        self.debouncer
+            .accept(self.source, content, self.window, Instant::now())
+    }
+}
+
+/// Compares content without keeping a copy of it around
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(300);
+
+    #[test]
+    fn test_repeat_within_window_is_dropped() {
+        let debouncer = Debouncer::new();
+        let start = Instant::now();
+
+        assert!(debouncer.accept(PasteSource::Tray, "hello", WINDOW, start));
+        for ms in [1, 50, 299] {
+            let at = start + Duration::from_millis(ms);
+            assert!(
+                !debouncer.accept(PasteSource::Tray, "hello", WINDOW, at),
+                "{ms}ms"
+            );
+        }
+        assert_eq!(debouncer.coalesced(), 3);
+    }
+
+    #[test]
+    fn test_repeat_at_window_boundary_goes_ahead() {
+        let debouncer = Debouncer::new();
+        let start = Instant::now();
+
+        assert!(debouncer.accept(PasteSource::Tray, "hello", WINDOW, start));
+        assert!(debouncer.accept(PasteSource::Tray, "hello", WINDOW, start + WINDOW));
+        // The window restarts from the paste that went ahead
+        let just_after = start + WINDOW + Duration::from_millis(1);
+        assert!(!debouncer.accept(PasteSource::Tray, "hello", WINDOW, just_after));
+    }
+
+    #[test]
+    fn test_distinct_pastes_are_not_coalesced() {
+        let debouncer = Debouncer::new();
+        let start = Instant::now();
+        let soon = start + Duration::from_millis(10);
+
+        assert!(debouncer.accept(PasteSource::Tray, "first", WINDOW, start));
+        assert!(debouncer.accept(PasteSource::Tray, "second", WINDOW, soon));
+        assert!(debouncer.accept(PasteSource::Control, "second", WINDOW, soon));
+        assert_eq!(debouncer.coalesced(), 0);
+    }
+
+    #[test]
+    fn test_zero_window_accepts_everything() {
+        let debouncer = Debouncer::new();
+        let now = Instant::now();
+
+        assert!(debouncer.accept(PasteSource::Tray, "hello", Duration::ZERO, now));
+        assert!(debouncer.accept(PasteSource::Tray, "hello", Duration::ZERO, now));
+    }
+}
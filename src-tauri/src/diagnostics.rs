@@ -0,0 +1,168 @@
+/// Non-sensitive diagnostics users can attach to bug reports
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    config::{Config, ConfigDto},
+    logs,
+    platform::{DisplayServer, PlatformProbe, WaylandSupport},
+};
+
+/// How many log lines are included
+const DIAGNOSTICS_LOG_LINES: usize = 50;
+
+/// Everything "Copy diagnostics" puts on the clipboard
+///
+/// Never contains clipboard or typed content: the config is the same
+/// snapshot the settings window sees and log lines go through the log
+/// viewer's redaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub app_version: String,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub keyboard_backend: &'static str,
+    pub display_server: Option<DisplayServer>,
+    /// Whether the input emulation backend could connect, `None` if unknown
+    pub input_emulation_available: Option<bool>,
+    pub wayland_support: WaylandSupport,
+    pub accessibility_trusted: Option<bool>,
+    pub config: ConfigDto,
+    pub has_saved_window_state: bool,
+    /// Most recent log lines, or why they are unavailable
+    pub recent_logs: Result<Vec<String>, String>,
+}
+
+/// Input method enigo uses on this platform
+pub fn keyboard_backend() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "enigo (CoreGraphics events)"
+    } else if cfg!(target_os = "windows") {
+        "enigo (SendInput)"
+    } else {
+        "enigo (X11)"
+    }
+}
+
+/// Gather the report from its sources
+pub fn collect_diagnostics(
+    app_version: &str,
+    probe: &dyn PlatformProbe,
+    config: &Config,
+    log_file: Option<&Path>,
+) -> DiagnosticsReport {
+    let recent_logs = match log_file {
+        Some(path) => logs::read_tail(path, DIAGNOSTICS_LOG_LINES)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display())),
+        None => Err("File logging is disabled".to_string()),
+    };
+
+    let display_server = probe.display_server();
+    let input_emulation_available = probe.input_emulation_available();
+
+    DiagnosticsReport {
+        app_version: app_version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        keyboard_backend: keyboard_backend(),
+        display_server,
+        input_emulation_available,
+        wayland_support: WaylandSupport::detect(display_server, input_emulation_available),
+        accessibility_trusted: probe.accessibility_trusted(),
+        config: ConfigDto::from(config.clone()),
+        has_saved_window_state: config.window_state.is_some(),
+        recent_logs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::keyboard::TypingSpeed;
+
+    struct FakeProbe;
+
+    impl PlatformProbe for FakeProbe {
+        fn accessibility_trusted(&self) -> Option<bool> {
+            Some(false)
+        }
+
+        fn display_server(&self) -> Option<DisplayServer> {
+            Some(DisplayServer::Wayland)
+        }
+
+        fn input_emulation_available(&self) -> Option<bool> {
+            Some(false)
+        }
+    }
+
+    #[test]
+    fn test_every_section_is_populated() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("pasta.log");
+        fs::write(&log, "INFO pasta: Starting Pasta\n").unwrap();
+        let config = Config {
+            typing_speed: TypingSpeed::Fast,
+            ..Config::default()
+        };
+
+        let report = collect_diagnostics("1.2.3", &FakeProbe, &config, Some(&log));
+
+        assert_eq!(report.app_version, "1.2.3");
+        assert!(!report.os.is_empty());
+        assert!(!report.arch.is_empty());
+        assert!(report.keyboard_backend.starts_with("enigo"));
+        assert_eq!(report.display_server, Some(DisplayServer::Wayland));
+        assert_eq!(report.input_emulation_available, Some(false));
+        assert_eq!(report.wayland_support, WaylandSupport::Unavailable);
+        assert_eq!(report.accessibility_trusted, Some(false));
+        assert_eq!(report.config.typing_speed, "fast");
+        assert!(!report.has_saved_window_state);
+        assert_eq!(
+            report.recent_logs,
+            Ok(vec!["INFO pasta: Starting Pasta".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_log_content_is_redacted() {
+        let dir = tempdir().unwrap();
+        let log = dir.path().join("pasta.log");
+        fs::write(
+            &log,
+            "INFO pasta: Paste clipboard event received\nDEBUG pasta: [content] my secret password\n",
+        )
+        .unwrap();
+
+        let report = collect_diagnostics("1.0.0", &FakeProbe, &Config::default(), Some(&log));
+        let json = serde_json::to_string(&report).unwrap();
+
+        assert!(!json.contains("my secret password"));
+        assert!(json.contains(logs::REDACTED_LINE));
+    }
+
+    #[test]
+    fn test_missing_log_file_is_reported() {
+        let report = collect_diagnostics("1.0.0", &FakeProbe, &Config::default(), None);
+        assert_eq!(
+            report.recent_logs,
+            Err("File logging is disabled".to_string())
+        );
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let report = collect_diagnostics("1.0.0", &FakeProbe, &Config::default(), None);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["appVersion"], "1.0.0");
+        assert_eq!(json["displayServer"], "wayland");
+        assert_eq!(json["config"]["typingSpeed"], "normal");
+        assert_eq!(json["recentLogs"]["Err"], "File logging is disabled");
+    }
+}
@@ -0,0 +1,264 @@
+/// Typing into elevated windows on Windows
+///
+/// Windows silently drops synthetic input aimed at a process with a higher
+/// integrity level than ours (UIPI), and at the secure desktop used by UAC
+/// prompts and the lock screen. Checking before typing turns that into an
+/// explained `PastaError::ElevatedTarget` instead of text that never arrives.
+use crate::error::PastaError;
+
+/// Name shown when the secure desktop has the input
+pub const SECURE_DESKTOP: &str = "Secure desktop";
+
+/// The process that owns the foreground window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForegroundProcess {
+    pub name: String,
+    /// `None` if its token couldn't be queried
+    pub elevated: Option<bool>,
+}
+
+/// What the elevation check asks the OS
+pub trait ElevationProbe {
+    /// Whether Pasta itself runs elevated, `None` if unknown
+    fn current_elevated(&self) -> Option<bool>;
+    /// Whether a UAC prompt or the lock screen has the input
+    fn secure_desktop_active(&self) -> bool;
+    /// Owner of the foreground window, `None` if there is none
+    fn foreground(&self) -> Option<ForegroundProcess>;
+}
+
+/// Fail if Windows would drop what Pasta types into the foreground window
+///
+/// Anything that can't be determined is allowed, so a failing probe never
+/// blocks typing.
+pub fn check_target(probe: &dyn ElevationProbe) -> Result<(), PastaError> {
+    if probe.secure_desktop_active() {
+        return Err(PastaError::ElevatedTarget {
+            process_name: SECURE_DESKTOP.to_string(),
+        });
+    }
+    if probe.current_elevated() != Some(false) {
+        return Ok(());
+    }
+    match probe.foreground() {
+        Some(ForegroundProcess {
+            name,
+            elevated: Some(true),
+        }) => Err(PastaError::ElevatedTarget { process_name: name }),
+        _ => Ok(()),
+    }
+}
+
+mod ffi {
+    use std::ffi::c_void;
+
+    pub type Handle = *mut c_void;
+
+    pub const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+    pub const TOKEN_QUERY: u32 = 0x0008;
+    /// `TOKEN_INFORMATION_CLASS::TokenElevation`
+    pub const TOKEN_ELEVATION: u32 = 20;
+    pub const DESKTOP_SWITCHDESKTOP: u32 = 0x0100;
+
+    #[link(name = "user32")]
+    extern "system" {
+        pub fn GetForegroundWindow() -> Handle;
+        pub fn GetWindowThreadProcessId(window: Handle, process_id: *mut u32) -> u32;
+        pub fn OpenInputDesktop(flags: u32, inherit: i32, access: u32) -> Handle;
+        pub fn CloseDesktop(desktop: Handle) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn GetCurrentProcess() -> Handle;
+        pub fn OpenProcess(access: u32, inherit: i32, process_id: u32) -> Handle;
+        pub fn CloseHandle(handle: Handle) -> i32;
+        pub fn QueryFullProcessImageNameW(
+            process: Handle,
+            flags: u32,
+            name: *mut u16,
+            size: *mut u32,
+        ) -> i32;
+    }
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        pub fn OpenProcessToken(process: Handle, access: u32, token: *mut Handle) -> i32;
+        pub fn GetTokenInformation(
+            token: Handle,
+            class: u32,
+            info: *mut c_void,
+            length: u32,
+            returned: *mut u32,
+        ) -> i32;
+    }
+}
+
+/// Whether the process behind `process` has an elevated token
+fn process_elevated(process: ffi::Handle) -> Option<bool> {
+    let mut token = std::ptr::null_mut();
+    // SAFETY: `token` is only used after the call succeeds and is closed below
+    if unsafe { ffi::OpenProcessToken(process, ffi::TOKEN_QUERY, &mut token) } == 0 {
+        return None;
+    }
+    let mut elevated: u32 = 0;
+    let mut returned = 0;
+    // SAFETY: `TOKEN_ELEVATION` is a single DWORD
+    let ok = unsafe {
+        ffi::GetTokenInformation(
+            token,
+            ffi::TOKEN_ELEVATION,
+            (&mut elevated as *mut u32).cast(),
+            std::mem::size_of::<u32>() as u32,
+            &mut returned,
+        )
+    };
+    unsafe { ffi::CloseHandle(token) };
+    (ok != 0).then_some(elevated != 0)
+}
+
+/// File name of the executable behind `process`
+fn process_name(process: ffi::Handle) -> Option<String> {
+    let mut buffer = [0u16; 260];
+    let mut size = buffer.len() as u32;
+    // SAFETY: `size` holds the buffer length in characters
+    let ok = unsafe { ffi::QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut size) };
+    if ok == 0 {
+        return None;
+    }
+    let path = String::from_utf16_lossy(&buffer[..size as usize]);
+    Some(path.rsplit('\\').next().unwrap_or(&path).to_string())
+}
+
+/// Asks Windows about the foreground window and Pasta's own token
+pub struct SystemElevationProbe;
+
+impl ElevationProbe for SystemElevationProbe {
+    fn current_elevated(&self) -> Option<bool> {
+        // SAFETY: the pseudo handle needs no closing
+        process_elevated(unsafe { ffi::GetCurrentProcess() })
+    }
+
+    fn secure_desktop_active(&self) -> bool {
+        // Opening the input desktop is denied while it is the secure desktop
+        // SAFETY: the handle is closed when the call succeeds
+        let desktop = unsafe { ffi::OpenInputDesktop(0, 0, ffi::DESKTOP_SWITCHDESKTOP) };
+        if desktop.is_null() {
+            return true;
+        }
+        unsafe { ffi::CloseDesktop(desktop) };
+        false
+    }
+
+    fn foreground(&self) -> Option<ForegroundProcess> {
+        // SAFETY: plain queries; the process handle is closed below
+        unsafe {
+            let window = ffi::GetForegroundWindow();
+            if window.is_null() {
+                return None;
+            }
+            let mut process_id = 0;
+            ffi::GetWindowThreadProcessId(window, &mut process_id);
+            let process = ffi::OpenProcess(ffi::PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+            if process.is_null() {
+                return None;
+            }
+            let foreground = ForegroundProcess {
+                name: process_name(process).unwrap_or_else(|| format!("Process {process_id}")),
+                elevated: process_elevated(process),
+            };
+            ffi::CloseHandle(process);
+            Some(foreground)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeProbe {
+        current: Option<bool>,
+        secure_desktop: bool,
+        foreground: Option<ForegroundProcess>,
+    }
+
+    impl FakeProbe {
+        fn new(current: Option<bool>, target: Option<bool>) -> Self {
+            Self {
+                current,
+                secure_desktop: false,
+                foreground: Some(ForegroundProcess {
+                    name: "regedit.exe".to_string(),
+                    elevated: target,
+                }),
+            }
+        }
+    }
+
+    impl ElevationProbe for FakeProbe {
+        fn current_elevated(&self) -> Option<bool> {
+            self.current
+        }
+
+        fn secure_desktop_active(&self) -> bool {
+            self.secure_desktop
+        }
+
+        fn foreground(&self) -> Option<ForegroundProcess> {
+            self.foreground.clone()
+        }
+    }
+
+    #[test]
+    fn test_elevated_target_fails_fast() {
+        assert_eq!(
+            check_target(&FakeProbe::new(Some(false), Some(true))),
+            Err(PastaError::ElevatedTarget {
+                process_name: "regedit.exe".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_matching_or_higher_elevation_is_allowed() {
+        assert_eq!(
+            check_target(&FakeProbe::new(Some(false), Some(false))),
+            Ok(())
+        );
+        assert_eq!(
+            check_target(&FakeProbe::new(Some(true), Some(true))),
+            Ok(())
+        );
+        assert_eq!(
+            check_target(&FakeProbe::new(Some(true), Some(false))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_unknowns_are_allowed() {
+        assert_eq!(check_target(&FakeProbe::new(None, Some(true))), Ok(()));
+        assert_eq!(check_target(&FakeProbe::new(Some(false), None)), Ok(()));
+
+        let no_window = FakeProbe {
+            foreground: None,
+            ..FakeProbe::new(Some(false), None)
+        };
+        assert_eq!(check_target(&no_window), Ok(()));
+    }
+
+    #[test]
+    fn test_secure_desktop_fails_even_when_elevated() {
+        let probe = FakeProbe {
+            secure_desktop: true,
+            ..FakeProbe::new(Some(true), Some(false))
+        };
+        assert_eq!(
+            check_target(&probe),
+            Err(PastaError::ElevatedTarget {
+                process_name: SECURE_DESKTOP.to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,386 @@
+//! Errors from the paste pipeline, shaped so callers can match on the cause
+use std::{fmt, io};
+
+use serde::{Serialize, Serializer};
+
+use crate::{hooks::HookError, keyboard::MAX_CONSECUTIVE_FAILURES, platform};
+
+/// Reading the system clipboard failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardError {
+    /// The clipboard couldn't be opened at all
+    Unavailable(String),
+    /// The clipboard was opened but its text couldn't be read
+    Read(String),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardError::Unavailable(e) => write!(f, "Failed to create clipboard: {e}"),
+            ClipboardError::Read(e) => write!(f, "Failed to read clipboard: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// Reading or writing the settings or statistics file failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    /// The platform has no per-user config directory to keep files in
+    NoConfigDir,
+    /// The file or its directory couldn't be read or written
+    Io(String),
+    /// The contents couldn't be parsed or serialized
+    Format(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NoConfigDir => write!(f, "Could not determine config directory"),
+            StorageError::Io(e) => write!(f, "{e}"),
+            StorageError::Format(e) => write!(f, "Invalid file contents: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for StorageError {
+    fn from(e: toml::de::Error) -> Self {
+        StorageError::Format(e.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for StorageError {
+    fn from(e: toml::ser::Error) -> Self {
+        StorageError::Format(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        StorageError::Format(e.to_string())
+    }
+}
+
+/// Handing work to the keyboard worker failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypingError {
+    /// The worker thread is gone, e.g. it panicked
+    WorkerStopped,
+    /// Something is already queued or typing and queueing wasn't requested
+    Busy,
+    /// The OS would drop synthesized input (macOS accessibility)
+    PermissionMissing,
+    /// The worker stopped in a Wayland session, where enigo can't type
+    WaylandUnsupported,
+    /// The system rejected keystrokes until the worker gave up on the operation
+    InputRejected,
+    /// The keyboard backend couldn't be created when the worker started
+    Unavailable(String),
+}
+
+impl fmt::Display for TypingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypingError::WorkerStopped => write!(f, "Keyboard worker is not running"),
+            TypingError::Busy => write!(f, "Keyboard is busy"),
+            TypingError::PermissionMissing => write!(f, "Keyboard input is not permitted"),
+            TypingError::WaylandUnsupported => {
+                write!(f, "Keyboard input is not available on Wayland")
+            }
+            TypingError::InputRejected => write!(
+                f,
+                "The system rejected {MAX_CONSECUTIVE_FAILURES} keystrokes in a row, so typing \
+                 was stopped"
+            ),
+            TypingError::Unavailable(e) => write!(f, "Keyboard input could not start: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TypingError {}
+
+/// Why a paste or typing request failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PastaError {
+    Clipboard(ClipboardError),
+    Keyboard(TypingError),
+    /// There was no text to type
+    Empty,
+    /// The text is longer than the operation allows
+    TooLarge {
+        chars: usize,
+        max: usize,
+    },
+    /// Another typing operation is in progress
+    Busy,
+    /// macOS hasn't granted the accessibility permission
+    PermissionMissing,
+    /// Typing failed in a Wayland session without an input emulation path
+    WaylandUnsupported,
+    /// Windows would drop input aimed at this elevated process
+    ElevatedTarget {
+        process_name: String,
+    },
+    /// macOS drops typing while an app holds secure input
+    SecureInputActive {
+        owner: Option<String>,
+    },
+    /// The pre-paste command failed and `pre_paste_failure = "abort"`
+    Hook(HookError),
+    /// The settings file couldn't be saved
+    Config(StorageError),
+    /// The statistics file couldn't be saved
+    Statistics(StorageError),
+    /// A window the command needs couldn't be shown or reached
+    Window(String),
+    /// No snippet by this name in the config
+    UnknownSnippet(String),
+}
+
+impl PastaError {
+    /// Stable identifier the frontend can match on
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PastaError::Clipboard(_) => "clipboard",
+            PastaError::Keyboard(_) => "keyboard",
+            PastaError::Empty => "empty",
+            PastaError::TooLarge { .. } => "tooLarge",
+            PastaError::Busy => "busy",
+            PastaError::PermissionMissing => "permissionMissing",
+            PastaError::WaylandUnsupported => "waylandUnsupported",
+            PastaError::ElevatedTarget { .. } => "elevatedTarget",
+            PastaError::SecureInputActive { .. } => "secureInputActive",
+            PastaError::Hook(_) => "hook",
+            PastaError::Config(_) => "config",
+            PastaError::Statistics(_) => "statistics",
+            PastaError::Window(_) => "window",
+            PastaError::UnknownSnippet(_) => "unknownSnippet",
+        }
+    }
+}
+
+impl fmt::Display for PastaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PastaError::Clipboard(e) => write!(f, "{e}"),
+            PastaError::Keyboard(e) => write!(f, "Failed to type text: {e}"),
+            PastaError::Empty => write!(f, "Nothing to type"),
+            PastaError::TooLarge { chars, max } => {
+                write!(f, "Text is {chars} characters, the limit is {max}")
+            }
+            PastaError::Busy => write!(f, "Already typing, try again when it finishes"),
+            PastaError::PermissionMissing => write!(
+                f,
+                "Pasta is not allowed to type. Grant it Accessibility access in System \
+                 Settings → Privacy & Security → Accessibility"
+            ),
+            PastaError::WaylandUnsupported => write!(f, "{}", platform::WAYLAND_UNSUPPORTED),
+            PastaError::ElevatedTarget { process_name } => write!(
+                f,
+                "{process_name} is running as administrator, so Windows blocks typing into \
+                 it. Right-click Pasta and choose \"Run as administrator\" to type there"
+            ),
+            PastaError::SecureInputActive { owner } => write!(
+                f,
+                "{} has secure input turned on, so macOS blocks typing. Leave its password \
+                 field or turn off Secure Keyboard Entry, then paste again",
+                owner.as_deref().unwrap_or("Another app")
+            ),
+            PastaError::Hook(e) => write!(f, "Pre-paste command {e}, nothing was typed"),
+            PastaError::Config(e) => write!(f, "Failed to save settings: {e}"),
+            PastaError::Statistics(e) => write!(f, "Failed to save statistics: {e}"),
+            PastaError::Window(e) => write!(f, "Failed to show window: {e}"),
+            PastaError::UnknownSnippet(name) => {
+                write!(f, "No snippet named \"{name}\" in config.toml")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PastaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PastaError::Clipboard(e) => Some(e),
+            PastaError::Keyboard(e) => Some(e),
+            PastaError::Hook(e) => Some(e),
+            PastaError::Config(e) | PastaError::Statistics(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ClipboardError> for PastaError {
+    fn from(e: ClipboardError) -> Self {
+        PastaError::Clipboard(e)
+    }
+}
+
+impl From<TypingError> for PastaError {
+    fn from(e: TypingError) -> Self {
+        match e {
+            TypingError::Busy => PastaError::Busy,
+            TypingError::PermissionMissing => PastaError::PermissionMissing,
+            TypingError::WaylandUnsupported => PastaError::WaylandUnsupported,
+            e => PastaError::Keyboard(e),
+        }
+    }
+}
+
+/// Sent to the frontend as `{ kind, message, ...details }`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorPayload {
+    kind: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chars: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process_name: Option<String>,
+}
+
+impl Serialize for PastaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (chars, max) = match self {
+            PastaError::TooLarge { chars, max } => (Some(*chars), Some(*max)),
+            _ => (None, None),
+        };
+        let process_name = match self {
+            PastaError::ElevatedTarget { process_name } => Some(process_name.clone()),
+            PastaError::SecureInputActive { owner } => owner.clone(),
+            _ => None,
+        };
+        ErrorPayload {
+            kind: self.kind(),
+            message: self.to_string(),
+            chars,
+            max,
+            process_name,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            PastaError::from(ClipboardError::Read("Invalid format".to_string())).to_string(),
+            "Failed to read clipboard: Invalid format"
+        );
+        assert_eq!(
+            PastaError::from(TypingError::WorkerStopped).to_string(),
+            "Failed to type text: Keyboard worker is not running"
+        );
+        assert_eq!(PastaError::Empty.to_string(), "Nothing to type");
+        assert_eq!(
+            PastaError::Hook(HookError::Exit(Some(2))).to_string(),
+            "Pre-paste command exited with status 2, nothing was typed"
+        );
+        assert_eq!(
+            PastaError::UnknownSnippet("sig".to_string()).to_string(),
+            "No snippet named \"sig\" in config.toml"
+        );
+    }
+
+    #[test]
+    fn test_storage_errors() {
+        let io = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let error = PastaError::Config(StorageError::from(io));
+        assert_eq!(
+            error,
+            PastaError::Config(StorageError::Io("denied".to_string()))
+        );
+        assert_eq!(error.kind(), "config");
+        assert_eq!(error.to_string(), "Failed to save settings: denied");
+
+        let error = PastaError::Statistics(StorageError::NoConfigDir);
+        assert_eq!(error.kind(), "statistics");
+        assert_eq!(
+            error.to_string(),
+            "Failed to save statistics: Could not determine config directory"
+        );
+
+        let parse = "version = ".parse::<toml::Table>().unwrap_err();
+        assert!(matches!(StorageError::from(parse), StorageError::Format(_)));
+    }
+
+    #[test]
+    fn test_busy_keyboard_is_a_busy_paste() {
+        assert_eq!(PastaError::from(TypingError::Busy), PastaError::Busy);
+    }
+
+    #[test]
+    fn test_missing_permission_is_its_own_kind() {
+        let error = PastaError::from(TypingError::PermissionMissing);
+        assert_eq!(error, PastaError::PermissionMissing);
+        assert_eq!(error.kind(), "permissionMissing");
+        assert!(error.to_string().contains("Accessibility"));
+    }
+
+    #[test]
+    fn test_wayland_failure_explains_itself() {
+        let error = PastaError::from(TypingError::WaylandUnsupported);
+        assert_eq!(error, PastaError::WaylandUnsupported);
+        assert_eq!(error.kind(), "waylandUnsupported");
+        assert!(error.to_string().contains("X11"));
+    }
+
+    #[test]
+    fn test_source_is_the_wrapped_error() {
+        use std::error::Error;
+
+        let error = PastaError::from(ClipboardError::Unavailable("denied".to_string()));
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "Failed to create clipboard: denied"
+        );
+        assert!(PastaError::Busy.source().is_none());
+    }
+
+    #[test]
+    fn test_serialization() {
+        let json = serde_json::to_value(PastaError::Busy).unwrap();
+        assert_eq!(json["kind"], "busy");
+        assert!(json.get("chars").is_none());
+
+        let json = serde_json::to_value(PastaError::TooLarge {
+            chars: 3000,
+            max: 2000,
+        })
+        .unwrap();
+        assert_eq!(json["kind"], "tooLarge");
+        assert_eq!(json["chars"], 3000);
+        assert_eq!(json["max"], 2000);
+        assert_eq!(
+            json["message"],
+            "Text is 3000 characters, the limit is 2000"
+        );
+
+        let json = serde_json::to_value(PastaError::ElevatedTarget {
+            process_name: "regedit.exe".to_string(),
+        })
+        .unwrap();
+        assert_eq!(json["kind"], "elevatedTarget");
+        assert_eq!(json["processName"], "regedit.exe");
+        assert!(json["message"]
+            .as_str()
+            .unwrap()
+            .contains("Run as administrator"));
+    }
+}
@@ -0,0 +1,242 @@
+/// Payloads of the paste and typing events shared by the tray, the backend
+/// and the frontends
+///
+/// Fields are camelCase. Listeners tolerate missing and unknown fields, and a
+/// unit or unreadable payload counts as a plain request, so older emitters
+/// keep working.
+use log::warn;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    app_logic::PasteReport, debounce::PasteSource, helpers::group_thousands, repeat::Repeat,
+    typing_state::TypingState,
+};
+
+/// `paste_clipboard`: type the clipboard
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PasteRequest {
+    pub source: PasteSource,
+    /// Seconds to count down instead of `paste_delay_secs`
+    pub delay_override: Option<u64>,
+    /// Type the clipboard several times instead of once
+    pub repeat: Option<Repeat>,
+}
+
+/// `cancel_typing`: stop the running and queued operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CancelRequest {
+    pub source: PasteSource,
+}
+
+impl PasteRequest {
+    pub fn parse(payload: &str) -> Self {
+        parse_request("paste_clipboard", payload)
+    }
+}
+
+impl CancelRequest {
+    pub fn parse(payload: &str) -> Self {
+        parse_request("cancel_typing", payload)
+    }
+}
+
+/// Read a request payload, falling back to the default request
+fn parse_request<T: DeserializeOwned + Default>(event: &str, payload: &str) -> T {
+    match payload.trim() {
+        "" | "null" => T::default(),
+        payload => serde_json::from_str(payload).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable {event} payload {payload:?}: {e}");
+            T::default()
+        }),
+    }
+}
+
+/// `paste_countdown`: seconds left before a paste starts typing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteCountdown {
+    pub remaining: u64,
+    pub source: PasteSource,
+}
+
+/// `paste_too_large`: the clipboard is over `max_paste_chars`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteTooLarge {
+    pub chars: usize,
+    pub max: usize,
+    /// Whether the first `max` characters are typed, or nothing
+    pub truncated: bool,
+}
+
+impl PasteTooLarge {
+    /// Shown as a notification, since the paste may come from a hotkey
+    pub fn message(&self) -> String {
+        let chars = group_thousands(self.chars);
+        let max = group_thousands(self.max);
+        if self.truncated {
+            format!("The clipboard is {chars} characters, only the first {max} are typed")
+        } else {
+            format!(
+                "The clipboard is {chars} characters, over the {max} limit, so nothing was typed"
+            )
+        }
+    }
+}
+
+/// `typing_progress`: the worker's state after each chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingProgress {
+    #[serde(flatten)]
+    pub state: TypingState,
+    /// Share of the current operation typed, 0 while idle
+    pub percent: u8,
+}
+
+impl From<TypingState> for TypingProgress {
+    fn from(state: TypingState) -> Self {
+        let percent = match state.total {
+            0 => 0,
+            total => (state.typed.min(total) * 100 / total) as u8,
+        };
+        Self { state, percent }
+    }
+}
+
+/// `typing_finished`: what a paste did and where it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingFinished {
+    #[serde(flatten)]
+    pub report: PasteReport,
+    pub source: PasteSource,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_json_is_stable() {
+        let snapshots = [
+            (
+                serde_json::to_string(&PasteRequest {
+                    source: PasteSource::Tray,
+                    delay_override: Some(3),
+                    repeat: Some(Repeat::new(2, "{TAB}")),
+                }),
+                r#"{"source":"tray","delayOverride":3,"repeat":{"count":2,"separator":"{TAB}"}}"#,
+            ),
+            (
+                serde_json::to_string(&CancelRequest {
+                    source: PasteSource::Control,
+                }),
+                r#"{"source":"control"}"#,
+            ),
+            (
+                serde_json::to_string(&PasteCountdown {
+                    remaining: 2,
+                    source: PasteSource::Command,
+                }),
+                r#"{"remaining":2,"source":"command"}"#,
+            ),
+            (
+                serde_json::to_string(&PasteTooLarge {
+                    chars: 200_000,
+                    max: 100_000,
+                    truncated: true,
+                }),
+                r#"{"chars":200000,"max":100000,"truncated":true}"#,
+            ),
+            (
+                serde_json::to_string(&TypingProgress::from(TypingState {
+                    is_typing: true,
+                    typed: 50,
+                    total: 200,
+                    started_at: Some(42),
+                    ..TypingState::default()
+                })),
+                r#"{"isTyping":true,"isPaused":false,"typed":50,"total":200,"queueLen":0,"startedAt":42,"percent":25}"#,
+            ),
+            (
+                serde_json::to_string(&TypingFinished {
+                    report: PasteReport {
+                        typed: 5,
+                        skipped: 1,
+                        duration_ms: 30,
+                        transformed: false,
+                        cancelled: true,
+                    },
+                    source: PasteSource::Tray,
+                }),
+                r#"{"typed":5,"skipped":1,"durationMs":30,"transformed":false,"cancelled":true,"source":"tray"}"#,
+            ),
+        ];
+        for (json, expected) in snapshots {
+            assert_eq!(json.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_requests_round_trip() {
+        let request = PasteRequest {
+            source: PasteSource::Control,
+            delay_override: Some(5),
+            repeat: Some(Repeat::new(10, "{ENTER}")),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(PasteRequest::parse(&json), request);
+    }
+
+    #[test]
+    fn test_unit_and_partial_payloads_use_defaults() {
+        assert_eq!(PasteRequest::parse("null"), PasteRequest::default());
+        assert_eq!(PasteRequest::parse(""), PasteRequest::default());
+        assert_eq!(CancelRequest::parse("{}"), CancelRequest::default());
+        assert_eq!(
+            PasteRequest::parse(r#"{"delayOverride":2}"#),
+            PasteRequest {
+                source: PasteSource::Tray,
+                delay_override: Some(2),
+                repeat: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        assert_eq!(
+            PasteRequest::parse(r#"{"source":"command","transformOverride":"upper","x":[1]}"#),
+            PasteRequest {
+                source: PasteSource::Command,
+                delay_override: None,
+                repeat: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unreadable_payloads_use_defaults() {
+        for payload in ["{ not json", r#"{"source":"keyboard"}"#, "42"] {
+            assert_eq!(PasteRequest::parse(payload), PasteRequest::default());
+        }
+    }
+
+    #[test]
+    fn test_progress_percent() {
+        let progress = |typed, total| {
+            TypingProgress::from(TypingState {
+                typed,
+                total,
+                ..TypingState::default()
+            })
+            .percent
+        };
+        assert_eq!(progress(0, 0), 0);
+        assert_eq!(progress(1, 3), 33);
+        assert_eq!(progress(3, 3), 100);
+    }
+}
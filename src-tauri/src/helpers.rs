@@ -13,9 +13,33 @@ pub fn format_paste_error(error: &str) -> String {
     format!("Failed to handle paste: {error}")
 }
 
-/// Calculate startup delay duration
-pub fn get_startup_delay() -> Duration {
-    Duration::from_millis(100)
+/// `10000` as `10,000`
+pub fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Tray setup attempts made with backoff before the fallback
+pub const TRAY_SETUP_ATTEMPTS: u32 = 5;
+
+/// Wait before the last-resort tray setup once every attempt has failed
+pub const TRAY_SETUP_FALLBACK_DELAY: Duration = Duration::from_millis(100);
+
+/// Wait before tray setup attempt `attempt`, counting from 0
+///
+/// The first attempt runs at once, then 25ms doubling each retry.
+pub fn get_startup_delay(attempt: u32) -> Duration {
+    match attempt {
+        0 => Duration::ZERO,
+        n => Duration::from_millis(25) * 2u32.saturating_pow(n - 1),
+    }
 }
 
 /// Get activation policy name for macOS
@@ -40,6 +64,14 @@ mod tests {
         assert_eq!(msg, "Paste clipboard event received");
     }
 
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1000), "1,000");
+        assert_eq!(group_thousands(1234567), "1,234,567");
+    }
+
     #[test]
     fn test_format_paste_error() {
         let error = format_paste_error("Clipboard access denied");
@@ -51,8 +83,10 @@ mod tests {
 
     #[test]
     fn test_get_startup_delay() {
-        let delay = get_startup_delay();
-        assert_eq!(delay.as_millis(), 100);
+        let delays: Vec<u128> = (0..TRAY_SETUP_ATTEMPTS)
+            .map(|attempt| get_startup_delay(attempt).as_millis())
+            .collect();
+        assert_eq!(delays, [0, 25, 50, 100, 200]);
     }
 
     #[test]
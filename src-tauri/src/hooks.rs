@@ -0,0 +1,262 @@
+/// Shell commands run around a paste, from `pre_paste_command` and
+/// `post_paste_command`
+///
+/// Hooks never see the clipboard. The post hook is told how the paste went
+/// through `PASTA_CHARS` and `PASTA_RESULT` instead.
+use std::{
+    fmt,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_logic::PasteReport, error::PastaError};
+
+/// How long a hook may run before it is killed
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running hook is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What a failing pre-paste hook does to the paste
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFailure {
+    /// Log it and type anyway
+    #[default]
+    Warn,
+    /// Drop the paste with `PastaError::Hook`
+    Abort,
+}
+
+/// Why a hook failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookError {
+    /// The shell couldn't be started
+    Spawn(String),
+    /// Exited unsuccessfully, `None` if killed by a signal
+    Exit(Option<i32>),
+    TimedOut(Duration),
+}
+
+impl fmt::Display for HookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookError::Spawn(e) => write!(f, "failed to start: {e}"),
+            HookError::Exit(Some(code)) => write!(f, "exited with status {code}"),
+            HookError::Exit(None) => write!(f, "was killed by a signal"),
+            HookError::TimedOut(timeout) => {
+                write!(f, "was stopped after {}s", timeout.as_secs_f32())
+            }
+        }
+    }
+}
+
+impl std::error::Error for HookError {}
+
+/// Run `command` through the shell with `env` added, killing it after `timeout`
+///
+/// Blocks until the command exits; its output is discarded.
+pub fn run(command: &str, env: &[(&str, String)], timeout: Duration) -> Result<(), HookError> {
+    let mut child = shell(command)
+        .envs(env.iter().cloned())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| HookError::Spawn(e.to_string()))?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(HookError::Exit(status.code())),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(HookError::TimedOut(timeout));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(HookError::Spawn(e.to_string())),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// The hooks configured for one paste
+#[derive(Debug, Clone)]
+pub struct PasteHooks {
+    pub pre: Option<String>,
+    pub post: Option<String>,
+    pub pre_failure: HookFailure,
+    pub timeout: Duration,
+}
+
+impl Default for PasteHooks {
+    fn default() -> Self {
+        Self {
+            pre: None,
+            post: None,
+            pre_failure: HookFailure::default(),
+            timeout: HOOK_TIMEOUT,
+        }
+    }
+}
+
+impl PasteHooks {
+    /// Run the pre-paste hook, failing only when `pre_failure` is `Abort`
+    pub async fn before(&self) -> Result<(), PastaError> {
+        let Some(command) = self.pre.clone() else {
+            return Ok(());
+        };
+        let timeout = self.timeout;
+        let result = tokio::task::spawn_blocking(move || run(&command, &[], timeout))
+            .await
+            .unwrap_or_else(|e| Err(HookError::Spawn(e.to_string())));
+
+        match (result, self.pre_failure) {
+            (Ok(()), _) => Ok(()),
+            (Err(e), HookFailure::Warn) => {
+                warn!("Pre-paste command {e}, typing anyway");
+                Ok(())
+            }
+            (Err(e), HookFailure::Abort) => Err(PastaError::Hook(e)),
+        }
+    }
+
+    /// Start the post-paste hook in the background, told how the paste went
+    ///
+    /// Returns the task running it, if there is a hook.
+    pub fn after(
+        &self,
+        result: &Result<PasteReport, PastaError>,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        let command = self.post.clone()?;
+        let timeout = self.timeout;
+        let env = post_env(result);
+        Some(tokio::task::spawn_blocking(move || {
+            match run(&command, &env, timeout) {
+                Ok(()) => info!("Post-paste command finished"),
+                Err(e) => warn!("Post-paste command {e}"),
+            }
+        }))
+    }
+}
+
+/// `PASTA_CHARS` and `PASTA_RESULT` for the post hook
+fn post_env(result: &Result<PasteReport, PastaError>) -> Vec<(&'static str, String)> {
+    let (chars, outcome) = match result {
+        Ok(report) if report.cancelled => (report.typed, "cancelled"),
+        Ok(report) => (report.typed, "typed"),
+        Err(_) => (0, "failed"),
+    };
+    vec![
+        ("PASTA_CHARS", chars.to_string()),
+        ("PASTA_RESULT", outcome.to_string()),
+    ]
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Write an executable script to `dir` and return the command running it
+    fn script(dir: &Path, name: &str, body: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_exit_status_is_reported() {
+        let dir = tempdir().unwrap();
+        assert_eq!(
+            run(&script(dir.path(), "ok", "exit 0"), &[], HOOK_TIMEOUT),
+            Ok(())
+        );
+        assert_eq!(
+            run(&script(dir.path(), "fail", "exit 3"), &[], HOOK_TIMEOUT),
+            Err(HookError::Exit(Some(3)))
+        );
+    }
+
+    #[test]
+    fn test_slow_hook_is_killed() {
+        let dir = tempdir().unwrap();
+        let timeout = Duration::from_millis(100);
+        let started = Instant::now();
+
+        let result = run(&script(dir.path(), "slow", "sleep 5"), &[], timeout);
+        assert_eq!(result, Err(HookError::TimedOut(timeout)));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_failure_policy() {
+        let dir = tempdir().unwrap();
+        let mut hooks = PasteHooks {
+            pre: Some(script(dir.path(), "fail", "exit 1")),
+            ..PasteHooks::default()
+        };
+        assert_eq!(hooks.before().await, Ok(()));
+
+        hooks.pre_failure = HookFailure::Abort;
+        assert_eq!(
+            hooks.before().await,
+            Err(PastaError::Hook(HookError::Exit(Some(1))))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_hook_gets_result_but_not_content() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("env");
+        let body = format!("env | grep '^PASTA_' | sort > {}", out.display());
+        let hooks = PasteHooks {
+            post: Some(script(dir.path(), "post", &body)),
+            ..PasteHooks::default()
+        };
+
+        let report = PasteReport {
+            typed: 4,
+            skipped: 2,
+            cancelled: true,
+            ..PasteReport::default()
+        };
+        hooks.after(&Ok(report)).unwrap().await.unwrap();
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "PASTA_CHARS=4\nPASTA_RESULT=cancelled\n"
+        );
+
+        hooks.after(&Err(PastaError::Busy)).unwrap().await.unwrap();
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "PASTA_CHARS=0\nPASTA_RESULT=failed\n"
+        );
+
+        assert!(PasteHooks::default().after(&Ok(report)).is_none());
+    }
+}
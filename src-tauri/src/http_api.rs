@@ -0,0 +1,438 @@
+/// Opt-in HTTP endpoint on 127.0.0.1 for tools that can't use the socket
+///
+/// `POST /paste`, `POST /type` (the body is the text), `POST /cancel` and
+/// `GET /status`, each answered with a `CliReply` as JSON. Every request needs
+/// `Authorization: Bearer <token>` and goes through `control::ControlTarget`,
+/// like control socket commands. One request per connection, served in turn.
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli::{CliOutcome, CliReply},
+    control::{self, ControlRequest, ControlTarget},
+    single_instance::MAX_MESSAGE_BYTES,
+};
+
+/// Port used when `http_api.port` isn't set
+pub const DEFAULT_HTTP_PORT: u16 = 7450;
+
+/// Largest request line plus headers accepted, in bytes
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+
+/// How long a client gets to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Requests allowed in a burst, and how quickly the allowance refills
+const BURST: f64 = 10.0;
+const REFILL_PER_SEC: f64 = 5.0;
+
+/// The `[http_api]` table in `config.toml`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpApiConfig {
+    pub enabled: bool,
+    pub port: u16,
+    /// Bearer token clients must send; the endpoint won't start without one
+    pub token: String,
+}
+
+impl Default for HttpApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_HTTP_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+/// The parts of a request the router looks at
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HttpRequest {
+    pub method: String,
+    /// Without the query string
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// A `CliReply` as JSON
+    pub body: String,
+}
+
+impl HttpResponse {
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: CliReply::error(message).encode(),
+        }
+    }
+
+    /// Busy replies are a conflict, other failures a bad request
+    fn from_reply(reply: CliReply) -> Self {
+        let status = match reply.outcome {
+            CliOutcome::Ok => 200,
+            CliOutcome::Busy => 409,
+            CliOutcome::Error => 400,
+        };
+        Self {
+            status,
+            body: reply.encode(),
+        }
+    }
+}
+
+/// Token bucket shared by all clients
+struct RateLimiter {
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    fn new(now: Instant) -> Self {
+        Self {
+            state: Mutex::new((BURST, now)),
+        }
+    }
+
+    fn allow(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last) = *state;
+        let refilled = now.saturating_duration_since(last).as_secs_f64() * REFILL_PER_SEC;
+        let tokens = (tokens + refilled).min(BURST);
+        if tokens < 1.0 {
+            *state = (tokens, now);
+            return false;
+        }
+        *state = (tokens - 1.0, now);
+        true
+    }
+}
+
+/// Checks the rate limit and the token, then runs the request
+///
+/// The limit comes first so wrong tokens use up the allowance too, slowing
+/// down guessing.
+pub struct Router {
+    token: String,
+    limiter: RateLimiter,
+}
+
+impl Router {
+    pub fn new(token: String) -> Self {
+        Self {
+            token,
+            limiter: RateLimiter::new(Instant::now()),
+        }
+    }
+
+    pub fn handle(
+        &self,
+        request: &HttpRequest,
+        target: &dyn ControlTarget,
+        now: Instant,
+    ) -> HttpResponse {
+        if !self.limiter.allow(now) {
+            return HttpResponse::error(429, "Too many requests, slow down");
+        }
+        if !token_matches(request.authorization.as_deref(), &self.token) {
+            return HttpResponse::error(401, "Missing or wrong bearer token");
+        }
+
+        let command = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/paste") => ControlRequest::Paste,
+            ("POST", "/cancel") => ControlRequest::Cancel,
+            ("GET", "/status") => ControlRequest::Status,
+            ("POST", "/type") => match String::from_utf8(request.body.clone()) {
+                Ok(text) => ControlRequest::Type { text },
+                Err(_) => return HttpResponse::error(400, "The body is not UTF-8 text"),
+            },
+            (_, "/paste" | "/cancel" | "/status" | "/type") => {
+                return HttpResponse::error(405, "Method not allowed")
+            }
+            _ => return HttpResponse::error(404, "Not found"),
+        };
+        HttpResponse::from_reply(control::handle_request(target, command))
+    }
+}
+
+/// Compare without stopping at the first differing byte
+fn token_matches(authorization: Option<&str>, token: &str) -> bool {
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    !token.is_empty()
+        && given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Read one request, or the response explaining why it was refused
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<HttpRequest, HttpResponse> {
+    let malformed = || HttpResponse::error(400, "Malformed request");
+    let mut head_bytes = 0;
+    let mut next_line = |reader: &mut R| -> Result<String, HttpResponse> {
+        let mut line = String::new();
+        let read = reader
+            .take((MAX_HEAD_BYTES - head_bytes) as u64)
+            .read_line(&mut line)
+            .map_err(|_| malformed())?;
+        head_bytes += read;
+        if !line.ends_with('\n') {
+            return Err(if head_bytes >= MAX_HEAD_BYTES {
+                HttpResponse::error(431, "Request headers too large")
+            } else {
+                malformed()
+            });
+        }
+        Ok(line.trim_end().to_string())
+    };
+
+    let request_line = next_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(malformed());
+    };
+    let mut request = HttpRequest {
+        method: method.to_string(),
+        path: target.split('?').next().unwrap_or(target).to_string(),
+        ..HttpRequest::default()
+    };
+
+    let mut content_length = 0;
+    loop {
+        let line = next_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(malformed());
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.parse().map_err(|_| malformed())?,
+            "authorization" => request.authorization = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    if content_length > MAX_MESSAGE_BYTES {
+        return Err(HttpResponse::error(
+            413,
+            &format!("Body is larger than {MAX_MESSAGE_BYTES} bytes"),
+        ));
+    }
+    request.body = vec![0; content_length as usize];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|_| malformed())?;
+    Ok(request)
+}
+
+fn write_response(mut stream: &TcpStream, response: &HttpResponse) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+/// Listen on `127.0.0.1:port` in the background, answering with `handler`
+pub fn serve<F>(port: u16, handler: F) -> io::Result<()>
+where
+    F: Fn(HttpRequest) -> HttpResponse + Send + 'static,
+{
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    info!("HTTP endpoint listening on 127.0.0.1:{port}");
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("HTTP endpoint stopped accepting: {e}");
+                    continue;
+                }
+            };
+            let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+            let response = match read_request(&mut BufReader::new(&stream)) {
+                Ok(request) => handler(request),
+                Err(response) => response,
+            };
+            if let Err(e) = write_response(&stream, &response) {
+                warn!("Failed to answer HTTP request: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::FakeTarget;
+
+    const TOKEN: &str = "s3cret";
+
+    fn request(method: &str, path: &str, token: Option<&str>, body: &str) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            authorization: token.map(|token| format!("Bearer {token}")),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_token_is_required() {
+        let router = Router::new(TOKEN.to_string());
+        let target = FakeTarget::default();
+        let now = Instant::now();
+
+        for token in [None, Some("wrong"), Some("s3cre")] {
+            let response = router.handle(&request("GET", "/status", token, ""), &target, now);
+            assert_eq!(response.status, 401, "{token:?}");
+        }
+        let response = router.handle(&request("GET", "/status", Some(TOKEN), ""), &target, now);
+        assert_eq!(response.status, 200);
+
+        // An unset token never matches, not even an empty one
+        let open = Router::new(String::new());
+        let response = open.handle(&request("GET", "/status", Some(""), ""), &target, now);
+        assert_eq!(response.status, 401);
+    }
+
+    #[test]
+    fn test_routes_reach_the_target() {
+        let router = Router::new(TOKEN.to_string());
+        let target = FakeTarget::default();
+        let now = Instant::now();
+
+        let response = router.handle(&request("POST", "/type", Some(TOKEN), "hi"), &target, now);
+        assert_eq!(response.status, 200);
+        assert_eq!(*target.calls.lock().unwrap(), ["type hi"]);
+
+        let status = |method, path| {
+            router
+                .handle(&request(method, path, Some(TOKEN), ""), &target, now)
+                .status
+        };
+        assert_eq!(status("POST", "/paste"), 200);
+        assert_eq!(status("POST", "/cancel"), 200);
+        assert_eq!(status("GET", "/paste"), 405);
+        assert_eq!(status("GET", "/"), 404);
+    }
+
+    #[test]
+    fn test_busy_paste_is_a_conflict() {
+        let router = Router::new(TOKEN.to_string());
+        let target = FakeTarget {
+            busy: true,
+            ..FakeTarget::default()
+        };
+        let response = router.handle(
+            &request("POST", "/paste", Some(TOKEN), ""),
+            &target,
+            Instant::now(),
+        );
+        assert_eq!(response.status, 409);
+        assert_eq!(CliReply::decode(&response.body).outcome, CliOutcome::Busy);
+    }
+
+    #[test]
+    fn test_requests_are_rate_limited() {
+        let router = Router::new(TOKEN.to_string());
+        let target = FakeTarget::default();
+        let now = Instant::now();
+        let status = |at| {
+            router
+                .handle(&request("GET", "/status", Some(TOKEN), ""), &target, at)
+                .status
+        };
+
+        for _ in 0..BURST as usize {
+            assert_eq!(status(now), 200);
+        }
+        assert_eq!(status(now), 429);
+        assert_eq!(status(now + Duration::from_secs(1)), 200);
+    }
+
+    #[test]
+    fn test_wrong_tokens_are_rate_limited() {
+        let router = Router::new(TOKEN.to_string());
+        let target = FakeTarget::default();
+        let now = Instant::now();
+        let status = |token| {
+            router
+                .handle(&request("GET", "/status", Some(token), ""), &target, now)
+                .status
+        };
+
+        for _ in 0..BURST as usize {
+            assert_eq!(status("guess"), 401);
+        }
+        assert_eq!(status("guess"), 429);
+        // Until the allowance refills, the right token is held back too
+        assert_eq!(status(TOKEN), 429);
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /type?x=1 HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer abc\r\n\
+                   Content-Length: 5\r\n\r\nhello";
+        let request = read_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/type");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer abc"));
+        assert_eq!(request.body, b"hello");
+
+        assert_eq!(
+            read_request(&mut "nonsense\r\n\r\n".as_bytes())
+                .unwrap_err()
+                .status,
+            400
+        );
+    }
+
+    #[test]
+    fn test_oversized_requests_are_refused() {
+        let raw = format!(
+            "POST /type HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_MESSAGE_BYTES + 1
+        );
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 413);
+
+        let raw = format!(
+            "GET /status HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD_BYTES)
+        );
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap_err().status, 431);
+    }
+}
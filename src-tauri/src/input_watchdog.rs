@@ -0,0 +1,335 @@
+/// Stopping a paste when the user touches the keyboard or mouse
+///
+/// Keys pressed while Pasta types end up interleaved with the paste. With
+/// `abort_on_user_input` set, a listen-only hook reports physical key
+/// presses and mouse clicks, and the paste is cancelled as if the user had
+/// asked. Windows marks injected input on its low-level hooks and macOS
+/// records which process posted an event, so Pasta's own keystrokes are
+/// told apart. Linux offers neither without extra libraries, so the setting
+/// does nothing there.
+use std::sync::{
+    mpsc::{self, SyncSender},
+    OnceLock,
+};
+
+/// Wakes the worker thread; full while it is busy, so bursts collapse
+static INPUT: OnceLock<SyncSender<()>> = OnceLock::new();
+
+/// Outcome of the first `start`, which later calls return
+static STARTED: OnceLock<Result<(), String>> = OnceLock::new();
+
+/// Call `on_input` after physical key presses and mouse clicks, for the rest
+/// of the run
+///
+/// Only the first call installs the hook, later ones report how that went.
+/// `on_input` runs on a thread of its own, so the hook returns right away.
+pub fn start(on_input: impl Fn() + Send + 'static) -> Result<(), String> {
+    STARTED
+        .get_or_init(|| {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            INPUT
+                .set(sender)
+                .map_err(|_| "Input watchdog already running".to_string())?;
+            platform::install()?;
+            std::thread::Builder::new()
+                .name("input-watchdog".to_string())
+                .spawn(move || {
+                    for () in receiver {
+                        on_input();
+                    }
+                })
+                .map_err(|e| format!("Couldn't start the input watchdog: {e}"))?;
+            Ok(())
+        })
+        .clone()
+}
+
+/// Called by the hooks for input Pasta didn't send
+#[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+fn user_input() {
+    if let Some(sender) = INPUT.get() {
+        let _ = sender.try_send(());
+    }
+}
+
+/// Whether a key or mouse event from the low-level hooks came from a device
+#[cfg(any(windows, test))]
+fn windows_event_is_physical(message: u32, flags: u32) -> bool {
+    const WM_KEYDOWN: u32 = 0x0100;
+    const WM_SYSKEYDOWN: u32 = 0x0104;
+    const WM_LBUTTONDOWN: u32 = 0x0201;
+    const WM_RBUTTONDOWN: u32 = 0x0204;
+    const WM_MBUTTONDOWN: u32 = 0x0207;
+    const LLKHF_INJECTED: u32 = 0x10;
+    const LLMHF_INJECTED: u32 = 0x01;
+
+    match message {
+        WM_KEYDOWN | WM_SYSKEYDOWN => flags & LLKHF_INJECTED == 0,
+        WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN => flags & LLMHF_INJECTED == 0,
+        _ => false,
+    }
+}
+
+/// Whether an event seen by the tap was posted by someone other than Pasta
+///
+/// Hardware events carry pid 0, synthetic ones the pid that posted them.
+#[cfg(any(target_os = "macos", test))]
+fn macos_event_is_foreign(source_pid: i64, own_pid: u32) -> bool {
+    source_pid != i64::from(own_pid)
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    type Handle = *mut c_void;
+    type HookProc = unsafe extern "system" fn(i32, usize, isize) -> isize;
+
+    const WH_KEYBOARD_LL: i32 = 13;
+    const WH_MOUSE_LL: i32 = 14;
+    const HC_ACTION: i32 = 0;
+
+    // Laid out as Windows defines it, not every field is read
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct KbdLlHookStruct {
+        vk_code: u32,
+        scan_code: u32,
+        flags: u32,
+        time: u32,
+        extra_info: usize,
+    }
+
+    // Laid out as Windows defines it, not every field is read
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct MsLlHookStruct {
+        point: [i32; 2],
+        mouse_data: u32,
+        flags: u32,
+        time: u32,
+        extra_info: usize,
+    }
+
+    // Laid out as Windows defines it, not every field is read
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct Msg {
+        window: Handle,
+        message: u32,
+        wparam: usize,
+        lparam: isize,
+        time: u32,
+        point: [i32; 2],
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn SetWindowsHookExW(id: i32, hook: HookProc, module: Handle, thread_id: u32) -> Handle;
+        fn CallNextHookEx(hook: Handle, code: i32, wparam: usize, lparam: isize) -> isize;
+        fn GetMessageW(msg: *mut Msg, window: Handle, min: u32, max: u32) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetModuleHandleW(name: *const u16) -> Handle;
+    }
+
+    unsafe extern "system" fn keyboard_hook(code: i32, wparam: usize, lparam: isize) -> isize {
+        if code == HC_ACTION {
+            // SAFETY: for HC_ACTION `lparam` points at a KBDLLHOOKSTRUCT
+            let event = &*(lparam as *const KbdLlHookStruct);
+            if super::windows_event_is_physical(wparam as u32, event.flags) {
+                super::user_input();
+            }
+        }
+        CallNextHookEx(std::ptr::null_mut(), code, wparam, lparam)
+    }
+
+    unsafe extern "system" fn mouse_hook(code: i32, wparam: usize, lparam: isize) -> isize {
+        if code == HC_ACTION {
+            // SAFETY: for HC_ACTION `lparam` points at a MSLLHOOKSTRUCT
+            let event = &*(lparam as *const MsLlHookStruct);
+            if super::windows_event_is_physical(wparam as u32, event.flags) {
+                super::user_input();
+            }
+        }
+        CallNextHookEx(std::ptr::null_mut(), code, wparam, lparam)
+    }
+
+    /// Install both hooks on a thread that pumps messages for them
+    pub fn install() -> Result<(), String> {
+        let (installed, result) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("input-hooks".to_string())
+            .spawn(move || {
+                // SAFETY: the hooks live for the rest of the process and only
+                // read the structs Windows hands them
+                let hooked = unsafe {
+                    let module = GetModuleHandleW(std::ptr::null());
+                    !SetWindowsHookExW(WH_KEYBOARD_LL, keyboard_hook, module, 0).is_null()
+                        && !SetWindowsHookExW(WH_MOUSE_LL, mouse_hook, module, 0).is_null()
+                };
+                let _ = installed.send(hooked);
+                if !hooked {
+                    return;
+                }
+                let mut msg = std::mem::MaybeUninit::<Msg>::uninit();
+                // SAFETY: low-level hooks are called from this loop
+                while unsafe { GetMessageW(msg.as_mut_ptr(), std::ptr::null_mut(), 0, 0) } > 0 {}
+            })
+            .map_err(|e| format!("Couldn't start the input hooks: {e}"))?;
+        match result.recv() {
+            Ok(true) => Ok(()),
+            _ => Err("Windows refused the keyboard and mouse hooks".to_string()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::{
+        ffi::c_void,
+        sync::atomic::{AtomicPtr, Ordering},
+    };
+
+    type Ref = *mut c_void;
+    type TapCallback = extern "C" fn(Ref, u32, Ref, *mut c_void) -> Ref;
+
+    const SESSION_EVENT_TAP: u32 = 1;
+    const HEAD_INSERT_EVENT_TAP: u32 = 0;
+    const TAP_OPTION_LISTEN_ONLY: u32 = 1;
+    const LEFT_MOUSE_DOWN: u32 = 1;
+    const RIGHT_MOUSE_DOWN: u32 = 3;
+    const KEY_DOWN: u32 = 10;
+    const OTHER_MOUSE_DOWN: u32 = 25;
+    const TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+    /// `kCGEventSourceUnixProcessID`
+    const SOURCE_PID_FIELD: u32 = 41;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn CGEventTapCreate(
+            tap: u32,
+            place: u32,
+            options: u32,
+            events: u64,
+            callback: TapCallback,
+            user_info: *mut c_void,
+        ) -> Ref;
+        fn CGEventTapEnable(tap: Ref, enable: bool);
+        fn CGEventGetIntegerValueField(event: Ref, field: u32) -> i64;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        static kCFRunLoopCommonModes: Ref;
+        fn CFMachPortCreateRunLoopSource(allocator: Ref, port: Ref, order: isize) -> Ref;
+        fn CFRunLoopGetCurrent() -> Ref;
+        fn CFRunLoopAddSource(run_loop: Ref, source: Ref, mode: Ref);
+        fn CFRunLoopRun();
+    }
+
+    /// The tap, to turn back on when macOS disables it for being slow
+    static TAP: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+    extern "C" fn tap_callback(
+        _proxy: Ref,
+        event_type: u32,
+        event: Ref,
+        _info: *mut c_void,
+    ) -> Ref {
+        if event_type == TAP_DISABLED_BY_TIMEOUT {
+            // SAFETY: the tap was created before it could call back
+            unsafe { CGEventTapEnable(TAP.load(Ordering::SeqCst), true) };
+            return event;
+        }
+        // SAFETY: `event` is valid for the duration of the callback
+        let source_pid = unsafe { CGEventGetIntegerValueField(event, SOURCE_PID_FIELD) };
+        if super::macos_event_is_foreign(source_pid, std::process::id()) {
+            super::user_input();
+        }
+        event
+    }
+
+    /// Add a listen-only tap on a thread running its run loop
+    ///
+    /// Fails without the Accessibility or Input Monitoring permission.
+    pub fn install() -> Result<(), String> {
+        let (installed, result) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("input-tap".to_string())
+            .spawn(move || {
+                let events = [
+                    LEFT_MOUSE_DOWN,
+                    RIGHT_MOUSE_DOWN,
+                    KEY_DOWN,
+                    OTHER_MOUSE_DOWN,
+                ]
+                .iter()
+                .fold(0u64, |mask, event| mask | 1 << event);
+                // SAFETY: the tap and its source live for the rest of the process
+                unsafe {
+                    let tap = CGEventTapCreate(
+                        SESSION_EVENT_TAP,
+                        HEAD_INSERT_EVENT_TAP,
+                        TAP_OPTION_LISTEN_ONLY,
+                        events,
+                        tap_callback,
+                        std::ptr::null_mut(),
+                    );
+                    let _ = installed.send(!tap.is_null());
+                    if tap.is_null() {
+                        return;
+                    }
+                    TAP.store(tap, Ordering::SeqCst);
+                    let source = CFMachPortCreateRunLoopSource(std::ptr::null_mut(), tap, 0);
+                    CFRunLoopAddSource(CFRunLoopGetCurrent(), source, kCFRunLoopCommonModes);
+                    CGEventTapEnable(tap, true);
+                    CFRunLoopRun();
+                }
+            })
+            .map_err(|e| format!("Couldn't start the input tap: {e}"))?;
+        match result.recv() {
+            Ok(true) => Ok(()),
+            _ => Err(
+                "macOS refused to report key presses, check Pasta's Accessibility and \
+                      Input Monitoring permissions"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod platform {
+    pub fn install() -> Result<(), String> {
+        Err("Stopping on user input isn't supported on this platform, \
+             Pasta's keystrokes can't be told apart from yours"
+            .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_injected_input_is_ignored() {
+        // WM_KEYDOWN, WM_LBUTTONDOWN
+        assert!(windows_event_is_physical(0x0100, 0));
+        assert!(!windows_event_is_physical(0x0100, 0x10));
+        assert!(windows_event_is_physical(0x0201, 0));
+        assert!(!windows_event_is_physical(0x0201, 0x01));
+        // Key up and mouse moves don't count
+        assert!(!windows_event_is_physical(0x0101, 0));
+        assert!(!windows_event_is_physical(0x0200, 0));
+    }
+
+    #[test]
+    fn test_macos_own_events_are_ignored() {
+        assert!(macos_event_is_foreign(0, 4242));
+        assert!(macos_event_is_foreign(99, 4242));
+        assert!(!macos_event_is_foreign(4242, 4242));
+    }
+}
@@ -10,7 +10,8 @@ mod integration_tests {
 
     use crate::{
         app_logic::{handle_paste_clipboard, ClipboardProvider},
-        keyboard::KeyboardEmulator,
+        error::ClipboardError,
+        typing_harness::{keyboard_with, recording_keyboard, RecordingBackend},
     };
 
     /// Mock clipboard that returns a long text string
@@ -28,63 +29,59 @@ mod integration_tests {
     }
 
     impl ClipboardProvider for LongTextClipboard {
-        fn get_content(&self) -> Result<Option<String>, String> {
+        fn get_content(&self) -> Result<Option<String>, ClipboardError> {
             Ok(Some(self.text.clone()))
         }
     }
 
     #[tokio::test]
-    #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
     async fn test_emergency_stop_cancels_typing() {
-        // Create a mock keyboard emulator that simulates typing
-        let keyboard_emulator = Arc::new(KeyboardEmulator::new().unwrap());
         let cancellation_flag = Arc::new(AtomicBool::new(false));
         let clipboard = LongTextClipboard::new(1000); // Long text to type
 
-        // Clone for the cancellation thread
-        let cancellation_flag_clone = cancellation_flag.clone();
+        // Cancel partway through, as if the user pressed the emergency stop
+        let (backend, recording) = RecordingBackend::new();
+        let keyboard_emulator = keyboard_with(backend.cancel_after(300, cancellation_flag.clone()));
 
-        // Start typing in a task
-        let typing_task = tokio::spawn(async move {
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag_clone).await
-        });
+        let report =
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, true)
+                .await
+                .unwrap();
 
-        // Wait a bit for typing to start
-        tokio::time::sleep(Duration::from_millis(50)).await;
-
-        // Trigger cancellation (simulating Ctrl+Shift+Escape press)
-        cancellation_flag.store(true, Ordering::Relaxed);
-
-        // Wait for the typing task to complete
-        let result = typing_task.await.unwrap();
-        assert!(result.is_ok());
-
-        // Verify that not all text was typed (since we cancelled)
-        // Note: In a real keyboard emulator, we would check that typing stopped
-        // For the mock, we just verify the function completed without error
+        assert!(report.cancelled);
+        assert_eq!(report.typed, 300);
+        assert_eq!(report.skipped, 700);
+        assert_eq!(recording.typed_text().len(), 300);
+        assert!(!keyboard_emulator.typing_state().is_typing);
     }
 
     #[tokio::test]
-    #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
     async fn test_cancellation_flag_reset_before_new_operation() {
-        let keyboard_emulator = Arc::new(KeyboardEmulator::new().unwrap());
+        let (keyboard_emulator, recording) = recording_keyboard();
         let clipboard = LongTextClipboard::new(100);
 
-        // First operation with cancellation
+        // A cancel left over while idle doesn't stop the next paste
         let cancellation_flag = Arc::new(AtomicBool::new(false));
         cancellation_flag.store(true, Ordering::Relaxed); // Pre-cancelled
 
-        let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag.clone()).await;
-        assert!(result.is_ok());
-
-        // Reset flag for second operation
-        cancellation_flag.store(false, Ordering::Relaxed);
+        let report = handle_paste_clipboard(
+            &clipboard,
+            &keyboard_emulator,
+            cancellation_flag.clone(),
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(!report.cancelled);
+        assert_eq!(report.typed, 100);
 
         // Second operation should work normally
-        let result =
-            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag).await;
-        assert!(result.is_ok());
+        let report =
+            handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, true)
+                .await
+                .unwrap();
+        assert_eq!(report.typed, 100);
+        recording.assert_typed(&"a".repeat(200));
     }
 
     #[test]
@@ -137,30 +134,23 @@ mod integration_tests {
     }
 
     #[tokio::test]
-    #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
     async fn test_multiple_emergency_stops() {
-        let keyboard_emulator = Arc::new(KeyboardEmulator::new().unwrap());
         let clipboard = LongTextClipboard::new(500);
 
-        // Test multiple cancellations
-        for _ in 0..3 {
+        // Each paste gets its own flag, cancelled at a different point
+        for stop_at in [10, 120, 480] {
             let cancellation_flag = Arc::new(AtomicBool::new(false));
-
-            // Start typing
-            let flag_clone = cancellation_flag.clone();
-            let keyboard_clone = keyboard_emulator.clone();
-            let clipboard_clone = clipboard.clone();
-            let typing_task = tokio::spawn(async move {
-                handle_paste_clipboard(&clipboard_clone, &keyboard_clone, flag_clone).await
-            });
-
-            // Cancel quickly
-            tokio::time::sleep(Duration::from_millis(10)).await;
-            cancellation_flag.store(true, Ordering::Relaxed);
-
-            // Verify task completes
-            let result = typing_task.await.unwrap();
-            assert!(result.is_ok());
+            let (backend, recording) = RecordingBackend::new();
+            let keyboard_emulator =
+                keyboard_with(backend.cancel_after(stop_at, cancellation_flag.clone()));
+
+            let report =
+                handle_paste_clipboard(&clipboard, &keyboard_emulator, cancellation_flag, true)
+                    .await
+                    .unwrap();
+            assert!(report.cancelled);
+            assert_eq!(report.typed, stop_at);
+            assert_eq!(recording.typed_text().len(), stop_at);
         }
     }
 }
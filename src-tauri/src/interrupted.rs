@@ -0,0 +1,239 @@
+/// Marker for a paste in progress, so a crash mid-paste is noticed next launch
+///
+/// Only counts are written, never the text. `interrupted.json` is rewritten
+/// between chunks and removed once the operation ends, cancelled or not, so a
+/// marker found at startup means the process or the machine died mid-paste.
+use std::{
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::group_thousands, typing_state::TypingState};
+
+/// Markers older than this are dropped without asking
+const STALE_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// What `interrupted.json` holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasteMarker {
+    /// Start of the operation in milliseconds since the Unix epoch
+    pub operation_id: u64,
+    pub typed: usize,
+    pub total: usize,
+    /// When the marker was last written, in milliseconds since the Unix epoch
+    pub updated_at: u64,
+}
+
+/// The marker file next to the config
+pub struct MarkerFile {
+    path: PathBuf,
+}
+
+impl MarkerFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Mirror the worker's state: write the marker while typing, remove it once idle
+    pub fn track(&self, state: &TypingState) {
+        let result = match state.started_at.filter(|_| state.is_typing) {
+            Some(operation_id) => {
+                let marker = PasteMarker {
+                    operation_id,
+                    typed: state.typed,
+                    total: state.total,
+                    updated_at: millis(SystemTime::now()),
+                };
+                serde_json::to_string(&marker)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| fs::write(&self.path, json).map_err(|e| e.to_string()))
+            }
+            None => self.remove(),
+        };
+        if let Err(e) = result {
+            warn!("Failed to update {}: {e}", self.path.display());
+        }
+    }
+
+    /// Read and remove a marker left by a previous run
+    ///
+    /// Unreadable markers and markers older than a week are removed and ignored.
+    pub fn take(&self, now: SystemTime) -> Option<PasteMarker> {
+        let contents = fs::read_to_string(&self.path).ok()?;
+        if let Err(e) = self.remove() {
+            warn!("Failed to remove {}: {e}", self.path.display());
+        }
+        let marker = match serde_json::from_str::<PasteMarker>(&contents) {
+            Ok(marker) => marker,
+            Err(e) => {
+                warn!("Ignoring unreadable {}: {e}", self.path.display());
+                return None;
+            }
+        };
+        let age = Duration::from_millis(millis(now).saturating_sub(marker.updated_at));
+        if age > STALE_AFTER {
+            info!(
+                "Ignoring interrupted paste marker from {}s ago",
+                age.as_secs()
+            );
+            return None;
+        }
+        Some(marker)
+    }
+
+    fn remove(&self) -> Result<(), String> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// An interrupted paste as shown to the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedPaste {
+    pub typed: usize,
+    pub total: usize,
+    /// Whether the rest can be typed, which needs the original text
+    pub resumable: bool,
+    pub message: String,
+}
+
+impl InterruptedPaste {
+    /// Resuming needs `content` to be the text the marker was written for
+    pub fn new(marker: PasteMarker, content: Option<&str>) -> Self {
+        let resumable = marker.typed < marker.total
+            && content.is_some_and(|text| text.chars().count() == marker.total);
+        Self {
+            typed: marker.typed,
+            total: marker.total,
+            resumable,
+            message: format!(
+                "A paste was interrupted at {}/{} characters",
+                group_thousands(marker.typed),
+                group_thousands(marker.total)
+            ),
+        }
+    }
+}
+
+/// The interrupted paste found at startup, until the user dismisses it
+#[derive(Default)]
+pub struct PendingInterruption(Mutex<Option<InterruptedPaste>>);
+
+impl PendingInterruption {
+    pub fn new(interrupted: Option<InterruptedPaste>) -> Self {
+        Self(Mutex::new(interrupted))
+    }
+
+    pub fn get(&self) -> Option<InterruptedPaste> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn dismiss(&self) {
+        self.0.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn typing(typed: usize, total: usize) -> TypingState {
+        TypingState {
+            is_typing: true,
+            typed,
+            total,
+            started_at: Some(42),
+            ..TypingState::default()
+        }
+    }
+
+    fn marker(typed: usize, total: usize) -> PasteMarker {
+        PasteMarker {
+            operation_id: 42,
+            typed,
+            total,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_marker_follows_the_operation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("interrupted.json");
+        let file = MarkerFile::new(path.clone());
+
+        file.track(&typing(0, 10));
+        assert!(path.exists());
+        file.track(&typing(4, 10));
+        let written: PasteMarker =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            (written.operation_id, written.typed, written.total),
+            (42, 4, 10)
+        );
+
+        file.track(&TypingState::default());
+        assert!(!path.exists());
+        // Idle again with no marker is fine
+        file.track(&TypingState::default());
+    }
+
+    #[test]
+    fn test_take_returns_the_marker_once() {
+        let dir = tempdir().unwrap();
+        let file = MarkerFile::new(dir.path().join("interrupted.json"));
+        file.track(&typing(3200, 10000));
+
+        let found = file.take(SystemTime::now()).unwrap();
+        assert_eq!((found.typed, found.total), (3200, 10000));
+        assert_eq!(file.take(SystemTime::now()), None);
+    }
+
+    #[test]
+    fn test_stale_and_corrupt_markers_are_removed() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("interrupted.json");
+        let file = MarkerFile::new(path.clone());
+
+        file.track(&typing(1, 2));
+        let later = SystemTime::now() + STALE_AFTER + Duration::from_secs(60);
+        assert_eq!(file.take(later), None);
+        assert!(!path.exists());
+
+        fs::write(&path, "{ not json").unwrap();
+        assert_eq!(file.take(SystemTime::now()), None);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_resuming_needs_the_original_text() {
+        let interrupted = InterruptedPaste::new(marker(3200, 10000), None);
+        assert!(!interrupted.resumable);
+        assert_eq!(
+            interrupted.message,
+            "A paste was interrupted at 3,200/10,000 characters"
+        );
+
+        let text = "é".repeat(10);
+        assert!(InterruptedPaste::new(marker(4, 10), Some(&text)).resumable);
+        assert!(!InterruptedPaste::new(marker(4, 10), Some("different")).resumable);
+        assert!(!InterruptedPaste::new(marker(10, 10), Some(&text)).resumable);
+    }
+}
@@ -1,14 +1,27 @@
 use std::{
+    panic::{self, AssertUnwindSafe},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
     },
-    time::Duration,
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
-use enigo::{Enigo, Key, Keyboard};
-use log::{debug, info};
-use tokio::sync::mpsc;
+use enigo::{Direction, Enigo, Key, Keyboard};
+use log::{debug, error, info, warn};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    oneshot,
+};
+
+use crate::{
+    error::TypingError,
+    permissions::PermissionGate,
+    platform::{DisplayServer, PlatformProbe, SystemProbe},
+    secure_input::{SecureInputProbe, SystemSecureInputProbe},
+    typing_state::{TypingOutcome, TypingState, TypingTracker},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
@@ -29,114 +42,907 @@ impl TypingSpeed {
     }
 }
 
+/// How the worker types a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TypingMode {
+    /// Everything in one go, newlines typed as Return
+    #[default]
+    Continuous,
+    /// A line at a time, waiting after each Return for the target to catch up
+    LineByLine,
+}
+
+/// Longest `line_delay_ms`
+pub const MAX_LINE_DELAY_MS: u64 = 10_000;
+
+/// How a text is split into chunks with a pause after each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunking {
+    /// Characters typed between pauses, at least 1
+    pub size: usize,
+    /// Wait after each chunk but the last, for slow targets to catch up
+    pub pause: Duration,
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_CHUNK_SIZE,
+            pause: Duration::from_millis(DEFAULT_CHUNK_DELAY_MS),
+        }
+    }
+}
+
+/// Default `chunk_size`
+pub const DEFAULT_CHUNK_SIZE: usize = 200;
+
+/// Largest `chunk_size`
+pub const MAX_CHUNK_SIZE: u64 = 100_000;
+
+/// Default `chunk_delay_ms`
+pub const DEFAULT_CHUNK_DELAY_MS: u64 = 100;
+
+/// Longest `chunk_delay_ms`
+pub const MAX_CHUNK_DELAY_MS: u64 = 10_000;
+
+/// Largest `jitter_percent`, which keeps every jittered delay above zero
+pub const MAX_JITTER_PERCENT: u64 = 50;
+
+/// Characters after which a jittered delay sometimes runs longer
+const JITTER_PUNCTUATION: [char; 6] = ['.', ',', ';', ':', '!', '?'];
+
+/// Xorshift generator behind the typing jitter, nothing here needs better
 #[derive(Debug, Clone)]
+struct JitterRng(u64);
+
+impl JitterRng {
+    fn new(seed: u64) -> Self {
+        // Xorshift never leaves 0
+        Self(seed.max(1))
+    }
+
+    /// Seeded from the clock, so no two runs type alike
+    fn from_clock() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+}
+
+/// Wait after typing `ch` with a key delay of `delay`, moved up to `percent`
+/// either way by the random `roll`
+///
+/// A quarter of the rolls after punctuation add another `delay` on top. With
+/// `percent` at 0 it is always `delay`; otherwise it is never zero.
+fn jittered_delay(delay: Duration, percent: u8, ch: char, roll: u64) -> Duration {
+    if percent == 0 {
+        return delay;
+    }
+    let delay_us = delay.as_micros() as u64;
+    let spread = delay_us * u64::from(percent).min(MAX_JITTER_PERCENT) / 100;
+    let mut jittered = delay_us - spread + roll % (2 * spread + 1);
+    if JITTER_PUNCTUATION.contains(&ch) && (roll >> 32) % 4 == 0 {
+        jittered += delay_us;
+    }
+    Duration::from_micros(jittered.max(1))
+}
+
+/// How `TypingMode::LineByLine` paces and ends a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineOptions {
+    /// Wait after each line's Return
+    pub line_delay: Duration,
+    /// Press Return after the last line too
+    pub final_newline: bool,
+}
+
+impl Default for LineOptions {
+    fn default() -> Self {
+        Self {
+            line_delay: Duration::from_millis(500),
+            final_newline: true,
+        }
+    }
+}
+
+/// `text` as typed line by line, with `\r\n` as `\n` and a newline after the
+/// last line only if `final_newline`
+///
+/// Empty lines stay, so they still press Return.
+fn line_by_line_text(text: &str, final_newline: bool) -> String {
+    let text = text.replace("\r\n", "\n");
+    let last_line_end = text.strip_suffix('\n').unwrap_or(&text);
+    if final_newline {
+        format!("{last_line_end}\n")
+    } else {
+        last_line_end.to_string()
+    }
+}
+
+/// Shortest `typing_delay_ms`
+pub const MIN_TYPING_DELAY_MS: u64 = 1;
+
+/// Longest `typing_delay_ms`
+pub const MAX_TYPING_DELAY_MS: u64 = 5000;
+
+/// `typing_delay_ms` from the config file within bounds, warning if it had to move
+pub fn clamp_typing_delay(delay_ms: i64) -> u64 {
+    let clamped = delay_ms.clamp(MIN_TYPING_DELAY_MS as i64, MAX_TYPING_DELAY_MS as i64) as u64;
+    if clamped as i64 != delay_ms {
+        warn!("typing_delay_ms = {delay_ms} is out of range, using {clamped}");
+    }
+    clamped
+}
+
+/// How a paste reaches the target app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteMethod {
+    /// Type the text key by key
+    #[default]
+    Type,
+    /// Press the platform paste shortcut so the target pastes the clipboard itself
+    Keystroke,
+    /// `Keystroke` for texts longer than the threshold, `Type` otherwise
+    Auto,
+}
+
+/// Default `paste_keystroke_threshold`, in characters
+pub const DEFAULT_KEYSTROKE_THRESHOLD: u64 = 20_000;
+
+/// Largest `paste_keystroke_threshold`
+pub const MAX_KEYSTROKE_THRESHOLD: u64 = 10_000_000;
+
+impl PasteMethod {
+    /// Whether a text of `chars` characters is pasted with the shortcut
+    pub fn uses_keystroke(self, chars: usize, threshold: u64) -> bool {
+        match self {
+            PasteMethod::Type => false,
+            PasteMethod::Keystroke => true,
+            PasteMethod::Auto => chars as u64 > threshold,
+        }
+    }
+}
+
+/// Modifier of the platform paste shortcut: Cmd+V on macOS, Ctrl+V elsewhere
+const PASTE_MODIFIER: Key = if cfg!(target_os = "macos") {
+    Key::Meta
+} else {
+    Key::Control
+};
+
+#[derive(Debug)]
 pub enum KeyboardCommand {
-    TypeText(String, Arc<AtomicBool>),
+    /// Type the text, sending the outcome once done with it
+    TypeText(String, Arc<AtomicBool>, oneshot::Sender<TypingOutcome>),
+    /// Press the paste shortcut for a clipboard of this many characters
+    ///
+    /// The flag is only checked before pressing it; a native paste can't be
+    /// stopped once it started.
+    PasteKeystroke(usize, Arc<AtomicBool>, oneshot::Sender<TypingOutcome>),
+    /// How the following texts are typed
+    SetMode(TypingMode, LineOptions),
+    /// How the following texts are split into chunks
+    SetChunking(Chunking),
+    /// Percent by which each key delay randomly varies, 0 to keep it even
+    SetJitter(u8),
+    /// Release modifier keys that may be logically held
+    ReleaseModifiers,
+    /// Stop after the commands queued before it, acknowledging on the sender
+    Shutdown(std::sync::mpsc::Sender<()>),
+}
+
+/// Resolves when the worker is done with a queued typing operation
+#[derive(Debug)]
+pub struct TypingHandle(oneshot::Receiver<TypingOutcome>);
+
+impl TypingHandle {
+    pub(crate) fn new(done: oneshot::Receiver<TypingOutcome>) -> Self {
+        Self(done)
+    }
+
+    /// Wait for the operation to finish or be cancelled
+    ///
+    /// Fails with `WorkerStopped` if the worker went away first, e.g. it panicked.
+    pub async fn finished(self) -> Result<TypingOutcome, TypingError> {
+        self.0.await.map_err(|_| TypingError::WorkerStopped)
+    }
+}
+
+/// Modifiers released when the worker stops, in case typing was interrupted
+pub(crate) const MODIFIER_KEYS: [Key; 4] = [Key::Shift, Key::Control, Key::Alt, Key::Meta];
+
+/// How often a paused operation checks whether it was resumed or cancelled
+const PAUSE_POLL: Duration = Duration::from_millis(20);
+
+/// Keystrokes in a row the system may reject before typing is abandoned
+pub(crate) const MAX_CONSECUTIVE_FAILURES: usize = 10;
+
+/// What the worker types through, enigo outside of tests
+///
+/// A failed keystroke isn't retried, as that can't be done meaningfully, but
+/// the worker gives up after `MAX_CONSECUTIVE_FAILURES` in a row.
+pub(crate) trait KeyBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String>;
+    fn text(&mut self, text: &str) -> Result<(), String>;
+
+    /// Wait between keystrokes and chunks
+    fn pause(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+impl KeyBackend for Enigo {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+        Keyboard::key(self, key, direction).map_err(|e| e.to_string())
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), String> {
+        Keyboard::text(self, text).map_err(|e| e.to_string())
+    }
 }
 
 pub struct KeyboardEmulator {
     tx: mpsc::Sender<KeyboardCommand>,
+    tracker: Arc<TypingTracker>,
+    permission: Arc<PermissionGate>,
+    display_server: Option<DisplayServer>,
+    secure_input: Arc<dyn SecureInputProbe>,
+    /// Why there is no worker, for an emulator made with `unavailable`
+    startup_error: Option<TypingError>,
+    /// The worker thread, until `shutdown` joins it
+    worker: Mutex<Option<JoinHandle<()>>>,
+    /// Speed and the delay overriding it, which `key_delay_ms` follows
+    speed: Mutex<(TypingSpeed, Option<u64>)>,
+    /// Delay between keystrokes, read by the worker before each one so a
+    /// change applies to the text being typed
+    key_delay_ms: Arc<AtomicU64>,
 }
 
 impl KeyboardEmulator {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let (tx, mut rx) = mpsc::channel::<KeyboardCommand>(10);
+    pub fn new() -> Result<Self, TypingError> {
+        Self::with_backend(|| Enigo::new(&enigo::Settings::default()).map_err(|e| e.to_string()))
+    }
 
-        // Spawn a dedicated thread for keyboard operations
-        std::thread::spawn(move || {
-            let mut enigo = Enigo::new(&enigo::Settings::default()).unwrap();
-            let typing_speed = TypingSpeed::default(); // Always use Normal speed
-
-            while let Some(cmd) = rx.blocking_recv() {
-                match cmd {
-                    KeyboardCommand::TypeText(text, cancellation_flag) => {
-                        let delay = Duration::from_millis(typing_speed.delay_ms());
-
-                        debug!("Typing text with {typing_speed:?} speed");
-
-                        // Chunk text for better performance with long content
-                        const CHUNK_SIZE: usize = 200;
-                        let chars: Vec<char> = text.chars().collect();
-                        let chunks: Vec<String> = chars
-                            .chunks(CHUNK_SIZE)
-                            .map(|chunk| chunk.iter().collect::<String>())
-                            .collect();
-
-                        for (i, chunk) in chunks.iter().enumerate() {
-                            // Check cancellation flag at the start of each chunk
-                            if cancellation_flag.load(Ordering::Relaxed) {
-                                info!("Typing cancelled by user at chunk {i}");
-                                break;
-                            }
-                            debug!("Processing chunk {} of {}", i + 1, chunks.len());
-
-                            // Type each character in the chunk
-                            for (char_index, ch) in chunk.chars().enumerate() {
-                                // Check cancellation at the start of each character for immediate response
-                                if char_index == 0 && cancellation_flag.load(Ordering::Relaxed) {
-                                    info!("Typing cancelled by user");
-                                    break;
-                                }
-                                // Check cancellation flag periodically (every 10 characters)
-                                if char_index % 10 == 0 && cancellation_flag.load(Ordering::Relaxed)
-                                {
-                                    info!("Typing cancelled by user");
-                                    break;
-                                }
-
-                                match ch {
-                                    '\n' => {
-                                        let _ = enigo.key(Key::Return, enigo::Direction::Click);
-                                    }
-                                    '\t' => {
-                                        let _ = enigo.key(Key::Tab, enigo::Direction::Click);
-                                    }
-                                    _ => {
-                                        let _ = enigo.text(&ch.to_string());
-                                    }
-                                }
-                                std::thread::sleep(delay);
-                            }
-
-                            // Check if cancelled before continuing to next chunk
-                            if cancellation_flag.load(Ordering::Relaxed) {
-                                info!("Typing cancelled by user");
-                                break;
-                            }
-
-                            // Add a small pause between chunks to avoid overwhelming the system
-                            if i < chunks.len() - 1 {
-                                std::thread::sleep(Duration::from_millis(100));
-                            }
-                        }
+    /// Start the worker thread, typing through the backend `make_backend` creates
+    ///
+    /// The backend is created on the worker thread, as enigo's isn't `Send`.
+    pub(crate) fn with_backend<B, F>(make_backend: F) -> Result<Self, TypingError>
+    where
+        B: KeyBackend,
+        F: FnOnce() -> Result<B, String> + Send + 'static,
+    {
+        Self::with_backend_and_probe(make_backend, Arc::new(SystemSecureInputProbe))
+    }
 
-                        info!("Finished typing text");
-                    }
-                }
+    /// Like `with_backend`, checking `secure_input` before typing and between chunks
+    ///
+    /// Waits for the worker to create the backend, failing with
+    /// `TypingError::Unavailable` (`WaylandUnsupported` on Wayland) if it can't.
+    pub(crate) fn with_backend_and_probe<B, F>(
+        make_backend: F,
+        secure_input: Arc<dyn SecureInputProbe>,
+    ) -> Result<Self, TypingError>
+    where
+        B: KeyBackend,
+        F: FnOnce() -> Result<B, String> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<KeyboardCommand>(10);
+        let tracker = Arc::new(TypingTracker::new());
+        let worker_tracker = tracker.clone();
+        let worker_probe = secure_input.clone();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let key_delay_ms = Arc::new(AtomicU64::new(TypingSpeed::default().delay_ms()));
+        let worker_key_delay_ms = key_delay_ms.clone();
+
+        // Spawn a dedicated thread for keyboard operations
+        let worker = std::thread::spawn(move || match make_backend() {
+            Ok(mut backend) => {
+                let _ = ready_tx.send(Ok(()));
+                run_worker(
+                    &mut backend,
+                    rx,
+                    &worker_tracker,
+                    &*worker_probe,
+                    &worker_key_delay_ms,
+                );
+            }
+            Err(e) => {
+                error!("Failed to start keyboard emulation: {e}");
+                let _ = ready_tx.send(Err(e));
             }
         });
 
-        Ok(Self { tx })
+        let display_server = SystemProbe.display_server();
+        match ready_rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                let _ = worker.join();
+                return Err(worker_stopped_error(display_server, Some(&e)));
+            }
+            // Creating the backend panicked
+            Err(_) => {
+                let _ = worker.join();
+                return Err(worker_stopped_error(display_server, None));
+            }
+        }
+
+        Ok(Self {
+            tx,
+            tracker,
+            permission: Arc::new(PermissionGate::new()),
+            display_server,
+            secure_input,
+            startup_error: None,
+            worker: Mutex::new(Some(worker)),
+            speed: Mutex::new((TypingSpeed::default(), None)),
+            key_delay_ms,
+        })
+    }
+
+    /// An emulator without a worker, every operation failing with `error`
+    ///
+    /// Lets the app start, and say why it can't type, when `new` failed.
+    pub fn unavailable(error: TypingError) -> Self {
+        let (tx, _) = mpsc::channel(1);
+        Self {
+            tx,
+            tracker: Arc::new(TypingTracker::new()),
+            permission: Arc::new(PermissionGate::new()),
+            display_server: SystemProbe.display_server(),
+            secure_input: Arc::new(SystemSecureInputProbe),
+            startup_error: Some(error),
+            worker: Mutex::new(None),
+            speed: Mutex::new((TypingSpeed::default(), None)),
+            key_delay_ms: Arc::new(AtomicU64::new(TypingSpeed::default().delay_ms())),
+        }
     }
 
     pub async fn type_text(
         &self,
         text: &str,
         cancellation_flag: Arc<AtomicBool>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.tx
-            .send(KeyboardCommand::TypeText(
-                text.to_string(),
-                cancellation_flag,
-            ))
-            .await?;
+    ) -> Result<TypingHandle, TypingError> {
+        self.permission.check()?;
+        self.tracker.enqueued();
+        self.send_text(text, cancellation_flag).await
+    }
+
+    /// Type text unless another operation is queued or typing
+    ///
+    /// When busy this fails with `TypingError::Busy`, or queues behind the
+    /// current work if `queue` is set. The cancellation flag is only cleared
+    /// when nothing else is using it, so a pending cancel still stops the
+    /// operation it was meant for; queued work is cancelled along with it.
+    pub async fn try_type_text(
+        &self,
+        text: &str,
+        cancellation_flag: Arc<AtomicBool>,
+        queue: bool,
+    ) -> Result<TypingHandle, TypingError> {
+        self.reserve(&cancellation_flag, queue)?;
+        self.send_text(text, cancellation_flag).await
+    }
+
+    /// Paste a clipboard of `chars` characters with the platform shortcut,
+    /// under the same busy policy as `try_type_text`
+    ///
+    /// The text isn't typed, so nothing can be paused or cancelled once the
+    /// shortcut is pressed.
+    pub async fn try_paste_keystroke(
+        &self,
+        chars: usize,
+        cancellation_flag: Arc<AtomicBool>,
+        queue: bool,
+    ) -> Result<TypingHandle, TypingError> {
+        self.reserve(&cancellation_flag, queue)?;
+        self.send(|done| KeyboardCommand::PasteKeystroke(chars, cancellation_flag, done))
+            .await
+    }
+
+    /// Count an operation in, or fail with `Busy` if something else runs and
+    /// `queue` isn't set
+    fn reserve(&self, cancellation_flag: &AtomicBool, queue: bool) -> Result<(), TypingError> {
+        self.permission.check()?;
+        if self.tracker.try_enqueue_idle() {
+            cancellation_flag.store(false, Ordering::Relaxed);
+        } else if queue {
+            self.tracker.enqueued();
+        } else {
+            return Err(TypingError::Busy);
+        }
         Ok(())
     }
+
+    /// Hand already counted work to the worker
+    async fn send_text(
+        &self,
+        text: &str,
+        cancellation_flag: Arc<AtomicBool>,
+    ) -> Result<TypingHandle, TypingError> {
+        let text = text.to_string();
+        self.send(|done| KeyboardCommand::TypeText(text, cancellation_flag, done))
+            .await
+    }
+
+    /// Send the command `make` builds around the completion sender
+    async fn send(
+        &self,
+        make: impl FnOnce(oneshot::Sender<TypingOutcome>) -> KeyboardCommand,
+    ) -> Result<TypingHandle, TypingError> {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(make(done_tx)).await.is_err() {
+            self.tracker.dequeued();
+            return Err(self
+                .startup_error
+                .clone()
+                .unwrap_or_else(|| worker_stopped_error(self.display_server, None)));
+        }
+        Ok(TypingHandle::new(done_rx))
+    }
+
+    /// Stop the worker once queued work is done, releasing modifier keys,
+    /// and join its thread
+    ///
+    /// Returns `false` if it didn't stop within `timeout`, e.g. because it is
+    /// still typing and the cancellation flag wasn't set. Blocks the caller.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        send_shutdown(&self.tx, timeout) && join_worker(&self.worker, deadline)
+    }
+
+    /// Ask the worker to release modifier keys without waiting
+    ///
+    /// Safe to call from a panic hook; does nothing if the queue is full.
+    pub fn release_modifiers(&self) {
+        let _ = self.tx.try_send(KeyboardCommand::ReleaseModifiers);
+    }
+
+    /// Whether the OS lets the worker type, checked before work is queued
+    pub fn permission(&self) -> &Arc<PermissionGate> {
+        &self.permission
+    }
+
+    /// What the worker asks whether secure input blocks typing
+    pub fn secure_input(&self) -> &Arc<dyn SecureInputProbe> {
+        &self.secure_input
+    }
+
+    /// Progress bookkeeping shared with the worker thread
+    pub fn tracker(&self) -> &Arc<TypingTracker> {
+        &self.tracker
+    }
+
+    /// Hold the running operation before its next character, returning
+    /// whether it was running
+    ///
+    /// Takes effect at once rather than through the command queue, which the
+    /// worker doesn't read while typing.
+    pub fn pause(&self) -> bool {
+        self.tracker.set_paused(true)
+    }
+
+    /// Continue a paused operation where it stopped, returning whether it was paused
+    pub fn resume(&self) -> bool {
+        self.tracker.set_paused(false)
+    }
+
+    /// Current typing state, cheap enough to poll
+    pub fn typing_state(&self) -> TypingState {
+        self.tracker.snapshot()
+    }
+
+    /// Change the speed, from the next keystroke on even mid-text
+    pub fn set_typing_speed(&self, speed: TypingSpeed) {
+        info!("Typing speed set to {speed:?}");
+        self.update_speed(|(typing_speed, _)| *typing_speed = speed);
+    }
+
+    /// Type with `delay_ms` between keystrokes whatever the speed, or follow
+    /// the speed again with `None`, from the next keystroke on
+    pub fn set_custom_delay(&self, delay_ms: Option<u64>) {
+        info!("Custom typing delay set to {delay_ms:?}ms");
+        self.update_speed(|(_, custom_delay)| *custom_delay = delay_ms);
+    }
+
+    fn update_speed(&self, update: impl FnOnce(&mut (TypingSpeed, Option<u64>))) {
+        let mut speed = self.speed.lock().unwrap();
+        update(&mut speed);
+        let (typing_speed, custom_delay) = *speed;
+        self.key_delay_ms.store(
+            custom_delay.unwrap_or(typing_speed.delay_ms()),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Type continuously or line by line, `lines` pacing the latter
+    pub async fn set_typing_mode(
+        &self,
+        mode: TypingMode,
+        lines: LineOptions,
+    ) -> Result<(), TypingError> {
+        self.tx
+            .send(KeyboardCommand::SetMode(mode, lines))
+            .await
+            .map_err(|_| TypingError::WorkerStopped)
+    }
+
+    /// Split the following texts into chunks as `chunking` says
+    pub async fn set_chunking(&self, chunking: Chunking) -> Result<(), TypingError> {
+        self.tx
+            .send(KeyboardCommand::SetChunking(chunking))
+            .await
+            .map_err(|_| TypingError::WorkerStopped)
+    }
+
+    /// Vary the following key delays by up to `percent` either way
+    pub async fn set_jitter(&self, percent: u8) -> Result<(), TypingError> {
+        self.tx
+            .send(KeyboardCommand::SetJitter(percent))
+            .await
+            .map_err(|_| TypingError::WorkerStopped)
+    }
+}
+
+/// Process commands until shutdown or until every sender is gone
+fn run_worker<B: KeyBackend>(
+    backend: &mut B,
+    mut rx: mpsc::Receiver<KeyboardCommand>,
+    tracker: &TypingTracker,
+    secure_input: &dyn SecureInputProbe,
+    key_delay_ms: &AtomicU64,
+) {
+    let mut typing_mode = TypingMode::default();
+    let mut line_options = LineOptions::default();
+    let mut chunking = Chunking::default();
+    let mut jitter_percent = 0;
+    let mut rng = JitterRng::from_clock();
+
+    while let Some(cmd) = rx.blocking_recv() {
+        match cmd {
+            KeyboardCommand::TypeText(text, cancellation_flag, done) => {
+                debug!(
+                    "Typing text with {}ms between keystrokes, {typing_mode:?}",
+                    key_delay_ms.load(Ordering::Relaxed)
+                );
+                let (text, line_delay) = match typing_mode {
+                    TypingMode::Continuous => (text, None),
+                    TypingMode::LineByLine => (
+                        line_by_line_text(&text, line_options.final_newline),
+                        Some(line_options.line_delay),
+                    ),
+                };
+                let chars: Vec<char> = text.chars().collect();
+                let operation = tracker.begin(chars.len());
+
+                // A panicking keystroke costs this operation, not the worker
+                let typed = panic::catch_unwind(AssertUnwindSafe(|| {
+                    type_chars(
+                        backend,
+                        &chars,
+                        &cancellation_flag,
+                        Pacing {
+                            key_delay_ms,
+                            line_delay,
+                            chunking,
+                            jitter_percent,
+                        },
+                        &mut rng,
+                        tracker,
+                        secure_input,
+                    )
+                }));
+                if typed.is_err() {
+                    error!("Typing panicked, releasing modifier keys");
+                    release_modifiers(backend);
+                }
+
+                let blocked = matches!(typed, Ok(Stop::SecureInput));
+                let rejected = matches!(typed, Ok(Stop::InputRejected));
+                let cancelled = blocked
+                    || rejected
+                    || typed.is_err()
+                    || cancellation_flag.load(Ordering::Relaxed);
+                let mut outcome = operation.finish(cancelled);
+                outcome.secure_input = blocked;
+                outcome.input_rejected = rejected;
+                info!("Finished typing text");
+                let _ = done.send(outcome);
+            }
+            KeyboardCommand::PasteKeystroke(chars, cancellation_flag, done) => {
+                let operation = tracker.begin(chars);
+                let stop = if cancellation_flag.load(Ordering::Relaxed) {
+                    info!("Keystroke paste cancelled before it started");
+                    Stop::Done
+                } else if secure_input.enabled() {
+                    warn!("Secure input is on, not pressing the paste shortcut");
+                    Stop::SecureInput
+                } else {
+                    match press_paste_shortcut(backend) {
+                        Ok(()) => {
+                            info!("Pasted {chars} characters with the paste shortcut");
+                            tracker.advance(chars);
+                            Stop::Done
+                        }
+                        Err(e) => {
+                            error!("Paste shortcut rejected: {e}");
+                            Stop::InputRejected
+                        }
+                    }
+                };
+                let cancelled = stop != Stop::Done || cancellation_flag.load(Ordering::Relaxed);
+                let mut outcome = operation.finish(cancelled);
+                outcome.secure_input = stop == Stop::SecureInput;
+                outcome.input_rejected = stop == Stop::InputRejected;
+                let _ = done.send(outcome);
+            }
+            KeyboardCommand::SetMode(mode, lines) => {
+                info!("Typing mode set to {mode:?} with {lines:?}");
+                typing_mode = mode;
+                line_options = lines;
+            }
+            KeyboardCommand::SetChunking(chunks) => {
+                info!("Chunking set to {chunks:?}");
+                chunking = chunks;
+            }
+            KeyboardCommand::SetJitter(percent) => {
+                info!("Typing jitter set to {percent}%");
+                jitter_percent = percent;
+            }
+            KeyboardCommand::ReleaseModifiers => release_modifiers(backend),
+            KeyboardCommand::Shutdown(ack) => {
+                release_modifiers(backend);
+                info!("Keyboard worker stopped");
+                // Later sends fail with WorkerStopped instead of queueing
+                rx.close();
+                let _ = ack.send(());
+                break;
+            }
+        }
+    }
+}
+
+/// Why `type_chars` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stop {
+    /// Typed everything or was cancelled
+    Done,
+    /// Secure input was on at a chunk boundary
+    SecureInput,
+    /// The system rejected `MAX_CONSECUTIVE_FAILURES` keystrokes in a row
+    InputRejected,
+}
+
+/// Waits `type_chars` puts between keystrokes and chunks
+#[derive(Debug, Clone, Copy)]
+struct Pacing<'a> {
+    /// After each keystroke, in milliseconds, read each time as it may change
+    key_delay_ms: &'a AtomicU64,
+    /// After each Return instead of the key delay, in line by line mode
+    line_delay: Option<Duration>,
+    chunking: Chunking,
+    /// Percent `delay` randomly varies by
+    jitter_percent: u8,
+}
+
+/// `chars` split into chunks of `size`, the last one possibly shorter
+fn chunk_text(chars: &[char], size: usize) -> Vec<String> {
+    // A size of 0 would panic, the config never holds one
+    chars
+        .chunks(size.max(1))
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect()
+}
+
+/// Type `chars` in chunks, stopping early once `cancellation_flag` is set,
+/// secure input turns on or the system keeps rejecting keystrokes
+fn type_chars<B: KeyBackend>(
+    backend: &mut B,
+    chars: &[char],
+    cancellation_flag: &AtomicBool,
+    pacing: Pacing<'_>,
+    rng: &mut JitterRng,
+    tracker: &TypingTracker,
+    secure_input: &dyn SecureInputProbe,
+) -> Stop {
+    let chunks = chunk_text(chars, pacing.chunking.size);
+    // Rejected keystrokes in a row, across chunks
+    let mut failures = 0;
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        // Check cancellation flag at the start of each chunk
+        if cancellation_flag.load(Ordering::Relaxed) {
+            info!("Typing cancelled by user at chunk {i}");
+            break;
+        }
+        // macOS would drop the keystrokes, so stop instead of losing the rest
+        if secure_input.enabled() {
+            warn!("Secure input is on, stopping at chunk {i}");
+            return Stop::SecureInput;
+        }
+        debug!("Processing chunk {} of {}", i + 1, chunks.len());
+
+        // Type each character in the chunk
+        for (char_index, ch) in chunk.chars().enumerate() {
+            // Check cancellation at the start of each character for immediate response
+            if char_index == 0 && cancellation_flag.load(Ordering::Relaxed) {
+                info!("Typing cancelled by user");
+                break;
+            }
+            // Check cancellation flag periodically (every 10 characters)
+            if char_index % 10 == 0 && cancellation_flag.load(Ordering::Relaxed) {
+                info!("Typing cancelled by user");
+                break;
+            }
+
+            // The rest of the text stays put until resumed
+            if tracker.is_paused() {
+                info!("Typing paused");
+                wait_while_paused(tracker, cancellation_flag);
+                if cancellation_flag.load(Ordering::Relaxed) {
+                    info!("Typing cancelled by user while paused");
+                    break;
+                }
+                info!("Typing resumed");
+            }
+
+            let result = match ch {
+                '\n' => backend.key(Key::Return, Direction::Click),
+                '\t' => backend.key(Key::Tab, Direction::Click),
+                _ => backend.text(&ch.to_string()),
+            };
+            match result {
+                Ok(()) => {
+                    failures = 0;
+                    tracker.advance(1);
+                }
+                Err(e) => {
+                    failures += 1;
+                    warn!("Keystroke rejected ({failures} in a row): {e}");
+                    if failures >= MAX_CONSECUTIVE_FAILURES {
+                        error!("Keyboard input keeps failing, abandoning the operation");
+                        return Stop::InputRejected;
+                    }
+                }
+            }
+            match pacing.line_delay {
+                Some(line_delay) if ch == '\n' => {
+                    backend.pause(line_delay);
+                    if cancellation_flag.load(Ordering::Relaxed) {
+                        info!("Typing cancelled by user after a line");
+                        break;
+                    }
+                }
+                _ => backend.pause(jittered_delay(
+                    Duration::from_millis(pacing.key_delay_ms.load(Ordering::Relaxed)),
+                    pacing.jitter_percent,
+                    ch,
+                    rng.next(),
+                )),
+            }
+        }
+        tracker.notify();
+
+        // Check if cancelled before continuing to next chunk
+        if cancellation_flag.load(Ordering::Relaxed) {
+            info!("Typing cancelled by user");
+            break;
+        }
+
+        if i < chunks.len() - 1 {
+            backend.pause(pacing.chunking.pause);
+        }
+    }
+    Stop::Done
+}
+
+/// Block until the operation is resumed or cancelled
+///
+/// Sleeps for real rather than through the backend, as time spent paused
+/// isn't part of typing.
+fn wait_while_paused(tracker: &TypingTracker, cancellation_flag: &AtomicBool) {
+    while tracker.is_paused() && !cancellation_flag.load(Ordering::Relaxed) {
+        std::thread::sleep(PAUSE_POLL);
+    }
+}
+
+/// Press Cmd+V or Ctrl+V, releasing the modifier even if V was rejected
+fn press_paste_shortcut<B: KeyBackend>(backend: &mut B) -> Result<(), String> {
+    backend.key(PASTE_MODIFIER, Direction::Press)?;
+    let pasted = backend.key(Key::Unicode('v'), Direction::Click);
+    let released = backend.key(PASTE_MODIFIER, Direction::Release);
+    pasted.and(released)
+}
+
+fn release_modifiers<B: KeyBackend>(backend: &mut B) {
+    for key in MODIFIER_KEYS {
+        if let Err(e) = backend.key(key, Direction::Release) {
+            debug!("Failed to release {key:?}: {e}");
+        }
+    }
+}
+
+/// Why typing failed once the worker is gone
+///
+/// On Wayland the likely cause is that enigo has no X server to type through.
+/// Elsewhere the backend's own startup error, if it never started, says more
+/// than "not running".
+pub(crate) fn worker_stopped_error(
+    display_server: Option<DisplayServer>,
+    startup_error: Option<&str>,
+) -> TypingError {
+    match (display_server, startup_error) {
+        (Some(DisplayServer::Wayland), _) => TypingError::WaylandUnsupported,
+        (_, Some(e)) => TypingError::Unavailable(e.to_string()),
+        _ => TypingError::WorkerStopped,
+    }
+}
+
+/// Join the worker thread if it exits by `deadline`
+///
+/// The worker acknowledges a shutdown before dropping its backend, so this
+/// waits for the backend to be gone too. Already joined counts as stopped.
+fn join_worker(worker: &Mutex<Option<JoinHandle<()>>>, deadline: Instant) -> bool {
+    let mut worker = worker.lock().unwrap();
+    let Some(handle) = worker.take() else {
+        return true;
+    };
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            *worker = Some(handle);
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    if handle.join().is_err() {
+        warn!("Keyboard worker panicked while stopping");
+    }
+    true
+}
+
+/// Queue a shutdown command and wait for the worker to acknowledge it
+pub(crate) fn send_shutdown(tx: &mpsc::Sender<KeyboardCommand>, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let (ack_tx, ack_rx) = std::sync::mpsc::channel();
+    let mut command = KeyboardCommand::Shutdown(ack_tx);
+    // Not `blocking_send`, which panics when called from async code
+    loop {
+        match tx.try_send(command) {
+            Ok(()) => break,
+            // The worker is already gone
+            Err(TrySendError::Closed(_)) => return true,
+            Err(TrySendError::Full(returned)) => {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                command = returned;
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+    ack_rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .is_ok()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::typing_harness::{
+        keyboard_with, pausing_keyboard, recording_keyboard, recording_keyboard_with_probe, Op,
+        RecordingBackend, SecureInputAfter,
+    };
 
     #[test]
     fn test_typing_speed_default() {
@@ -150,6 +956,23 @@ mod tests {
         assert_eq!(TypingSpeed::Fast.delay_ms(), 10);
     }
 
+    #[test]
+    fn test_stopped_worker_on_wayland_maps_to_wayland_error() {
+        assert_eq!(
+            worker_stopped_error(Some(DisplayServer::Wayland), Some("no X server")),
+            TypingError::WaylandUnsupported
+        );
+        assert_eq!(
+            worker_stopped_error(Some(DisplayServer::X11), None),
+            TypingError::WorkerStopped
+        );
+        assert_eq!(worker_stopped_error(None, None), TypingError::WorkerStopped);
+        assert_eq!(
+            worker_stopped_error(None, Some("no display")),
+            TypingError::Unavailable("no display".to_string())
+        );
+    }
+
     #[test]
     fn test_typing_speed_serialization() {
         assert_eq!(
@@ -182,14 +1005,14 @@ mod tests {
         );
     }
 
-    #[test]
-    #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
-    #[cfg(not(tarpaulin))]
-    fn test_keyboard_emulator_creation() {
-        let result = KeyboardEmulator::new();
-        assert!(result.is_ok());
+    #[tokio::test]
+    async fn test_keyboard_emulator_creation() {
+        let (keyboard, recording) = recording_keyboard();
+        assert!(!keyboard.typing_state().is_typing);
+        assert!(recording.events().is_empty());
     }
 
+    /// The one test that types for real, as a smoke test on a developer machine
     #[tokio::test]
     #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
     #[cfg(not(tarpaulin))]
@@ -200,29 +1023,46 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn type_text_command(text: &str) -> KeyboardCommand {
+        let (done, _) = oneshot::channel();
+        KeyboardCommand::TypeText(text.to_string(), Arc::new(AtomicBool::new(false)), done)
+    }
+
     #[test]
     fn test_keyboard_command_creation() {
-        let cmd = KeyboardCommand::TypeText("test".to_string(), Arc::new(AtomicBool::new(false)));
-        match cmd {
-            KeyboardCommand::TypeText(text, _) => assert_eq!(text, "test"),
+        match type_text_command("test") {
+            KeyboardCommand::TypeText(text, _, _) => assert_eq!(text, "test"),
+            _ => panic!("Expected TypeText command"),
         }
     }
 
     #[test]
     fn test_keyboard_command_debug() {
-        let cmd = KeyboardCommand::TypeText("test".to_string(), Arc::new(AtomicBool::new(false)));
-        let debug_str = format!("{:?}", cmd);
+        let debug_str = format!("{:?}", type_text_command("test"));
         assert!(debug_str.contains("TypeText"));
         assert!(debug_str.contains("test"));
     }
 
-    #[test]
-    fn test_keyboard_command_clone() {
-        let cmd = KeyboardCommand::TypeText("test".to_string(), Arc::new(AtomicBool::new(false)));
-        let cloned = cmd.clone();
-        match cloned {
-            KeyboardCommand::TypeText(text, _) => assert_eq!(text, "test"),
-        }
+    #[tokio::test]
+    async fn test_handle_reports_outcome_or_stopped_worker() {
+        let outcome = TypingOutcome {
+            typed: 3,
+            total: 5,
+            cancelled: true,
+            duration: Duration::from_millis(40),
+            secure_input: false,
+            input_rejected: false,
+        };
+        let (done, rx) = oneshot::channel();
+        done.send(outcome).unwrap();
+        assert_eq!(TypingHandle(rx).finished().await, Ok(outcome));
+
+        let (done, rx) = oneshot::channel::<TypingOutcome>();
+        drop(done);
+        assert_eq!(
+            TypingHandle(rx).finished().await,
+            Err(TypingError::WorkerStopped)
+        );
     }
 
     #[test]
@@ -240,53 +1080,50 @@ mod tests {
         assert_eq!(speed, copied);
     }
 
+    /// Chunk sizes the chunking tests run with
+    const DEFAULT_CHUNK_SIZES: [usize; 4] = [1, 7, DEFAULT_CHUNK_SIZE, 1000];
+
+    fn chunks_of(text: &str, size: usize) -> Vec<String> {
+        chunk_text(&text.chars().collect::<Vec<_>>(), size)
+    }
+
     #[test]
     fn test_text_chunking_logic() {
-        let text = "a".repeat(500);
-        let chars: Vec<char> = text.chars().collect();
-        let chunks: Vec<String> = chars
-            .chunks(200)
-            .map(|chunk| chunk.iter().collect::<String>())
-            .collect();
-        assert_eq!(chunks.len(), 3);
-        assert_eq!(chunks[0].len(), 200);
-        assert_eq!(chunks[1].len(), 200);
-        assert_eq!(chunks[2].len(), 100);
+        for size in DEFAULT_CHUNK_SIZES {
+            let text = "a".repeat(2 * size + size / 2 + 1);
+            let chunks = chunks_of(&text, size);
+            assert_eq!(chunks.len(), 3, "size {size}");
+            assert_eq!(chunks[0].len(), size);
+            assert_eq!(chunks[1].len(), size);
+            assert_eq!(chunks[2].len(), size / 2 + 1);
+        }
     }
 
     #[test]
     fn test_empty_text_chunking() {
-        let text = "";
-        let chars: Vec<char> = text.chars().collect();
-        let chunks: Vec<String> = chars
-            .chunks(200)
-            .map(|chunk| chunk.iter().collect::<String>())
-            .collect();
-        assert_eq!(chunks.len(), 0);
+        for size in DEFAULT_CHUNK_SIZES {
+            assert!(chunks_of("", size).is_empty());
+        }
     }
 
     #[test]
     fn test_single_char_chunking() {
-        let text = "a";
-        let chars: Vec<char> = text.chars().collect();
-        let chunks: Vec<String> = chars
-            .chunks(200)
-            .map(|chunk| chunk.iter().collect::<String>())
-            .collect();
-        assert_eq!(chunks.len(), 1);
-        assert_eq!(chunks[0], "a");
+        for size in DEFAULT_CHUNK_SIZES {
+            assert_eq!(chunks_of("a", size), ["a"]);
+        }
     }
 
     #[test]
     fn test_exact_chunk_size_text() {
-        let text = "a".repeat(200);
-        let chars: Vec<char> = text.chars().collect();
-        let chunks: Vec<String> = chars
-            .chunks(200)
-            .map(|chunk| chunk.iter().collect::<String>())
-            .collect();
-        assert_eq!(chunks.len(), 1);
-        assert_eq!(chunks[0].len(), 200);
+        for size in DEFAULT_CHUNK_SIZES {
+            let text = "a".repeat(size);
+            assert_eq!(chunks_of(&text, size), [text.clone()]);
+        }
+    }
+
+    #[test]
+    fn test_zero_chunk_size_types_a_char_at_a_time() {
+        assert_eq!(chunks_of("ab", 0), ["a", "b"]);
     }
 
     #[test]
@@ -300,14 +1137,12 @@ mod tests {
 
     #[test]
     fn test_unicode_text_chunking() {
-        let text = "😀🎉".repeat(100);
-        let chars: Vec<char> = text.chars().collect();
-        let chunks: Vec<String> = chars
-            .chunks(200)
-            .map(|chunk| chunk.iter().collect::<String>())
-            .collect();
-        assert_eq!(chunks.len(), 1);
-        assert_eq!(chunks[0].chars().count(), 200);
+        for size in DEFAULT_CHUNK_SIZES {
+            let text = "😀🎉".repeat(size);
+            let chunks = chunks_of(&text, size);
+            assert_eq!(chunks.len(), 2);
+            assert!(chunks.iter().all(|chunk| chunk.chars().count() == size));
+        }
     }
 
     #[test]
@@ -343,25 +1178,34 @@ mod tests {
 
     #[test]
     fn test_chunk_delay_calculation() {
-        // Chunk delay is hardcoded to 100ms
-        let chunk_delay = Duration::from_millis(100);
-        assert_eq!(chunk_delay.as_millis(), 100);
+        let chunking = Chunking::default();
+        assert_eq!(chunking.size, DEFAULT_CHUNK_SIZE);
+        assert_eq!(
+            chunking.pause.as_millis(),
+            u128::from(DEFAULT_CHUNK_DELAY_MS)
+        );
     }
 
     #[test]
     fn test_keyboard_command_exhaustive_match() {
-        let cmd = KeyboardCommand::TypeText("test".to_string(), Arc::new(AtomicBool::new(false)));
-        match cmd {
-            KeyboardCommand::TypeText(_, _) => {
+        match type_text_command("test") {
+            KeyboardCommand::TypeText(_, _, _) => {
                 // All variants handled
             }
+            KeyboardCommand::PasteKeystroke(_, _, _) => {}
+            KeyboardCommand::SetMode(_, _) => {}
+            KeyboardCommand::SetChunking(_) => {}
+            KeyboardCommand::SetJitter(_) => {}
+            KeyboardCommand::ReleaseModifiers => {}
+            KeyboardCommand::Shutdown(_) => {}
         }
     }
 
     #[test]
     fn test_keyboard_command_pattern_matching() {
-        let cmd = KeyboardCommand::TypeText("Hello".to_string(), Arc::new(AtomicBool::new(false)));
-        let KeyboardCommand::TypeText(text, _) = cmd;
+        let KeyboardCommand::TypeText(text, _, _) = type_text_command("Hello") else {
+            panic!("Expected TypeText command");
+        };
         assert_eq!(text, "Hello");
     }
 
@@ -377,27 +1221,467 @@ mod tests {
     #[test]
     fn test_keyboard_command_with_cancellation() {
         let flag = Arc::new(AtomicBool::new(true));
-        let cmd = KeyboardCommand::TypeText("test".to_string(), flag.clone());
+        let (done, _) = oneshot::channel();
+        let cmd = KeyboardCommand::TypeText("test".to_string(), flag.clone(), done);
 
-        let KeyboardCommand::TypeText(_, cancellation_flag) = cmd;
+        let KeyboardCommand::TypeText(_, cancellation_flag, _) = cmd else {
+            panic!("Expected TypeText command");
+        };
         assert!(cancellation_flag.load(Ordering::Relaxed));
     }
 
+    #[test]
+    fn test_keyboard_emulator_starts_idle() {
+        let (keyboard, _) = recording_keyboard();
+        let state = keyboard.typing_state();
+        assert!(!state.is_typing);
+        assert_eq!(state.queue_len, 0);
+    }
+
+    #[tokio::test]
+    async fn test_keyboard_emulator_set_typing_speed() {
+        let (keyboard, _) = recording_keyboard();
+        keyboard.set_typing_speed(TypingSpeed::Slow);
+        assert_eq!(keyboard.key_delay_ms.load(Ordering::Relaxed), 50);
+    }
+
     #[tokio::test]
-    #[ignore = "Creates real keyboard emulator that can type on system - run with --ignored flag"]
-    #[cfg(not(tarpaulin))]
     async fn test_keyboard_emulator_multiple_operations() {
-        let keyboard = KeyboardEmulator::new().unwrap();
+        let (keyboard, recording) = recording_keyboard();
 
-        // Send multiple commands
+        // Queue both before waiting, they are typed in order
         let flag1 = Arc::new(AtomicBool::new(false));
         let flag2 = Arc::new(AtomicBool::new(false));
+        let first = keyboard.type_text("test1", flag1).await.unwrap();
+        let second = keyboard.type_text("test2", flag2).await.unwrap();
+
+        assert_eq!(first.finished().await.unwrap().typed, 5);
+        assert_eq!(second.finished().await.unwrap().typed, 5);
+        recording.assert_typed("test1test2");
+    }
+
+    async fn type_and_wait(
+        keyboard: &KeyboardEmulator,
+        text: &str,
+        flag: Arc<AtomicBool>,
+    ) -> TypingOutcome {
+        let handle = keyboard.type_text(text, flag).await.unwrap();
+        handle.finished().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_output_matches_input() {
+        let (keyboard, recording) = recording_keyboard();
+        let text = "Line1\nLine2\tTab 😀 é\n".repeat(30);
+
+        let outcome = type_and_wait(&keyboard, &text, Arc::new(AtomicBool::new(false))).await;
+        assert_eq!(outcome.typed, text.chars().count());
+        assert!(!outcome.cancelled);
+        recording.assert_typed(&text);
+    }
+
+    #[tokio::test]
+    async fn test_long_text_is_typed_in_chunks() {
+        let (keyboard, recording) = recording_keyboard();
+        let text = "a".repeat(2 * DEFAULT_CHUNK_SIZE + 100);
+
+        type_and_wait(&keyboard, &text, Arc::new(AtomicBool::new(false))).await;
+        assert_eq!(
+            recording.chunk_boundaries(),
+            [DEFAULT_CHUNK_SIZE, 2 * DEFAULT_CHUNK_SIZE]
+        );
+        assert_eq!(recording.typed_text().len(), text.len());
+    }
+
+    #[tokio::test]
+    async fn test_chunking_is_configurable() {
+        let (keyboard, recording) = recording_keyboard();
+        keyboard.set_typing_speed(TypingSpeed::Fast);
+        let chunking = Chunking {
+            size: 3,
+            pause: Duration::from_millis(400),
+        };
+        keyboard.set_chunking(chunking).await.unwrap();
+
+        type_and_wait(&keyboard, "abcdefgh", Arc::new(AtomicBool::new(false))).await;
+        assert_eq!(recording.chunk_boundaries(), [3, 6]);
+        assert_eq!(
+            recording.elapsed(),
+            Duration::from_millis(TypingSpeed::Fast.delay_ms()) * 8 + chunking.pause * 2
+        );
+    }
+
+    #[test]
+    fn test_jittered_delay_is_constant_without_jitter() {
+        let delay = Duration::from_millis(25);
+        let mut rng = JitterRng::new(7);
+        for ch in "Hello, world.".chars() {
+            assert_eq!(jittered_delay(delay, 0, ch, rng.next()), delay);
+        }
+    }
+
+    #[test]
+    fn test_jittered_delay_varies_within_bounds() {
+        let delay = Duration::from_millis(25);
+        let mut rng = JitterRng::new(7);
+        let delays: Vec<Duration> = (0..200)
+            .map(|_| jittered_delay(delay, 20, 'a', rng.next()))
+            .collect();
+
+        assert!(delays.iter().any(|d| *d != delays[0]));
+        for d in &delays {
+            assert!(*d >= Duration::from_millis(20) && *d <= Duration::from_millis(30));
+        }
+    }
+
+    #[test]
+    fn test_jittered_delay_is_never_zero() {
+        let mut rng = JitterRng::new(7);
+        for delay_us in [1, 2, 3, 1000] {
+            let delay = Duration::from_micros(delay_us);
+            for _ in 0..100 {
+                let jittered = jittered_delay(delay, u8::MAX, 'a', rng.next());
+                assert!(jittered > Duration::ZERO);
+                assert!(jittered >= delay / 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_jittered_delay_sometimes_lingers_after_punctuation() {
+        let delay = Duration::from_millis(25);
+        let mut rng = JitterRng::new(7);
+        let longest = (0..200)
+            .map(|_| jittered_delay(delay, 10, '.', rng.next()))
+            .max()
+            .unwrap();
+        assert!(longest > delay * 3 / 2);
+    }
 
-        let result1 = keyboard.type_text("test1", flag1).await;
-        let result2 = keyboard.type_text("test2", flag2).await;
+    #[tokio::test]
+    async fn test_jitter_varies_the_worker_delays() {
+        let (keyboard, recording) = recording_keyboard();
+        keyboard.set_typing_speed(TypingSpeed::Fast);
+        keyboard.set_jitter(30).await.unwrap();
+
+        type_and_wait(&keyboard, &"a".repeat(50), Arc::new(AtomicBool::new(false))).await;
+        let delays = recording.key_delays();
+        assert_eq!(delays.len(), 50);
+        assert!(delays.iter().any(|d| *d != delays[0]));
+        assert!(delays.iter().all(|d| *d >= Duration::from_millis(7)));
+    }
+
+    #[tokio::test]
+    async fn test_delays_follow_the_typing_speed() {
+        for speed in [TypingSpeed::Slow, TypingSpeed::Normal, TypingSpeed::Fast] {
+            let (keyboard, recording) = recording_keyboard();
+            keyboard.set_typing_speed(speed);
+            let text = "x".repeat(DEFAULT_CHUNK_SIZE + 1);
+
+            type_and_wait(&keyboard, &text, Arc::new(AtomicBool::new(false))).await;
+            let delay = Duration::from_millis(speed.delay_ms());
+            recording.assert_delays_near(delay, Duration::ZERO);
+            assert_eq!(recording.key_delays().len(), text.len());
+            assert_eq!(
+                recording.elapsed(),
+                delay * text.len() as u32 + Chunking::default().pause
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_input_abandons_the_operation() {
+        let (backend, recording) = RecordingBackend::new();
+        let keyboard = keyboard_with(backend.reject_from(25));
+        let handle = keyboard
+            .type_text(&"x".repeat(100), Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+
+        let outcome = handle.finished().await.unwrap();
+        assert!(outcome.input_rejected);
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.typed, 25);
+        // Every keystroke is followed by its delay, except the one it gave up
+        // on, so the other 65 characters weren't attempted
+        let delays = recording
+            .events()
+            .iter()
+            .filter(|event| matches!(event.op, Op::Pause(_)))
+            .count();
+        assert_eq!(delays, 25 + MAX_CONSECUTIVE_FAILURES - 1);
+
+        // The worker is still there, and a single dropped keystroke isn't
+        // enough to give up on the next operation
+        let handle = keyboard
+            .type_text("y", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        let outcome = handle.finished().await.unwrap();
+        assert!(!outcome.input_rejected);
+        assert_eq!(outcome.typed, 0);
+    }
+
+    #[test]
+    fn test_backend_startup_error_is_reported() {
+        let result = KeyboardEmulator::with_backend(|| {
+            Err::<RecordingBackend, _>("no display to type on".to_string())
+        });
+        let expected = match SystemProbe.display_server() {
+            Some(DisplayServer::Wayland) => TypingError::WaylandUnsupported,
+            _ => TypingError::Unavailable("no display to type on".to_string()),
+        };
+        assert_eq!(result.err(), Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_emulator_fails_with_its_error() {
+        let error = TypingError::Unavailable("no display to type on".to_string());
+        let keyboard = KeyboardEmulator::unavailable(error.clone());
+
+        let result = keyboard
+            .type_text("x", Arc::new(AtomicBool::new(false)))
+            .await;
+        assert_eq!(result.err(), Some(error));
+        assert_eq!(keyboard.typing_state().queue_len, 0);
+        assert!(keyboard.shutdown(Duration::from_millis(10)));
+    }
+
+    #[tokio::test]
+    async fn test_pause_resumes_where_it_stopped() {
+        let (keyboard, recording) =
+            pausing_keyboard(DEFAULT_CHUNK_SIZE + 5, Duration::from_millis(60));
+        let text: String = (0..2 * DEFAULT_CHUNK_SIZE)
+            .map(|i| char::from(b'a' + (i % 26) as u8))
+            .collect();
+
+        type_and_wait(&keyboard, &text, Arc::new(AtomicBool::new(false))).await;
+        recording.assert_typed(&text);
+        // Nothing more was typed while paused
+        let paused = recording.while_paused().unwrap();
+        assert!(paused.is_paused);
+        assert_eq!(paused.typed, DEFAULT_CHUNK_SIZE + 5);
+        assert!(!keyboard.typing_state().is_paused);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_while_paused() {
+        let (keyboard, recording) = pausing_keyboard(10, Duration::from_secs(60));
+        let flag = Arc::new(AtomicBool::new(false));
+        let cancel = flag.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.store(true, Ordering::Relaxed);
+        });
+
+        let handle = keyboard.type_text(&"x".repeat(100), flag).await.unwrap();
+        assert!(handle.finished().await.unwrap().cancelled);
+        assert_eq!(recording.typed_text().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_pause_needs_a_running_operation() {
+        let (keyboard, _recording) = recording_keyboard();
+        assert!(!keyboard.pause());
+        assert!(!keyboard.resume());
+        assert!(!keyboard.typing_state().is_paused);
+    }
+
+    #[test]
+    fn test_paste_method_uses_keystroke() {
+        assert!(!PasteMethod::Type.uses_keystroke(1_000_000, 10));
+        assert!(PasteMethod::Keystroke.uses_keystroke(1, 10));
+        assert!(!PasteMethod::Auto.uses_keystroke(10, 10));
+        assert!(PasteMethod::Auto.uses_keystroke(11, 10));
+    }
+
+    #[tokio::test]
+    async fn test_paste_keystroke_presses_the_shortcut() {
+        let (keyboard, recording) = recording_keyboard();
+
+        let outcome = keyboard
+            .try_paste_keystroke(50_000, Arc::new(AtomicBool::new(false)), false)
+            .await
+            .unwrap()
+            .finished()
+            .await
+            .unwrap();
+        assert_eq!((outcome.typed, outcome.total), (50_000, 50_000));
+        assert!(!outcome.cancelled);
+        let ops: Vec<Op> = recording.events().into_iter().map(|e| e.op).collect();
+        assert_eq!(
+            ops,
+            [
+                Op::Key(PASTE_MODIFIER, Direction::Press),
+                Op::Key(Key::Unicode('v'), Direction::Click),
+                Op::Key(PASTE_MODIFIER, Direction::Release),
+            ]
+        );
+    }
 
-        assert!(result1.is_ok());
-        assert!(result2.is_ok());
+    #[tokio::test]
+    async fn test_queued_keystroke_paste_is_cancelled_with_the_queue() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let (backend, recording) = RecordingBackend::new();
+        let keyboard = keyboard_with(backend.cancel_after(2, flag.clone()));
+
+        let typing = keyboard.type_text("abcd", flag.clone()).await.unwrap();
+        let pasting = keyboard.try_paste_keystroke(100, flag, true).await.unwrap();
+        assert!(typing.finished().await.unwrap().cancelled);
+        let outcome = pasting.finished().await.unwrap();
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.typed, 0);
+        assert!(!recording
+            .events()
+            .iter()
+            .any(|e| e.op == Op::Key(Key::Unicode('v'), Direction::Click)));
+    }
+
+    #[test]
+    fn test_line_by_line_text() {
+        assert_eq!(line_by_line_text("a\nb", true), "a\nb\n");
+        assert_eq!(line_by_line_text("a\nb\n", true), "a\nb\n");
+        assert_eq!(line_by_line_text("a\nb\n", false), "a\nb");
+        assert_eq!(line_by_line_text("a\r\n\r\nb", false), "a\n\nb");
+        // Empty lines still press Return
+        assert_eq!(line_by_line_text("a\n\n\n", false), "a\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_line_by_line_waits_after_each_line() {
+        let line_delay = Duration::from_millis(300);
+        let (keyboard, recording) = recording_keyboard();
+        keyboard
+            .set_typing_mode(
+                TypingMode::LineByLine,
+                LineOptions {
+                    line_delay,
+                    final_newline: false,
+                },
+            )
+            .await
+            .unwrap();
+
+        type_and_wait(&keyboard, "ab\n\ncd\n", Arc::new(AtomicBool::new(false))).await;
+        recording.assert_typed("ab\n\ncd");
+        let line_pauses = recording
+            .events()
+            .iter()
+            .filter(|event| event.op == Op::Pause(line_delay))
+            .count();
+        assert_eq!(line_pauses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_line_by_line_stops_after_the_cancelled_line() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let (backend, recording) = RecordingBackend::new();
+        let keyboard = keyboard_with(backend.cancel_after(2, flag.clone()));
+        keyboard
+            .set_typing_mode(TypingMode::LineByLine, LineOptions::default())
+            .await
+            .unwrap();
+
+        type_and_wait(&keyboard, "ab\ncd\nef", flag).await;
+        // Cancelled mid-line, the wait after its Return is the next check
+        recording.assert_typed("ab\n");
+    }
+
+    #[tokio::test]
+    async fn test_custom_delay_overrides_the_speed() {
+        let (keyboard, recording) = recording_keyboard();
+        keyboard.set_typing_speed(TypingSpeed::Fast);
+        keyboard.set_custom_delay(Some(120));
+        type_and_wait(&keyboard, "abc", Arc::new(AtomicBool::new(false))).await;
+        recording.assert_delays_near(Duration::from_millis(120), Duration::ZERO);
+
+        // Back to the speed once the override is cleared
+        let (keyboard, recording) = recording_keyboard();
+        keyboard.set_custom_delay(Some(120));
+        keyboard.set_custom_delay(None);
+        type_and_wait(&keyboard, "abc", Arc::new(AtomicBool::new(false))).await;
+        recording.assert_delays_near(
+            Duration::from_millis(TypingSpeed::Normal.delay_ms()),
+            Duration::ZERO,
+        );
+    }
+
+    #[tokio::test]
+    async fn test_speed_changes_apply_mid_text() {
+        // Paused after 50 characters, long enough to change the speed meanwhile
+        let (keyboard, recording) = pausing_keyboard(50, Duration::from_millis(200));
+        keyboard.set_typing_speed(TypingSpeed::Fast);
+        let handle = keyboard
+            .type_text(&"x".repeat(100), Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        while !keyboard.typing_state().is_paused {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        keyboard.set_typing_speed(TypingSpeed::Slow);
+        handle.finished().await.unwrap();
+
+        let delays = recording.key_delays();
+        assert_eq!(delays.len(), 100);
+        let fast = Duration::from_millis(TypingSpeed::Fast.delay_ms());
+        let slow = Duration::from_millis(TypingSpeed::Slow.delay_ms());
+        // The 50th character's delay may race the change
+        assert!(delays[..49].iter().all(|delay| *delay == fast));
+        assert!(delays[50..].iter().all(|delay| *delay == slow));
+    }
+
+    #[test]
+    fn test_clamp_typing_delay() {
+        assert_eq!(clamp_typing_delay(120), 120);
+        assert_eq!(clamp_typing_delay(0), MIN_TYPING_DELAY_MS);
+        assert_eq!(clamp_typing_delay(-5), MIN_TYPING_DELAY_MS);
+        assert_eq!(clamp_typing_delay(60_000), MAX_TYPING_DELAY_MS);
+        assert_eq!(clamp_typing_delay(5000), 5000);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_positions() {
+        // The flag is checked every 10 characters within a chunk and between chunks
+        for (cancel_after, stopped_at) in [
+            (1, 10),
+            (245, 250),
+            (250, 250),
+            (DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_SIZE),
+        ] {
+            let flag = Arc::new(AtomicBool::new(false));
+            let (backend, recording) = RecordingBackend::new();
+            let keyboard = keyboard_with(backend.cancel_after(cancel_after, flag.clone()));
+
+            let outcome = type_and_wait(&keyboard, &"a".repeat(1000), flag).await;
+            assert!(outcome.cancelled);
+            assert_eq!(outcome.typed, stopped_at, "cancelled after {cancel_after}");
+            assert_eq!(recording.typed_text().len(), stopped_at);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secure_input_stops_at_a_chunk_boundary() {
+        // Off for the first two chunks, on before the third
+        let (keyboard, recording) =
+            recording_keyboard_with_probe(SecureInputAfter::new(2, "Terminal"));
+
+        let outcome = type_and_wait(
+            &keyboard,
+            &"a".repeat(1000),
+            Arc::new(AtomicBool::new(false)),
+        )
+        .await;
+        assert!(outcome.secure_input);
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.typed, 2 * DEFAULT_CHUNK_SIZE);
+        assert_eq!(recording.typed_text().len(), 2 * DEFAULT_CHUNK_SIZE);
+
+        // The worker carries on with the next operation once it is off again
+        let (keyboard, recording) =
+            recording_keyboard_with_probe(SecureInputAfter::new(usize::MAX, "Terminal"));
+        let outcome = type_and_wait(&keyboard, "ok", Arc::new(AtomicBool::new(false))).await;
+        assert!(!outcome.secure_input);
+        recording.assert_typed("ok");
     }
 
     #[test]
@@ -460,4 +1744,95 @@ mod tests {
             }
         }
     }
+
+    /// Types nothing and records when it is dropped
+    struct DropFlagBackend(Arc<AtomicBool>);
+
+    impl KeyBackend for DropFlagBackend {
+        fn key(&mut self, _key: Key, _direction: Direction) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn text(&mut self, _text: &str) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    impl Drop for DropFlagBackend {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_shutdown_joins_the_worker() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let backend = DropFlagBackend(dropped.clone());
+        let keyboard = KeyboardEmulator::with_backend(move || Ok(backend)).unwrap();
+
+        assert!(keyboard.shutdown(Duration::from_secs(1)));
+        // Joined, so the backend is already gone
+        assert!(dropped.load(Ordering::SeqCst));
+        assert!(keyboard.worker.lock().unwrap().is_none());
+        assert!(keyboard.shutdown(Duration::from_millis(10)));
+    }
+
+    /// Records keystrokes and panics when asked to type `panic_on`
+    struct PanickingBackend {
+        events: Arc<std::sync::Mutex<Vec<String>>>,
+        panic_on: char,
+    }
+
+    impl KeyBackend for PanickingBackend {
+        fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("{key:?} {direction:?}"));
+            Ok(())
+        }
+
+        fn text(&mut self, text: &str) -> Result<(), String> {
+            if text.starts_with(self.panic_on) {
+                panic!("backend failed on {text:?}");
+            }
+            self.events.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panicking_backend_releases_modifiers_and_keeps_worker() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let backend = PanickingBackend {
+            events: events.clone(),
+            panic_on: '!',
+        };
+        let keyboard = KeyboardEmulator::with_backend(move || Ok(backend)).unwrap();
+        let flag = Arc::new(AtomicBool::new(false));
+
+        let handle = keyboard.type_text("ab!cd", flag.clone()).await.unwrap();
+        let outcome = handle.finished().await.unwrap();
+        assert!(outcome.cancelled);
+        assert_eq!(outcome.typed, 2);
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "a",
+                "b",
+                "Shift Release",
+                "Control Release",
+                "Alt Release",
+                "Meta Release"
+            ]
+        );
+        assert!(!keyboard.typing_state().is_typing);
+
+        // The worker survived and types the next operation in full
+        let handle = keyboard.type_text("ok", flag).await.unwrap();
+        let outcome = handle.finished().await.unwrap();
+        assert!(!outcome.cancelled);
+        assert_eq!(outcome.typed, 2);
+        assert!(events.lock().unwrap().ends_with(&["o".into(), "k".into()]));
+    }
 }
@@ -0,0 +1,289 @@
+/// Logger setup: stderr, plus a rotating `pasta.log` when enabled
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, logs};
+
+/// Size at which `pasta.log` is rotated
+pub const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Log files kept, including the active one
+pub const KEPT_LOG_FILES: usize = 3;
+
+/// Most verbose level that is logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+/// `pasta.log` in the app data directory
+pub fn default_log_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("pasta").join("pasta.log"))
+}
+
+/// Append-only log file that rotates to `.1`, `.2`, ... once it is too large
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    kept_files: usize,
+    file: File,
+    len: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_bytes: u64, kept_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            kept_files,
+            file,
+            len,
+        })
+    }
+
+    /// Path of the `n`th rotated file, the active file for 0
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        if n == 0 {
+            return self.path.clone();
+        }
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for n in (1..self.kept_files).rev() {
+            let from = self.rotated_path(n - 1);
+            let to = self.rotated_path(n);
+            if from.exists() {
+                // Renaming over an existing file fails on Windows
+                if to.exists() {
+                    fs::remove_file(&to)?;
+                }
+                fs::rename(from, to)?;
+            }
+        }
+        if self.kept_files <= 1 {
+            fs::remove_file(&self.path)?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    /// Writes whole records, redacting lines that may hold user content
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.len > 0 && self.len + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let text = String::from_utf8_lossy(buf);
+        let mut written = 0;
+        for line in text.split_inclusive('\n') {
+            let line = if logs::contains_content(line) {
+                format!("{}\n", logs::REDACTED_LINE)
+            } else {
+                line.to_string()
+            };
+            self.file.write_all(line.as_bytes())?;
+            written += line.len() as u64;
+        }
+        self.len += written;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Sends every record to stderr and the log file
+struct Tee(RotatingFile);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _ = io::stderr().write_all(buf);
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = io::stderr().flush();
+        self.0.flush()
+    }
+}
+
+/// Start logging as configured
+///
+/// `RUST_LOG` still overrides `log_level`. If the log file can't be opened
+/// Pasta logs to stderr only and says why.
+pub fn init(config: &Config) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(config.log_level.filter());
+    builder.parse_default_env();
+
+    let mut failure = None;
+    if config.log_to_file {
+        match open_log_file(default_log_path()) {
+            Ok((path, file)) => {
+                builder.target(env_logger::Target::Pipe(Box::new(Tee(file))));
+                logs::set_log_file(path);
+            }
+            Err(e) => failure = Some(e),
+        }
+    }
+
+    if builder.try_init().is_err() {
+        return;
+    }
+    if let Some(failure) = failure {
+        log::warn!("{failure}, logging to stderr only");
+    }
+}
+
+/// Open the log file at `path`, or say why it can't be used
+fn open_log_file(path: Option<PathBuf>) -> Result<(PathBuf, RotatingFile), String> {
+    let path = path.ok_or("No data directory for the log file")?;
+    match RotatingFile::open(&path, MAX_LOG_BYTES, KEPT_LOG_FILES) {
+        Ok(file) => Ok((path, file)),
+        Err(e) => Err(format!("Failed to open {}: {e}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_rotates_when_full() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        let mut file = RotatingFile::open(&path, 20, 3).unwrap();
+
+        file.write_all(b"first record 1234\n").unwrap();
+        assert!(!dir.path().join("pasta.log.1").exists());
+        file.write_all(b"second record 123\n").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pasta.log.1")).unwrap(),
+            "first record 1234\n"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second record 123\n");
+    }
+
+    #[test]
+    fn test_keeps_limited_number_of_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        let mut file = RotatingFile::open(&path, 10, 3).unwrap();
+
+        for i in 0..5 {
+            file.write_all(format!("record {i:03}\n").as_bytes())
+                .unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "record 004\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pasta.log.1")).unwrap(),
+            "record 003\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pasta.log.2")).unwrap(),
+            "record 002\n"
+        );
+        assert!(!dir.path().join("pasta.log.3").exists());
+    }
+
+    #[test]
+    fn test_existing_size_counts_towards_rotation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        fs::write(&path, "left over from last run\n").unwrap();
+
+        let mut file = RotatingFile::open(&path, 30, 3).unwrap();
+        file.write_all(b"new run\n").unwrap();
+
+        assert!(dir.path().join("pasta.log.1").exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new run\n");
+    }
+
+    #[test]
+    fn test_content_is_never_written() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        let mut file = RotatingFile::open(&path, MAX_LOG_BYTES, 3).unwrap();
+
+        file.write_all(b"DEBUG pasta: [content] hunter2\n").unwrap();
+        file.write_all(b"INFO pasta: Typing finished\n").unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("hunter2"));
+        assert_eq!(
+            contents,
+            format!("{}\nINFO pasta: Typing finished\n", logs::REDACTED_LINE)
+        );
+    }
+
+    #[test]
+    fn test_unusable_log_path_falls_back() {
+        let dir = tempdir().unwrap();
+        // A file where the log directory should be can't be created over
+        let blocker = dir.path().join("pasta");
+        fs::write(&blocker, "").unwrap();
+
+        let error = open_log_file(Some(blocker.join("pasta.log")))
+            .err()
+            .unwrap();
+        assert!(error.starts_with("Failed to open"));
+        assert_eq!(
+            open_log_file(None).err().unwrap(),
+            "No data directory for the log file"
+        );
+    }
+
+    #[test]
+    fn test_usable_log_path_is_opened() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("logs").join("pasta.log");
+
+        let (opened, _file) = open_log_file(Some(path.clone())).unwrap();
+        assert_eq!(opened, path);
+        assert!(path.exists());
+    }
+}
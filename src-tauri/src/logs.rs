@@ -0,0 +1,216 @@
+/// Access to Pasta's log file for the in-app log viewer
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Most lines `get_log_tail` returns
+pub const MAX_TAIL_LINES: usize = 1000;
+
+/// Longest line `get_log_tail` returns, in characters
+pub const MAX_LINE_CHARS: usize = 256;
+
+/// Lines containing any of these may include clipboard or typed content
+const CONTENT_MARKERS: &[&str] = &["[content]", "clipboard=", "text="];
+
+/// Replacement for lines that may contain user content
+pub const REDACTED_LINE: &str = "[redacted]";
+
+/// How much of the file is read per step when scanning backwards
+const TAIL_BLOCK_SIZE: u64 = 8 * 1024;
+
+/// Path of the active log file, set once file logging starts
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record where file logging writes to
+pub fn set_log_file(path: PathBuf) {
+    let _ = LOG_FILE.set(path);
+}
+
+/// Path of the active log file, `None` when file logging is disabled
+pub fn log_file() -> Option<&'static Path> {
+    LOG_FILE.get().map(PathBuf::as_path)
+}
+
+/// Last `lines` lines of the active log, redacted and capped
+pub fn get_log_tail(lines: usize) -> Result<Vec<String>, String> {
+    let path = log_file().ok_or("File logging is disabled, there is no log file to show")?;
+    read_tail(path, lines).map_err(|e| format!("Failed to read {}: {e}", path.display()))
+}
+
+/// Read the last `lines` lines of a file without reading all of it
+///
+/// Scans backwards in blocks until enough newlines are found, then redacts
+/// and truncates each line. `lines` is capped at `MAX_TAIL_LINES`.
+pub fn read_tail(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let lines = lines.min(MAX_TAIL_LINES);
+    if lines == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut file = File::open(path)?;
+    let len = file.seek(SeekFrom::End(0))?;
+
+    // Collect blocks from the end until they hold more than `lines` newlines,
+    // ignoring a newline that terminates the file
+    let mut start = len;
+    let mut newlines = 0;
+    let mut buf = Vec::new();
+    while start > 0 && newlines <= lines {
+        let block_len = TAIL_BLOCK_SIZE.min(start);
+        start -= block_len;
+
+        let mut block = vec![0; block_len as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut block)?;
+
+        newlines += block.iter().filter(|&&b| b == b'\n').count();
+        if start + block_len == len && block.last() == Some(&b'\n') {
+            newlines -= 1;
+        }
+
+        block.extend_from_slice(&buf);
+        buf = block;
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let mut tail: Vec<&str> = text.lines().collect();
+    // The first line is partial when we stopped before the start of the file
+    if start > 0 && !tail.is_empty() {
+        tail.remove(0);
+    }
+    let skip = tail.len().saturating_sub(lines);
+
+    Ok(tail[skip..]
+        .iter()
+        .map(|line| sanitize_line(line))
+        .collect())
+}
+
+/// Whether a log line may contain clipboard or typed content
+pub fn contains_content(line: &str) -> bool {
+    CONTENT_MARKERS.iter().any(|marker| line.contains(marker))
+}
+
+/// Redact lines that may contain content and cap their length
+fn sanitize_line(line: &str) -> String {
+    if contains_content(line) {
+        return REDACTED_LINE.to_string();
+    }
+
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Write};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_numbered_lines(path: &Path, count: usize) {
+        let mut file = io::BufWriter::new(File::create(path).unwrap());
+        for i in 0..count {
+            writeln!(file, "2026-01-01T00:00:00Z INFO pasta: line {i}").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_tail_of_multi_megabyte_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        write_numbered_lines(&path, 100_000);
+        assert!(fs::metadata(&path).unwrap().len() > 4 * 1024 * 1024);
+
+        let tail = read_tail(&path, 3).unwrap();
+        assert_eq!(
+            tail,
+            vec![
+                "2026-01-01T00:00:00Z INFO pasta: line 99997",
+                "2026-01-01T00:00:00Z INFO pasta: line 99998",
+                "2026-01-01T00:00:00Z INFO pasta: line 99999",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tail_is_capped() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        write_numbered_lines(&path, 5_000);
+
+        let tail = read_tail(&path, 10_000).unwrap();
+        assert_eq!(tail.len(), MAX_TAIL_LINES);
+        assert!(tail.last().unwrap().ends_with("line 4999"));
+        assert!(tail[0].ends_with(&format!("line {}", 5_000 - MAX_TAIL_LINES)));
+    }
+
+    #[test]
+    fn test_tail_of_short_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        fs::write(&path, "first\nsecond").unwrap();
+
+        assert_eq!(read_tail(&path, 10).unwrap(), vec!["first", "second"]);
+        assert_eq!(read_tail(&path, 1).unwrap(), vec!["second"]);
+        assert!(read_tail(&path, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tail_of_empty_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        fs::write(&path, "").unwrap();
+
+        assert!(read_tail(&path, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tail_across_block_boundary() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pasta.log");
+        let long_line = "x".repeat(TAIL_BLOCK_SIZE as usize + 10);
+        fs::write(&path, format!("before\n{long_line}\nlast\n")).unwrap();
+
+        let tail = read_tail(&path, 2).unwrap();
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].chars().count(), MAX_LINE_CHARS + 1);
+        assert_eq!(tail[1], "last");
+    }
+
+    #[test]
+    fn test_long_lines_are_truncated() {
+        let line = "é".repeat(MAX_LINE_CHARS + 50);
+        let sanitized = sanitize_line(&line);
+        assert_eq!(sanitized.chars().count(), MAX_LINE_CHARS + 1);
+        assert!(sanitized.ends_with('…'));
+    }
+
+    #[test]
+    fn test_content_lines_are_redacted() {
+        assert_eq!(
+            sanitize_line("DEBUG pasta: [content] hunter2"),
+            REDACTED_LINE
+        );
+        assert_eq!(
+            sanitize_line("DEBUG pasta: clipboard=secret"),
+            REDACTED_LINE
+        );
+        assert_eq!(
+            sanitize_line("INFO pasta: Finished typing text"),
+            "INFO pasta: Finished typing text"
+        );
+    }
+
+    #[test]
+    fn test_missing_file_is_an_error() {
+        let dir = tempdir().unwrap();
+        assert!(read_tail(&dir.path().join("missing.log"), 10).is_err());
+    }
+}
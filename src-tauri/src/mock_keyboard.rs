@@ -3,14 +3,22 @@ pub use mock::MockKeyboardEmulator;
 
 #[cfg(test)]
 mod mock {
-    use std::sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
     };
 
-    use tokio::sync::mpsc;
+    use tokio::sync::{mpsc, oneshot};
 
-    use crate::keyboard::KeyboardCommand;
+    use crate::{
+        error::TypingError,
+        keyboard::{self, KeyboardCommand, TypingHandle},
+        permissions::PermissionGate,
+        typing_state::{TypingState, TypingTracker},
+    };
 
     /// A mock keyboard emulator that doesn't perform actual keyboard operations
     /// Used for testing to prevent tests from typing on the system
@@ -18,45 +26,134 @@ mod mock {
         tx: mpsc::Sender<KeyboardCommand>,
         /// Records all typed text for test assertions
         pub typed_text: Arc<Mutex<Vec<String>>>,
+        tracker: Arc<TypingTracker>,
+        permission: Arc<PermissionGate>,
     }
 
     impl MockKeyboardEmulator {
-        pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        pub fn new() -> Result<Self, TypingError> {
             let (tx, mut rx) = mpsc::channel::<KeyboardCommand>(10);
             let typed_text = Arc::new(Mutex::new(Vec::new()));
 
             let typed_text_clone = typed_text.clone();
+            let tracker = Arc::new(TypingTracker::new());
+            let worker_tracker = tracker.clone();
 
             // Spawn a mock thread that doesn't perform actual keyboard operations
             std::thread::spawn(move || {
                 while let Some(cmd) = rx.blocking_recv() {
                     match cmd {
-                        KeyboardCommand::TypeText(text, cancellation_flag) => {
+                        KeyboardCommand::TypeText(text, cancellation_flag, done) => {
+                            let operation = worker_tracker.begin(text.chars().count());
                             // Check if cancelled before recording
                             if !cancellation_flag.load(Ordering::Relaxed) {
+                                worker_tracker.advance(text.chars().count());
+                                worker_tracker.notify();
                                 // Just record the text, don't actually type it
                                 typed_text_clone.lock().unwrap().push(text);
                             }
+                            let outcome =
+                                operation.finish(cancellation_flag.load(Ordering::Relaxed));
+                            let _ = done.send(outcome);
+                        }
+                        KeyboardCommand::PasteKeystroke(chars, cancellation_flag, done) => {
+                            let operation = worker_tracker.begin(chars);
+                            if !cancellation_flag.load(Ordering::Relaxed) {
+                                worker_tracker.advance(chars);
+                            }
+                            let outcome =
+                                operation.finish(cancellation_flag.load(Ordering::Relaxed));
+                            let _ = done.send(outcome);
+                        }
+                        KeyboardCommand::SetMode(..)
+                        | KeyboardCommand::SetChunking(_)
+                        | KeyboardCommand::SetJitter(_)
+                        | KeyboardCommand::ReleaseModifiers => {}
+                        KeyboardCommand::Shutdown(ack) => {
+                            rx.close();
+                            let _ = ack.send(());
+                            break;
                         }
                     }
                 }
             });
 
-            Ok(Self { tx, typed_text })
+            Ok(Self {
+                tx,
+                typed_text,
+                tracker,
+                permission: Arc::new(PermissionGate::new()),
+            })
         }
 
         pub async fn type_text(
             &self,
             text: &str,
             cancellation_flag: Arc<AtomicBool>,
-        ) -> Result<(), Box<dyn std::error::Error>> {
-            self.tx
+        ) -> Result<TypingHandle, TypingError> {
+            self.permission.check()?;
+            self.tracker.enqueued();
+            self.send_text(text, cancellation_flag).await
+        }
+
+        /// Same busy policy as `KeyboardEmulator::try_type_text`
+        pub async fn try_type_text(
+            &self,
+            text: &str,
+            cancellation_flag: Arc<AtomicBool>,
+            queue: bool,
+        ) -> Result<TypingHandle, TypingError> {
+            self.permission.check()?;
+            if self.tracker.try_enqueue_idle() {
+                cancellation_flag.store(false, Ordering::Relaxed);
+            } else if queue {
+                self.tracker.enqueued();
+            } else {
+                return Err(TypingError::Busy);
+            }
+            self.send_text(text, cancellation_flag).await
+        }
+
+        async fn send_text(
+            &self,
+            text: &str,
+            cancellation_flag: Arc<AtomicBool>,
+        ) -> Result<TypingHandle, TypingError> {
+            let (done_tx, done_rx) = oneshot::channel();
+            if self
+                .tx
                 .send(KeyboardCommand::TypeText(
                     text.to_string(),
                     cancellation_flag,
+                    done_tx,
                 ))
-                .await?;
-            Ok(())
+                .await
+                .is_err()
+            {
+                self.tracker.dequeued();
+                return Err(TypingError::WorkerStopped);
+            }
+            Ok(TypingHandle::new(done_rx))
+        }
+
+        /// Same as `KeyboardEmulator::shutdown`
+        pub fn shutdown(&self, timeout: Duration) -> bool {
+            keyboard::send_shutdown(&self.tx, timeout)
+        }
+
+        /// Same gate as `KeyboardEmulator::permission`
+        pub fn permission(&self) -> &Arc<PermissionGate> {
+            &self.permission
+        }
+
+        /// Progress bookkeeping shared with the mock worker
+        pub fn tracker(&self) -> &Arc<TypingTracker> {
+            &self.tracker
+        }
+
+        /// Current typing state, as polled by `get_typing_state`
+        pub fn typing_state(&self) -> TypingState {
+            self.tracker.snapshot()
         }
 
         /// Get all text that has been "typed" for test assertions
@@ -70,3 +167,294 @@ mod mock {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::MockKeyboardEmulator;
+    use crate::{
+        app_logic::PasteReport,
+        error::{PastaError, TypingError},
+        stats::StatsManager,
+        typing_state::{CancelReport, TypingState},
+    };
+
+    async fn wait_until_idle(keyboard: &MockKeyboardEmulator) {
+        for _ in 0..100 {
+            let state = keyboard.typing_state();
+            if !state.is_typing && state.queue_len == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("mock keyboard did not become idle");
+    }
+
+    #[tokio::test]
+    async fn test_events_match_polled_state() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        let events = Arc::new(Mutex::new(Vec::<TypingState>::new()));
+        let events_clone = events.clone();
+        keyboard
+            .tracker()
+            .set_listener(move |state| events_clone.lock().unwrap().push(*state));
+
+        keyboard
+            .type_text("hello", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        keyboard
+            .type_text("wörld!", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+
+        assert_eq!(keyboard.get_typed_text(), vec!["hello", "wörld!"]);
+
+        let events = events.lock().unwrap();
+        // start, progress and finish for each operation
+        assert_eq!(events.len(), 6);
+        assert_eq!((events[1].typed, events[1].total), (5, 5));
+        assert_eq!((events[4].typed, events[4].total), (6, 6));
+
+        // The last event and the polled snapshot describe the same idle state
+        let polled = keyboard.typing_state();
+        assert_eq!(*events.last().unwrap(), polled);
+        assert_eq!(polled.typed, 6);
+        assert_eq!(polled.started_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_after_worker_finished_reports_idle() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        keyboard
+            .type_text("done", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+
+        let report = CancelReport::from_state(&keyboard.typing_state());
+        assert!(!report.was_typing);
+        assert_eq!(report.chars_remaining, None);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_operation_reports_no_progress() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        keyboard
+            .type_text("skipped", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+
+        let state = keyboard.typing_state();
+        assert_eq!((state.typed, state.total), (0, 7));
+        assert!(keyboard.get_typed_text().is_empty());
+
+        keyboard.clear_typed_text();
+        assert!(keyboard.get_typed_text().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_statistics_accumulate_across_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        let stats = Arc::new(StatsManager::with_path(path.clone()));
+
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        let recorder = stats.clone();
+        keyboard
+            .tracker()
+            .set_finish_listener(move |outcome| recorder.record(outcome));
+
+        keyboard
+            .type_text("one", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        keyboard
+            .type_text("three", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        keyboard
+            .type_text("cancelled", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+
+        let totals = stats.get();
+        assert_eq!(totals.total_characters, 8);
+        assert_eq!(totals.operations, 3);
+        assert_eq!(totals.cancellations, 1);
+        assert_eq!(
+            totals
+                .per_day
+                .values()
+                .map(|day| day.characters)
+                .sum::<u64>(),
+            8
+        );
+
+        // Persisted after every operation, and the reset is persisted too
+        assert_eq!(StatsManager::with_path(path.clone()).get(), totals);
+        stats.reset().unwrap();
+        assert_eq!(StatsManager::with_path(path).get().operations, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_trigger_keeps_pending_cancel() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        // An operation is typing and the user just cancelled it
+        keyboard.tracker().start(100);
+        let flag = Arc::new(AtomicBool::new(true));
+
+        let result = keyboard.try_type_text("second", flag.clone(), false).await;
+
+        assert_eq!(result.unwrap_err(), TypingError::Busy);
+        // Resetting here used to let the first operation carry on
+        assert!(flag.load(Ordering::Relaxed));
+        assert_eq!(keyboard.typing_state().queue_len, 0);
+        assert!(keyboard.get_typed_text().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_queued_trigger_keeps_pending_cancel() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        keyboard.tracker().start(100);
+        let flag = Arc::new(AtomicBool::new(true));
+
+        keyboard
+            .try_type_text("queued", flag.clone(), true)
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+
+        // The cancel applies to the queued work too
+        assert!(flag.load(Ordering::Relaxed));
+        assert!(keyboard.get_typed_text().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_trigger_clears_stale_cancel() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        let flag = Arc::new(AtomicBool::new(true));
+
+        keyboard
+            .try_type_text("fresh", flag.clone(), false)
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+
+        assert!(!flag.load(Ordering::Relaxed));
+        assert_eq!(keyboard.get_typed_text(), vec!["fresh"]);
+    }
+
+    #[tokio::test]
+    async fn test_typing_state_clears_after_success_and_cancel() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        for cancelled in [false, true, false] {
+            keyboard
+                .type_text("text", Arc::new(AtomicBool::new(cancelled)))
+                .await
+                .unwrap();
+            wait_until_idle(&keyboard).await;
+
+            let state = keyboard.typing_state();
+            assert!(!state.is_typing);
+            assert!(!state.is_paused);
+            assert_eq!(state.started_at, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_worker_after_queued_work() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        keyboard
+            .type_text("last words", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+
+        assert!(keyboard.shutdown(Duration::from_secs(1)));
+        assert_eq!(keyboard.get_typed_text(), vec!["last words"]);
+
+        let result = keyboard
+            .type_text("too late", Arc::new(AtomicBool::new(false)))
+            .await;
+        assert_eq!(result.unwrap_err(), TypingError::WorkerStopped);
+        // A second shutdown finds the worker gone
+        assert!(keyboard.shutdown(Duration::from_millis(10)));
+    }
+
+    #[tokio::test]
+    async fn test_missing_permission_fails_before_queueing() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        keyboard.permission().set_missing(true);
+
+        let result = keyboard
+            .try_type_text("dropped", Arc::new(AtomicBool::new(false)), true)
+            .await;
+        assert_eq!(result.unwrap_err(), TypingError::PermissionMissing);
+        assert_eq!(keyboard.typing_state().queue_len, 0);
+
+        // Granting it again lets the next paste through
+        keyboard.permission().set_missing(false);
+        keyboard
+            .try_type_text("typed", Arc::new(AtomicBool::new(false)), false)
+            .await
+            .unwrap();
+        wait_until_idle(&keyboard).await;
+        assert_eq!(keyboard.get_typed_text(), vec!["typed"]);
+    }
+
+    #[tokio::test]
+    async fn test_paste_report_for_complete_run() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        let typing = keyboard
+            .type_text("hello", Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+
+        let report = PasteReport::from_outcome(&typing.finished().await.unwrap());
+        assert_eq!((report.typed, report.skipped), (5, 0));
+        assert!(!report.cancelled);
+        assert!(!report.transformed);
+    }
+
+    #[tokio::test]
+    async fn test_paste_report_for_cancelled_run() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        let typing = keyboard
+            .type_text("skipped", Arc::new(AtomicBool::new(true)))
+            .await
+            .unwrap();
+
+        let report = PasteReport::from_outcome(&typing.finished().await.unwrap());
+        assert_eq!((report.typed, report.skipped), (0, 7));
+        assert!(report.cancelled);
+
+        let json = serde_json::to_value(report).unwrap();
+        assert_eq!(json["skipped"], 7);
+        assert!(json.get("durationMs").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_paste_report_for_failed_run() {
+        let keyboard = MockKeyboardEmulator::new().unwrap();
+        assert!(keyboard.shutdown(Duration::from_secs(1)));
+
+        let error = keyboard
+            .try_type_text("never", Arc::new(AtomicBool::new(false)), false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            PastaError::from(error),
+            PastaError::Keyboard(TypingError::WorkerStopped)
+        );
+    }
+}
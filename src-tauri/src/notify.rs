@@ -0,0 +1,94 @@
+/// System notifications, for warnings that may come from a paste with no
+/// Pasta window open to show them
+///
+/// Shown by the platform's own tool, like sound.rs plays cues: `osascript`
+/// on macOS, a PowerShell toast on Windows and `notify-send` elsewhere.
+use std::process::{Command, Stdio};
+
+/// Show `message` as a system notification without waiting for it
+///
+/// A missing tool is logged at `warn!`, as the message is then only in the log.
+pub fn notify(message: &str) {
+    let spawned = notifier(message)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        // Reap the tool once it is done so it doesn't linger
+        Ok(mut child) => {
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+        }
+        Err(e) => log::warn!("Failed to show notification \"{message}\": {e}"),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn notifier(message: &str) -> Command {
+    let mut command = Command::new("osascript");
+    command.args([
+        "-e",
+        &format!(
+            "display notification {} with title \"Pasta\"",
+            applescript_string(message)
+        ),
+    ]);
+    command
+}
+
+/// A toast shown under PowerShell's app id, as Pasta registers none
+#[cfg(target_os = "windows")]
+fn notifier(message: &str) -> Command {
+    let script = format!(
+        "$m = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]; \
+         $t = $m::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $x = $t.GetElementsByTagName('text'); \
+         [void]$x.Item(0).AppendChild($t.CreateTextNode('Pasta')); \
+         [void]$x.Item(1).AppendChild($t.CreateTextNode({})); \
+         $m::CreateToastNotifier('{{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}}\\WindowsPowerShell\\v1.0\\powershell.exe').Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+        powershell_string(message)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    command
+}
+
+/// freedesktop notifications through libnotify's `notify-send`
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn notifier(message: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name=Pasta", "Pasta", message]);
+    command
+}
+
+/// `text` as a quoted AppleScript string literal
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `text` as a single-quoted PowerShell string literal
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn powershell_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_applescript_string_escapes_quotes() {
+        assert_eq!(
+            applescript_string(r#"say "hi" \ bye"#),
+            r#""say \"hi\" \\ bye""#
+        );
+    }
+
+    #[test]
+    fn test_powershell_string_doubles_single_quotes() {
+        assert_eq!(powershell_string("it's $HOME"), "'it''s $HOME'");
+    }
+}
@@ -0,0 +1,213 @@
+/// First-run onboarding: platform checks shown before Pasta is first used
+use serde::Serialize;
+
+use crate::platform::{DisplayServer, PlatformProbe};
+
+/// Outcome of a single onboarding check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// A single onboarding check with instructions for fixing it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingCheck {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+/// Everything the onboarding window needs to render
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingStatus {
+    pub first_run: bool,
+    pub checks: Vec<OnboardingCheck>,
+    pub ready: bool,
+}
+
+fn accessibility_check(trusted: bool) -> OnboardingCheck {
+    if trusted {
+        OnboardingCheck {
+            id: "accessibility",
+            label: "Accessibility permission",
+            status: CheckStatus::Pass,
+            detail: "Pasta is allowed to type for you".to_string(),
+            fix: None,
+        }
+    } else {
+        OnboardingCheck {
+            id: "accessibility",
+            label: "Accessibility permission",
+            status: CheckStatus::Fail,
+            detail: "Pasta is not allowed to simulate keyboard input".to_string(),
+            fix: Some(
+                "Open System Settings → Privacy & Security → Accessibility, add Pasta and \
+                 make sure it is checked, then restart Pasta"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+fn display_server_check(display_server: DisplayServer) -> OnboardingCheck {
+    match display_server {
+        DisplayServer::X11 => OnboardingCheck {
+            id: "display_server",
+            label: "Display server",
+            status: CheckStatus::Pass,
+            detail: "X11 session detected".to_string(),
+            fix: None,
+        },
+        DisplayServer::Wayland => OnboardingCheck {
+            id: "display_server",
+            label: "Display server",
+            status: CheckStatus::Warn,
+            detail: "Wayland session detected; keyboard emulation may be limited".to_string(),
+            fix: Some(
+                "If nothing is typed, log in with an X11 (\"Xorg\") session or make sure \
+                 the XWayland input path is available"
+                    .to_string(),
+            ),
+        },
+        DisplayServer::Unknown => OnboardingCheck {
+            id: "display_server",
+            label: "Display server",
+            status: CheckStatus::Fail,
+            detail: "No X11 or Wayland session found".to_string(),
+            fix: Some("Run Pasta from within a graphical desktop session".to_string()),
+        },
+    }
+}
+
+/// Run the platform probes and aggregate them for the onboarding window
+pub fn onboarding_status(first_run: bool, probe: &dyn PlatformProbe) -> OnboardingStatus {
+    let mut checks = Vec::new();
+
+    if let Some(trusted) = probe.accessibility_trusted() {
+        checks.push(accessibility_check(trusted));
+    }
+
+    if let Some(display_server) = probe.display_server() {
+        checks.push(display_server_check(display_server));
+    }
+
+    let ready = checks.iter().all(|check| check.status != CheckStatus::Fail);
+
+    OnboardingStatus {
+        first_run,
+        checks,
+        ready,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeProbe {
+        accessibility: Option<bool>,
+        display_server: Option<DisplayServer>,
+    }
+
+    impl PlatformProbe for FakeProbe {
+        fn accessibility_trusted(&self) -> Option<bool> {
+            self.accessibility
+        }
+
+        fn display_server(&self) -> Option<DisplayServer> {
+            self.display_server
+        }
+    }
+
+    #[test]
+    fn test_no_applicable_checks_is_ready() {
+        let probe = FakeProbe {
+            accessibility: None,
+            display_server: None,
+        };
+        let status = onboarding_status(true, &probe);
+        assert!(status.first_run);
+        assert!(status.checks.is_empty());
+        assert!(status.ready);
+    }
+
+    #[test]
+    fn test_untrusted_accessibility_fails() {
+        let probe = FakeProbe {
+            accessibility: Some(false),
+            display_server: None,
+        };
+        let status = onboarding_status(true, &probe);
+        assert_eq!(status.checks.len(), 1);
+        assert_eq!(status.checks[0].id, "accessibility");
+        assert_eq!(status.checks[0].status, CheckStatus::Fail);
+        assert!(status.checks[0].fix.is_some());
+        assert!(!status.ready);
+    }
+
+    #[test]
+    fn test_trusted_accessibility_passes() {
+        let probe = FakeProbe {
+            accessibility: Some(true),
+            display_server: None,
+        };
+        let status = onboarding_status(false, &probe);
+        assert!(!status.first_run);
+        assert_eq!(status.checks[0].status, CheckStatus::Pass);
+        assert!(status.checks[0].fix.is_none());
+        assert!(status.ready);
+    }
+
+    #[test]
+    fn test_wayland_warns_but_is_ready() {
+        let probe = FakeProbe {
+            accessibility: None,
+            display_server: Some(DisplayServer::Wayland),
+        };
+        let status = onboarding_status(true, &probe);
+        assert_eq!(status.checks[0].id, "display_server");
+        assert_eq!(status.checks[0].status, CheckStatus::Warn);
+        assert!(status.ready);
+    }
+
+    #[test]
+    fn test_unknown_display_server_fails() {
+        let probe = FakeProbe {
+            accessibility: None,
+            display_server: Some(DisplayServer::Unknown),
+        };
+        let status = onboarding_status(true, &probe);
+        assert_eq!(status.checks[0].status, CheckStatus::Fail);
+        assert!(!status.ready);
+    }
+
+    #[test]
+    fn test_x11_passes() {
+        let probe = FakeProbe {
+            accessibility: None,
+            display_server: Some(DisplayServer::X11),
+        };
+        let status = onboarding_status(true, &probe);
+        assert_eq!(status.checks[0].status, CheckStatus::Pass);
+        assert!(status.ready);
+    }
+
+    #[test]
+    fn test_status_serialization() {
+        let probe = FakeProbe {
+            accessibility: Some(false),
+            display_server: None,
+        };
+        let json = serde_json::to_value(onboarding_status(true, &probe)).unwrap();
+        assert_eq!(json["firstRun"], true);
+        assert_eq!(json["ready"], false);
+        assert_eq!(json["checks"][0]["status"], "fail");
+    }
+}
@@ -0,0 +1,39 @@
+/// Last-ditch cleanup when any thread panics
+///
+/// A panic mid-paste could otherwise kill the process with a modifier
+/// logically held and the rest of the text still queued. The hook stops
+/// typing and releases modifiers before the default hook reports the panic.
+use std::{panic, sync::OnceLock};
+
+use log::error;
+
+/// What the hook acts on, `AppState` in the app
+pub trait PanicTarget: Send + Sync {
+    /// Ask running and queued typing to stop
+    fn cancel_typing(&self);
+    /// Ask the keyboard worker to release modifier keys, without blocking
+    fn release_modifiers(&self);
+}
+
+static TARGET: OnceLock<Box<dyn PanicTarget>> = OnceLock::new();
+
+/// Install the hook, chaining to the one already set
+///
+/// Until `set_target` is called the hook only logs the panic.
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(target) = TARGET.get() {
+            target.cancel_typing();
+            target.release_modifiers();
+        }
+        error!("{info}");
+        log::logger().flush();
+        default_hook(info);
+    }));
+}
+
+/// Give the hook the app state to act on, once it exists
+pub fn set_target<T: PanicTarget + 'static>(target: T) {
+    let _ = TARGET.set(Box::new(target));
+}
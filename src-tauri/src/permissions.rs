@@ -0,0 +1,201 @@
+/// Degraded mode while macOS hasn't granted the accessibility permission
+///
+/// Without it, synthesized keystrokes are silently dropped. The gate makes
+/// pastes fail fast with `PastaError::PermissionMissing` instead, and a
+/// poller lifts it once the user grants the permission.
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use log::{info, warn};
+
+use crate::{error::TypingError, platform::PlatformProbe};
+
+/// How often the poller asks whether the permission was granted
+pub const PERMISSION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Whether typing is allowed, checked before any work is queued
+#[derive(Debug, Default)]
+pub struct PermissionGate {
+    missing: AtomicBool,
+    polling: AtomicBool,
+}
+
+impl PermissionGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_missing(&self) -> bool {
+        self.missing.load(Ordering::Acquire)
+    }
+
+    pub fn set_missing(&self, missing: bool) {
+        self.missing.store(missing, Ordering::Release);
+    }
+
+    /// Fail if typing would be dropped by the OS
+    pub fn check(&self) -> Result<(), TypingError> {
+        if self.is_missing() {
+            Err(TypingError::PermissionMissing)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Update the gate from `probe`, returning whether typing is allowed
+    ///
+    /// Platforms without the permission are always allowed.
+    pub fn refresh(&self, probe: &dyn PlatformProbe) -> bool {
+        let trusted = probe.accessibility_trusted().unwrap_or(true);
+        self.set_missing(!trusted);
+        trusted
+    }
+
+    /// Poll `probe` on a background thread until the permission is granted
+    ///
+    /// Does nothing if a poller is already running. `on_granted` runs once,
+    /// after the gate is lifted.
+    pub fn watch_until_granted<F>(
+        self: &Arc<Self>,
+        probe: Arc<dyn PlatformProbe>,
+        interval: Duration,
+        on_granted: F,
+    ) where
+        F: FnOnce() + Send + 'static,
+    {
+        if self.polling.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let gate = self.clone();
+        std::thread::spawn(move || {
+            while !gate.refresh(&*probe) {
+                std::thread::sleep(interval);
+            }
+            gate.polling.store(false, Ordering::Release);
+            info!("Accessibility permission granted, typing enabled");
+            on_granted();
+        });
+    }
+}
+
+/// Check the permission at startup and watch for it if it is missing
+///
+/// Returns whether typing is allowed right now.
+pub fn check_at_startup<F>(
+    gate: &Arc<PermissionGate>,
+    probe: Arc<dyn PlatformProbe>,
+    on_granted: F,
+) -> bool
+where
+    F: FnOnce() + Send + 'static,
+{
+    if gate.refresh(&*probe) {
+        return true;
+    }
+    warn!("Accessibility permission is missing, pastes will fail until it is granted");
+    gate.watch_until_granted(probe, PERMISSION_POLL_INTERVAL, on_granted);
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::platform::DisplayServer;
+
+    /// Probe whose permission can be granted while a test runs
+    #[derive(Default)]
+    struct FakeProbe {
+        trusted: AtomicBool,
+    }
+
+    impl PlatformProbe for FakeProbe {
+        fn accessibility_trusted(&self) -> Option<bool> {
+            Some(self.trusted.load(Ordering::SeqCst))
+        }
+
+        fn display_server(&self) -> Option<DisplayServer> {
+            None
+        }
+    }
+
+    /// Probe for platforms without the permission
+    struct NoPermissionProbe;
+
+    impl PlatformProbe for NoPermissionProbe {
+        fn accessibility_trusted(&self) -> Option<bool> {
+            None
+        }
+
+        fn display_server(&self) -> Option<DisplayServer> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_missing_permission_fails_fast() {
+        let gate = PermissionGate::new();
+        assert!(!gate.refresh(&FakeProbe::default()));
+        assert_eq!(gate.check(), Err(TypingError::PermissionMissing));
+    }
+
+    #[test]
+    fn test_platforms_without_the_permission_are_allowed() {
+        let gate = PermissionGate::new();
+        assert!(gate.refresh(&NoPermissionProbe));
+        assert_eq!(gate.check(), Ok(()));
+    }
+
+    #[test]
+    fn test_granting_the_permission_lifts_the_gate() {
+        let gate = Arc::new(PermissionGate::new());
+        let probe = Arc::new(FakeProbe::default());
+        let (granted_tx, granted_rx) = mpsc::channel();
+
+        assert!(!gate.refresh(&*probe));
+        gate.watch_until_granted(probe.clone(), Duration::from_millis(5), move || {
+            granted_tx.send(()).unwrap();
+        });
+        assert!(gate.is_missing());
+
+        probe.trusted.store(true, Ordering::SeqCst);
+        granted_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(gate.check(), Ok(()));
+    }
+
+    #[test]
+    fn test_only_one_poller_runs() {
+        let gate = Arc::new(PermissionGate::new());
+        let probe = Arc::new(FakeProbe::default());
+        let (granted_tx, granted_rx) = mpsc::channel();
+
+        for _ in 0..3 {
+            let granted_tx = granted_tx.clone();
+            gate.watch_until_granted(probe.clone(), Duration::from_millis(5), move || {
+                granted_tx.send(()).unwrap();
+            });
+        }
+        probe.trusted.store(true, Ordering::SeqCst);
+
+        granted_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(granted_rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_startup_with_permission_starts_no_poller() {
+        let gate = Arc::new(PermissionGate::new());
+        let probe = Arc::new(FakeProbe::default());
+        probe.trusted.store(true, Ordering::SeqCst);
+
+        let on_granted = || panic!("nothing to grant");
+        assert!(check_at_startup(&gate, probe, on_granted));
+        assert!(!gate.polling.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,342 @@
+/// Platform capability probes
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+/// Display server of the current Linux session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayServer {
+    X11,
+    Wayland,
+    Unknown,
+}
+
+/// Determine the display server from the session environment variables
+pub fn detect_display_server(
+    xdg_session_type: Option<&str>,
+    wayland_display: Option<&str>,
+    display: Option<&str>,
+) -> DisplayServer {
+    match xdg_session_type.map(|s| s.trim().to_ascii_lowercase()) {
+        Some(session) if session == "wayland" => return DisplayServer::Wayland,
+        Some(session) if session == "x11" => return DisplayServer::X11,
+        _ => {}
+    }
+
+    if wayland_display.is_some_and(|s| !s.is_empty()) {
+        DisplayServer::Wayland
+    } else if display.is_some_and(|s| !s.is_empty()) {
+        DisplayServer::X11
+    } else {
+        DisplayServer::Unknown
+    }
+}
+
+/// How well keyboard emulation can work in this session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WaylandSupport {
+    /// Not a Wayland session, nothing to worry about
+    NotWayland,
+    /// Wayland with an X server to type through; native Wayland apps may
+    /// not receive the input
+    XWayland,
+    /// Wayland without a working input emulation path
+    Unavailable,
+}
+
+impl WaylandSupport {
+    /// Classify the session from its display server and the input probe
+    pub fn detect(display_server: Option<DisplayServer>, input_available: Option<bool>) -> Self {
+        match (display_server, input_available) {
+            (Some(DisplayServer::Wayland), Some(false)) => WaylandSupport::Unavailable,
+            (Some(DisplayServer::Wayland), _) => WaylandSupport::XWayland,
+            _ => WaylandSupport::NotWayland,
+        }
+    }
+
+    /// What to tell the user, `None` when typing should just work
+    pub fn warning(self) -> Option<&'static str> {
+        match self {
+            WaylandSupport::NotWayland => None,
+            WaylandSupport::XWayland => Some(
+                "Wayland session: Pasta types through XWayland, so native Wayland apps may \
+                 not receive the text",
+            ),
+            WaylandSupport::Unavailable => Some(WAYLAND_UNSUPPORTED),
+        }
+    }
+}
+
+/// Probe the session, only trying the input backend on Wayland
+pub fn wayland_support(probe: &dyn PlatformProbe) -> WaylandSupport {
+    match probe.display_server() {
+        Some(DisplayServer::Wayland) => WaylandSupport::detect(
+            Some(DisplayServer::Wayland),
+            probe.input_emulation_available(),
+        ),
+        display_server => WaylandSupport::detect(display_server, None),
+    }
+}
+
+/// Explanation for typing failures in Wayland sessions
+pub const WAYLAND_UNSUPPORTED: &str = "Keyboard emulation is not available in this Wayland \
+     session. Log in with an X11 (\"Xorg\") session or enable XWayland so Pasta can type";
+
+/// Tells the user about a degraded session once, not on every paste
+#[derive(Debug, Default)]
+pub struct DegradedWarning {
+    warned: AtomicBool,
+}
+
+impl DegradedWarning {
+    pub const fn new() -> Self {
+        Self {
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    /// Call `notify` with the warning the first time the session is degraded
+    /// Returns whether it was called
+    pub fn warn_once<F: FnOnce(&'static str)>(&self, support: WaylandSupport, notify: F) -> bool {
+        let Some(message) = support.warning() else {
+            return false;
+        };
+        if self.warned.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        notify(message);
+        true
+    }
+}
+
+/// Queries about what the platform allows Pasta to do
+/// Implemented by `SystemProbe` and by fakes in tests
+pub trait PlatformProbe: Send + Sync {
+    /// Whether the process may synthesize keyboard input (macOS accessibility)
+    /// `None` where no such permission exists
+    fn accessibility_trusted(&self) -> Option<bool>;
+
+    /// Ask the OS to prompt for the accessibility permission
+    /// Returns the current state like `accessibility_trusted`
+    fn request_accessibility(&self) -> Option<bool> {
+        self.accessibility_trusted()
+    }
+
+    /// Display server of the session, `None` outside Linux
+    fn display_server(&self) -> Option<DisplayServer>;
+
+    /// Whether the input emulation backend can connect, `None` if not probed
+    fn input_emulation_available(&self) -> Option<bool> {
+        None
+    }
+}
+
+/// Probe backed by the real platform APIs
+pub struct SystemProbe;
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    use std::ffi::c_void;
+
+    pub type CFTypeRef = *const c_void;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        pub fn AXIsProcessTrusted() -> u8;
+        pub fn AXIsProcessTrustedWithOptions(options: CFTypeRef) -> u8;
+        pub static kAXTrustedCheckOptionPrompt: CFTypeRef;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        pub static kCFBooleanTrue: CFTypeRef;
+        pub static kCFTypeDictionaryKeyCallBacks: c_void;
+        pub static kCFTypeDictionaryValueCallBacks: c_void;
+        pub fn CFDictionaryCreate(
+            allocator: CFTypeRef,
+            keys: *const CFTypeRef,
+            values: *const CFTypeRef,
+            count: isize,
+            key_callbacks: *const c_void,
+            value_callbacks: *const c_void,
+        ) -> CFTypeRef;
+        pub fn CFRelease(cf: CFTypeRef);
+    }
+}
+
+impl PlatformProbe for SystemProbe {
+    fn accessibility_trusted(&self) -> Option<bool> {
+        #[cfg(target_os = "macos")]
+        {
+            // SAFETY: AXIsProcessTrusted takes no arguments and only reads process state
+            Some(unsafe { ffi::AXIsProcessTrusted() } != 0)
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            None
+        }
+    }
+
+    fn request_accessibility(&self) -> Option<bool> {
+        #[cfg(target_os = "macos")]
+        {
+            // SAFETY: the dictionary holds CF constants, is only used for the
+            // call and is released afterwards
+            unsafe {
+                let keys = [ffi::kAXTrustedCheckOptionPrompt];
+                let values = [ffi::kCFBooleanTrue];
+                let options = ffi::CFDictionaryCreate(
+                    std::ptr::null(),
+                    keys.as_ptr(),
+                    values.as_ptr(),
+                    1,
+                    &ffi::kCFTypeDictionaryKeyCallBacks as *const _ as *const _,
+                    &ffi::kCFTypeDictionaryValueCallBacks as *const _ as *const _,
+                );
+                let trusted = ffi::AXIsProcessTrustedWithOptions(options) != 0;
+                if !options.is_null() {
+                    ffi::CFRelease(options);
+                }
+                Some(trusted)
+            }
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            None
+        }
+    }
+
+    fn display_server(&self) -> Option<DisplayServer> {
+        #[cfg(target_os = "linux")]
+        {
+            let xdg_session_type = std::env::var("XDG_SESSION_TYPE").ok();
+            let wayland_display = std::env::var("WAYLAND_DISPLAY").ok();
+            let display = std::env::var("DISPLAY").ok();
+            Some(detect_display_server(
+                xdg_session_type.as_deref(),
+                wayland_display.as_deref(),
+                display.as_deref(),
+            ))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+
+    fn input_emulation_available(&self) -> Option<bool> {
+        #[cfg(target_os = "linux")]
+        {
+            // enigo types through X11, so this fails without an X server
+            Some(enigo::Enigo::new(&enigo::Settings::default()).is_ok())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_wayland_from_session_type() {
+        assert_eq!(
+            detect_display_server(Some("wayland"), None, Some(":0")),
+            DisplayServer::Wayland
+        );
+    }
+
+    #[test]
+    fn test_detect_x11_from_session_type() {
+        assert_eq!(
+            detect_display_server(Some("x11"), Some("wayland-0"), None),
+            DisplayServer::X11
+        );
+    }
+
+    #[test]
+    fn test_detect_from_display_variables() {
+        assert_eq!(
+            detect_display_server(Some("tty"), Some("wayland-0"), None),
+            DisplayServer::Wayland
+        );
+        assert_eq!(
+            detect_display_server(None, None, Some(":1")),
+            DisplayServer::X11
+        );
+        assert_eq!(
+            detect_display_server(None, Some(""), Some("")),
+            DisplayServer::Unknown
+        );
+    }
+
+    #[test]
+    fn test_display_server_serialization() {
+        assert_eq!(
+            serde_json::to_string(&DisplayServer::Wayland).unwrap(),
+            "\"wayland\""
+        );
+    }
+
+    #[test]
+    fn test_wayland_support_detection() {
+        use DisplayServer::*;
+
+        assert_eq!(
+            WaylandSupport::detect(Some(Wayland), Some(false)),
+            WaylandSupport::Unavailable
+        );
+        assert_eq!(
+            WaylandSupport::detect(Some(Wayland), Some(true)),
+            WaylandSupport::XWayland
+        );
+        assert_eq!(
+            WaylandSupport::detect(Some(X11), Some(false)),
+            WaylandSupport::NotWayland
+        );
+        assert_eq!(
+            WaylandSupport::detect(None, None),
+            WaylandSupport::NotWayland
+        );
+        assert_eq!(WaylandSupport::NotWayland.warning(), None);
+        assert_eq!(
+            WaylandSupport::Unavailable.warning(),
+            Some(WAYLAND_UNSUPPORTED)
+        );
+    }
+
+    #[test]
+    fn test_degraded_warning_is_shown_once() {
+        let warning = DegradedWarning::new();
+        let mut shown = Vec::new();
+
+        assert!(!warning.warn_once(WaylandSupport::NotWayland, |m| shown.push(m)));
+        assert!(warning.warn_once(WaylandSupport::Unavailable, |m| shown.push(m)));
+        assert!(!warning.warn_once(WaylandSupport::Unavailable, |m| shown.push(m)));
+
+        assert_eq!(shown, [WAYLAND_UNSUPPORTED]);
+    }
+
+    #[test]
+    fn test_system_probe_platform_applicability() {
+        let probe = SystemProbe;
+
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(probe.accessibility_trusted(), None);
+
+        #[cfg(target_os = "linux")]
+        assert!(probe.display_server().is_some());
+
+        #[cfg(not(target_os = "linux"))]
+        assert_eq!(probe.display_server(), None);
+    }
+}
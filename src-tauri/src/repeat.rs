@@ -0,0 +1,113 @@
+/// Typing the clipboard several times in one operation, e.g. to fill a row of
+/// spreadsheet cells
+///
+/// The copies are joined into one text before typing, so the whole thing is a
+/// single `TypeText`: one countdown, one progress bar and one cancel.
+use serde::{Deserialize, Serialize};
+
+use crate::error::PastaError;
+
+/// Longest text a repeated paste may produce
+pub const MAX_REPEATED_CHARS: usize = 100_000;
+
+/// Counts offered in the tray's "Paste ×N" submenu, separated by Tab
+pub const TRAY_PRESETS: [u32; 3] = [2, 5, 10];
+
+/// Separator of the tray presets
+pub const TRAY_SEPARATOR: &str = "{TAB}";
+
+/// Type the text `count` times with `separator` between the copies
+///
+/// `separator` may use `{TAB}` and `{ENTER}` for the keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Repeat {
+    pub count: u32,
+    pub separator: String,
+}
+
+impl Repeat {
+    pub fn new(count: u32, separator: &str) -> Self {
+        Self {
+            count,
+            separator: separator.to_string(),
+        }
+    }
+
+    /// The copies of `text` joined by the expanded separator
+    ///
+    /// Fails with `TooLarge` if the result would exceed `MAX_REPEATED_CHARS`,
+    /// before building anything.
+    pub fn apply(&self, text: &str) -> Result<String, PastaError> {
+        if self.count == 0 || text.is_empty() {
+            return Err(PastaError::Empty);
+        }
+        let separator = expand_separator(&self.separator);
+        let count = self.count as usize;
+        let chars = text
+            .chars()
+            .count()
+            .saturating_mul(count)
+            .saturating_add(separator.chars().count().saturating_mul(count - 1));
+        if chars > MAX_REPEATED_CHARS {
+            return Err(PastaError::TooLarge {
+                chars,
+                max: MAX_REPEATED_CHARS,
+            });
+        }
+        Ok(vec![text; count].join(&separator))
+    }
+}
+
+/// Replace `{TAB}` and `{ENTER}` with the characters typed as those keys
+///
+/// Anything else, including unknown `{NAMES}`, is typed as written.
+pub fn expand_separator(separator: &str) -> String {
+    separator.replace("{TAB}", "\t").replace("{ENTER}", "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copies_are_joined_by_the_separator() {
+        assert_eq!(Repeat::new(3, ", ").apply("ab").unwrap(), "ab, ab, ab");
+        assert_eq!(Repeat::new(1, "{TAB}").apply("ab").unwrap(), "ab");
+        assert_eq!(Repeat::new(2, "").apply("ab").unwrap(), "abab");
+    }
+
+    #[test]
+    fn test_separator_expansion() {
+        assert_eq!(expand_separator("{TAB}"), "\t");
+        assert_eq!(expand_separator("{ENTER}"), "\n");
+        assert_eq!(expand_separator(";{TAB}{ENTER}"), ";\t\n");
+        assert_eq!(expand_separator("{tab}{SPACE}"), "{tab}{SPACE}");
+        assert_eq!(Repeat::new(3, "{ENTER}").apply("x").unwrap(), "x\nx\nx");
+    }
+
+    #[test]
+    fn test_final_size_is_enforced() {
+        let text = "é".repeat(1000);
+        // 100 copies and 99 one-character separators
+        assert_eq!(
+            Repeat::new(100, "{TAB}").apply(&text),
+            Err(PastaError::TooLarge {
+                chars: 100_099,
+                max: MAX_REPEATED_CHARS,
+            })
+        );
+        let fits = Repeat::new(99, "{TAB}").apply(&text).unwrap();
+        assert_eq!(fits.chars().count(), 99_098);
+        assert!(matches!(
+            Repeat::new(u32::MAX, "").apply("x"),
+            Err(PastaError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_nothing_to_repeat() {
+        assert_eq!(Repeat::new(0, "{TAB}").apply("x"), Err(PastaError::Empty));
+        assert_eq!(Repeat::new(3, "{TAB}").apply(""), Err(PastaError::Empty));
+    }
+}
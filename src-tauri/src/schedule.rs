@@ -0,0 +1,214 @@
+/// Pastes scheduled for later, e.g. once a remote session has logged in
+///
+/// The clipboard is read when the paste is scheduled, so copying something
+/// else while waiting doesn't change what gets typed. Only one paste can be
+/// pending. Cancelling typing through the command, the CLI or the HTTP API
+/// drops it too; clicking the tray doesn't, since that also opens the menu.
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app_logic::ClipboardProvider,
+    error::{ClipboardError, PastaError},
+};
+
+/// Delays offered in the tray's "Paste later" submenu
+pub const TRAY_PRESETS_SECS: [u64; 3] = [10, 30, 60];
+
+/// Longest delay `schedule_paste` accepts
+pub const MAX_SCHEDULE_DELAY_SECS: u64 = 60 * 60;
+
+struct Pending {
+    id: u64,
+    text: String,
+    due: Instant,
+}
+
+/// The one paste waiting to run, if any
+#[derive(Default)]
+pub struct ScheduledPaste {
+    pending: Mutex<Option<Pending>>,
+    next_id: AtomicU64,
+}
+
+impl ScheduledPaste {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Schedule `text` to be typed `delay` after `now`, returning its id
+    ///
+    /// Fails with `Busy` while another paste is pending and with `Empty` if
+    /// there is nothing to type.
+    pub fn schedule(&self, text: String, delay: Duration, now: Instant) -> Result<u64, PastaError> {
+        if text.is_empty() {
+            return Err(PastaError::Empty);
+        }
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_some() {
+            return Err(PastaError::Busy);
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        *pending = Some(Pending {
+            id,
+            text,
+            due: now + delay,
+        });
+        Ok(id)
+    }
+
+    /// Drop the pending paste, returning whether there was one
+    pub fn cancel(&self) -> bool {
+        self.pending.lock().unwrap().take().is_some()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.lock().unwrap().is_some()
+    }
+
+    /// Time left until paste `id` is due, `None` once it was cancelled or taken
+    pub fn remaining(&self, id: u64, now: Instant) -> Option<Duration> {
+        let pending = self.pending.lock().unwrap();
+        pending
+            .as_ref()
+            .filter(|pending| pending.id == id)
+            .map(|pending| pending.due.saturating_duration_since(now))
+    }
+
+    /// The text of paste `id` if it is due at `now`, which is then no longer pending
+    pub fn take_due(&self, id: u64, now: Instant) -> Option<String> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending
+            .as_ref()
+            .is_some_and(|pending| pending.id == id && pending.due <= now)
+        {
+            return pending.take().map(|pending| pending.text);
+        }
+        None
+    }
+}
+
+/// Tray tooltip while a paste is pending
+pub fn tooltip(remaining: Duration) -> String {
+    // Round up so the last second reads 1s, not 0s
+    let secs = remaining.as_millis().div_ceil(1000);
+    format!("Pasta - pasting in {secs}s")
+}
+
+/// The clipboard as it was when the paste was scheduled
+pub struct SnapshotClipboard(pub String);
+
+impl ClipboardProvider for SnapshotClipboard {
+    fn get_content(&self) -> Result<Option<String>, ClipboardError> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn test_one_paste_at_a_time() {
+        let scheduled = ScheduledPaste::new();
+        let now = Instant::now();
+        let id = scheduled.schedule("first".to_string(), DELAY, now).unwrap();
+        assert!(scheduled.is_pending());
+        assert_eq!(
+            scheduled.schedule("second".to_string(), DELAY, now),
+            Err(PastaError::Busy)
+        );
+        assert_eq!(scheduled.take_due(id, now + DELAY).unwrap(), "first");
+
+        // Free again once the first one ran
+        assert!(scheduled.schedule("second".to_string(), DELAY, now).is_ok());
+    }
+
+    #[test]
+    fn test_cancelled_paste_never_runs() {
+        let scheduled = ScheduledPaste::new();
+        let now = Instant::now();
+        let id = scheduled.schedule("text".to_string(), DELAY, now).unwrap();
+
+        assert!(scheduled.cancel());
+        assert!(!scheduled.cancel());
+        assert_eq!(scheduled.remaining(id, now), None);
+        assert_eq!(scheduled.take_due(id, now + DELAY), None);
+
+        // A paste scheduled after the cancel isn't mistaken for the old one
+        let next = scheduled.schedule("text".to_string(), DELAY, now).unwrap();
+        assert_ne!(next, id);
+        assert_eq!(scheduled.take_due(id, now + DELAY), None);
+        assert!(scheduled.is_pending());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let scheduled = ScheduledPaste::new();
+        let start = Instant::now();
+        let id = scheduled
+            .schedule("text".to_string(), DELAY, start)
+            .unwrap();
+
+        let almost = start + DELAY - Duration::from_millis(1);
+        assert_eq!(scheduled.remaining(id, start), Some(DELAY));
+        assert_eq!(
+            scheduled.remaining(id, almost),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(scheduled.take_due(id, almost), None);
+
+        let late = start + DELAY + Duration::from_secs(5);
+        assert_eq!(scheduled.remaining(id, late), Some(Duration::ZERO));
+        assert_eq!(scheduled.take_due(id, late).unwrap(), "text");
+        assert_eq!(scheduled.take_due(id, late), None);
+        assert!(!scheduled.is_pending());
+    }
+
+    #[test]
+    fn test_later_copies_dont_change_the_text() {
+        struct Clipboard(Mutex<String>);
+
+        impl ClipboardProvider for Clipboard {
+            fn get_content(&self) -> Result<Option<String>, ClipboardError> {
+                Ok(Some(self.0.lock().unwrap().clone()))
+            }
+        }
+
+        let clipboard = Clipboard(Mutex::new("copied first".to_string()));
+        let scheduled = ScheduledPaste::new();
+        let now = Instant::now();
+        let text = clipboard.get_content().unwrap().unwrap();
+        let id = scheduled.schedule(text, DELAY, now).unwrap();
+
+        *clipboard.0.lock().unwrap() = "copied later".to_string();
+        let snapshot = SnapshotClipboard(scheduled.take_due(id, now + DELAY).unwrap());
+        assert_eq!(
+            snapshot.get_content().unwrap().as_deref(),
+            Some("copied first")
+        );
+    }
+
+    #[test]
+    fn test_nothing_to_schedule() {
+        let scheduled = ScheduledPaste::new();
+        assert_eq!(
+            scheduled.schedule(String::new(), DELAY, Instant::now()),
+            Err(PastaError::Empty)
+        );
+        assert!(!scheduled.is_pending());
+    }
+
+    #[test]
+    fn test_tooltip_counts_whole_seconds() {
+        assert_eq!(tooltip(DELAY), "Pasta - pasting in 30s");
+        assert_eq!(tooltip(Duration::from_millis(200)), "Pasta - pasting in 1s");
+    }
+}
@@ -0,0 +1,176 @@
+/// Secure input on macOS
+///
+/// While a password field or Terminal's Secure Keyboard Entry holds secure
+/// event input, macOS drops synthetic keystrokes and Pasta looks broken.
+/// It is checked before typing and again between chunks, so a paste fails
+/// with `PastaError::SecureInputActive` naming the app instead.
+use crate::error::PastaError;
+
+/// What the secure input check asks the OS
+pub trait SecureInputProbe: Send + Sync {
+    /// Whether some process holds secure event input right now
+    fn enabled(&self) -> bool;
+    /// Name of the app holding it, `None` if it can't be told
+    fn owner(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Fail if macOS would drop what Pasta types
+pub fn check(probe: &dyn SecureInputProbe) -> Result<(), PastaError> {
+    if probe.enabled() {
+        return Err(PastaError::SecureInputActive {
+            owner: probe.owner(),
+        });
+    }
+    Ok(())
+}
+
+/// Probe backed by Carbon and the I/O Registry
+pub struct SystemSecureInputProbe;
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        pub fn IsSecureEventInputEnabled() -> u8;
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl SecureInputProbe for SystemSecureInputProbe {
+    fn enabled(&self) -> bool {
+        // SAFETY: takes no arguments and only reads the session state
+        unsafe { ffi::IsSecureEventInputEnabled() != 0 }
+    }
+
+    /// The console session records the pid holding secure input
+    fn owner(&self) -> Option<String> {
+        use std::process::{Command, Stdio};
+
+        let ioreg = Command::new("ioreg")
+            .args(["-l", "-w", "0", "-d", "1"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let pid = secure_input_pid(&String::from_utf8_lossy(&ioreg.stdout))?;
+        let ps = Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "comm="])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        process_name(&String::from_utf8_lossy(&ps.stdout))
+    }
+}
+
+/// Secure input only exists on macOS
+#[cfg(not(target_os = "macos"))]
+impl SecureInputProbe for SystemSecureInputProbe {
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+/// `kCGSSessionSecureInputPID` from `ioreg` output
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn secure_input_pid(ioreg: &str) -> Option<u32> {
+    const KEY: &str = "\"kCGSSessionSecureInputPID\"=";
+    let start = ioreg.find(KEY)? + KEY.len();
+    let digits: String = ioreg[start..]
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok().filter(|pid| *pid != 0)
+}
+
+/// App name from the executable path `ps` prints
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn process_name(path: &str) -> Option<String> {
+    let path = path.trim();
+    // `/Applications/Terminal.app/Contents/MacOS/Terminal` reads as the bundle
+    let name = match path.rsplit_once(".app/") {
+        Some((bundle, _)) => bundle.rsplit('/').next().unwrap_or(bundle),
+        None => path.rsplit('/').next().unwrap_or(path),
+    };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeProbe {
+        enabled: bool,
+        owner: Option<&'static str>,
+    }
+
+    impl SecureInputProbe for FakeProbe {
+        fn enabled(&self) -> bool {
+            self.enabled
+        }
+
+        fn owner(&self) -> Option<String> {
+            self.owner.map(str::to_string)
+        }
+    }
+
+    #[test]
+    fn test_check_fails_fast_while_enabled() {
+        let probe = FakeProbe {
+            enabled: true,
+            owner: Some("Terminal"),
+        };
+        assert_eq!(
+            check(&probe),
+            Err(PastaError::SecureInputActive {
+                owner: Some("Terminal".to_string())
+            })
+        );
+
+        let probe = FakeProbe {
+            enabled: false,
+            owner: Some("Terminal"),
+        };
+        assert_eq!(check(&probe), Ok(()));
+    }
+
+    #[test]
+    fn test_message_names_the_owner() {
+        let named = PastaError::SecureInputActive {
+            owner: Some("1Password".to_string()),
+        };
+        assert_eq!(
+            named.to_string(),
+            "1Password has secure input turned on, so macOS blocks typing. Leave its \
+             password field or turn off Secure Keyboard Entry, then paste again"
+        );
+
+        let unnamed = PastaError::SecureInputActive { owner: None };
+        assert!(unnamed
+            .to_string()
+            .starts_with("Another app has secure input turned on"));
+        assert_eq!(unnamed.kind(), "secureInputActive");
+    }
+
+    #[test]
+    fn test_secure_input_pid() {
+        let ioreg = r#"  | "IOConsoleUsers" = ({"kCGSSessionOnConsoleKey"=Yes,"kCGSSessionSecureInputPID"=412,"kCGSessionLoginDoneKey"=Yes})"#;
+        assert_eq!(secure_input_pid(ioreg), Some(412));
+        assert_eq!(secure_input_pid(r#""kCGSSessionSecureInputPID"=0"#), None);
+        assert_eq!(secure_input_pid(r#""IOConsoleUsers" = ({})"#), None);
+    }
+
+    #[test]
+    fn test_process_name() {
+        assert_eq!(
+            process_name("/System/Applications/Utilities/Terminal.app/Contents/MacOS/Terminal\n")
+                .as_deref(),
+            Some("Terminal")
+        );
+        assert_eq!(
+            process_name("/usr/libexec/loginwindow").as_deref(),
+            Some("loginwindow")
+        );
+        assert_eq!(process_name("  "), None);
+    }
+}
@@ -0,0 +1,142 @@
+/// Ordered shutdown before Pasta exits, from Quit or any other exit path
+use std::time::Duration;
+
+use log::{error, info, warn};
+
+/// How long the keyboard worker gets to stop
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the shutdown sequence acts on, `AppState` in the app
+pub trait ShutdownTarget {
+    /// Ask running and queued typing to stop
+    fn cancel_typing(&self);
+    /// Stop the keyboard worker, `false` if it didn't stop within `timeout`
+    fn stop_keyboard(&self, timeout: Duration) -> bool;
+    /// Stop taking forwarded launches and control commands
+    fn stop_listeners(&self);
+    /// Persist config and statistics
+    fn flush(&self) -> Result<(), String>;
+}
+
+/// How the shutdown went, for logging and tests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub keyboard_stopped: bool,
+    pub flush_error: Option<String>,
+}
+
+/// Run the shutdown sequence
+///
+/// Typing is cancelled before the worker is stopped so it finishes promptly,
+/// and state is flushed last so the interrupted operation is in the stats.
+/// A worker that doesn't stop in time doesn't hold up the rest.
+pub fn shutdown(target: &dyn ShutdownTarget, timeout: Duration) -> ShutdownReport {
+    info!("Shutting down");
+    target.cancel_typing();
+
+    let keyboard_stopped = target.stop_keyboard(timeout);
+    if !keyboard_stopped {
+        warn!("Keyboard worker did not stop within {timeout:?}, exiting anyway");
+    }
+
+    target.stop_listeners();
+
+    let flush_error = target.flush().err();
+    if let Some(e) = &flush_error {
+        error!("Failed to save state on exit: {e}");
+    }
+
+    ShutdownReport {
+        keyboard_stopped,
+        flush_error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Instant};
+
+    use super::*;
+
+    /// Records the order of steps; the keyboard takes `stop_after` to stop
+    struct RecordingTarget {
+        steps: Mutex<Vec<&'static str>>,
+        stop_after: Duration,
+        flush_fails: bool,
+    }
+
+    impl RecordingTarget {
+        fn new(stop_after: Duration) -> Self {
+            Self {
+                steps: Mutex::new(Vec::new()),
+                stop_after,
+                flush_fails: false,
+            }
+        }
+
+        fn steps(&self) -> Vec<&'static str> {
+            self.steps.lock().unwrap().clone()
+        }
+    }
+
+    impl ShutdownTarget for RecordingTarget {
+        fn cancel_typing(&self) {
+            self.steps.lock().unwrap().push("cancel");
+        }
+
+        fn stop_keyboard(&self, timeout: Duration) -> bool {
+            self.steps.lock().unwrap().push("keyboard");
+            std::thread::sleep(self.stop_after.min(timeout));
+            self.stop_after <= timeout
+        }
+
+        fn stop_listeners(&self) {
+            self.steps.lock().unwrap().push("listeners");
+        }
+
+        fn flush(&self) -> Result<(), String> {
+            self.steps.lock().unwrap().push("flush");
+            if self.flush_fails {
+                Err("disk full".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_steps_run_in_order() {
+        let target = RecordingTarget::new(Duration::ZERO);
+        let report = shutdown(&target, SHUTDOWN_TIMEOUT);
+
+        assert_eq!(target.steps(), ["cancel", "keyboard", "listeners", "flush"]);
+        assert_eq!(
+            report,
+            ShutdownReport {
+                keyboard_stopped: true,
+                flush_error: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_stuck_keyboard_times_out_and_shutdown_continues() {
+        let target = RecordingTarget::new(Duration::from_secs(60));
+        let started = Instant::now();
+        let report = shutdown(&target, Duration::from_millis(50));
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!report.keyboard_stopped);
+        assert_eq!(target.steps(), ["cancel", "keyboard", "listeners", "flush"]);
+    }
+
+    #[test]
+    fn test_flush_failure_is_reported() {
+        let target = RecordingTarget {
+            flush_fails: true,
+            ..RecordingTarget::new(Duration::ZERO)
+        };
+        let report = shutdown(&target, SHUTDOWN_TIMEOUT);
+        assert_eq!(report.flush_error.as_deref(), Some("disk full"));
+    }
+}
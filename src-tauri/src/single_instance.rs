@@ -0,0 +1,620 @@
+/// Keep one Pasta running per user and forward later launches to it
+///
+/// The primary instance listens on a per-user Unix socket, or a named pipe on
+/// Windows. A later launch connects, sends its arguments as one JSON line,
+/// prints the reply and exits. A socket file nobody listens on is left over
+/// from a crash and is replaced; pipes go away with their process.
+/// Scripts can also send newline-delimited JSON objects, see `control`.
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use log::info;
+
+/// Largest request or reply accepted, in bytes
+pub const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+/// How this launch should proceed
+pub enum Instance {
+    /// No other instance is running; serve forwarded launches
+    Primary(InstanceListener),
+    /// Another instance handled the launch and replied
+    Secondary(String),
+}
+
+/// Socket the primary instance listens on
+pub struct InstanceListener {
+    #[cfg(unix)]
+    listener: std::os::unix::net::UnixListener,
+    /// First instance of the pipe, not yet connected
+    #[cfg(windows)]
+    pipe: std::fs::File,
+    path: PathBuf,
+}
+
+/// Socket this process serves, removed again by `stop_serving`
+static SERVED_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Per-user socket path, in the runtime dir where there is one
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(|| dirs::config_dir().map(|dir| dir.join("pasta")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("pasta.sock")
+}
+
+/// Per-user pipe name
+#[cfg(windows)]
+pub fn socket_path() -> PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    PathBuf::from(format!(r"\\.\pipe\pasta-{user}"))
+}
+
+/// Encode launch arguments as a request line
+pub fn encode_request(args: &[String]) -> String {
+    // Serializing strings can't fail
+    let mut line = serde_json::to_string(args).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// A line received on the instance socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// Arguments of a later launch, a JSON array
+    Launch(Vec<String>),
+    /// A control command, a JSON object left for the handler to decode
+    Control(String),
+}
+
+/// Decode a request line
+pub fn decode_request(line: &str) -> Result<Request, String> {
+    let line = line.trim_end();
+    if line.trim_start().starts_with('{') {
+        return Ok(Request::Control(line.to_string()));
+    }
+    serde_json::from_str(line)
+        .map(Request::Launch)
+        .map_err(|e| format!("Malformed request: {e}"))
+}
+
+/// Read a reply line from the primary
+fn read_reply(stream: impl Read) -> io::Result<String> {
+    let mut reply = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_BYTES)).read_line(&mut reply)?;
+    Ok(reply.trim_end().to_string())
+}
+
+/// Answer each request line until the client hangs up
+fn handle_connection<S, F>(stream: S, handler: &F) -> io::Result<()>
+where
+    S: Read + Write + Copy,
+    F: Fn(Request) -> String,
+{
+    let mut reader = BufReader::new(stream);
+    let mut writer = stream;
+
+    loop {
+        let mut line = String::new();
+        if (&mut reader).take(MAX_MESSAGE_BYTES).read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let complete = line.ends_with('\n');
+        let reply = if !complete {
+            "Request too large or incomplete".to_string()
+        } else {
+            match decode_request(&line) {
+                Ok(request) => handler(request),
+                Err(e) => e,
+            }
+        };
+
+        writer.write_all(reply.replace('\n', " ").as_bytes())?;
+        writer.write_all(b"\n")?;
+        // The rest of an oversized line can't be told apart from a new request
+        if !complete {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        fs,
+        io::{self, Write},
+        os::unix::{
+            fs::PermissionsExt,
+            net::{UnixListener, UnixStream},
+        },
+        path::Path,
+        time::Duration,
+    };
+
+    use log::{error, warn};
+
+    use super::*;
+
+    /// How long a forwarding launch waits for the primary to reply
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn acquire(path: &Path, args: &[String]) -> io::Result<Instance> {
+        match forward(path, args) {
+            Ok(reply) => return Ok(Instance::Secondary(reply)),
+            Err(e) if no_listener(&e) => {}
+            Err(e) => return Err(e),
+        }
+
+        if path.exists() {
+            warn!(
+                "Removing stale instance socket {}, Pasta probably crashed",
+                path.display()
+            );
+            fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match UnixListener::bind(path) {
+            Ok(listener) => {
+                // Only the current user may talk to this instance
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+                Ok(Instance::Primary(InstanceListener {
+                    listener,
+                    path: path.to_path_buf(),
+                }))
+            }
+            // Another launch bound the socket between our checks
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                forward(path, args).map(Instance::Secondary)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn no_listener(e: &io::Error) -> bool {
+        matches!(
+            e.kind(),
+            io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+        )
+    }
+
+    fn forward(path: &Path, args: &[String]) -> io::Result<String> {
+        let mut stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(REPLY_TIMEOUT))?;
+        stream.write_all(encode_request(args).as_bytes())?;
+        read_reply(stream)
+    }
+
+    pub fn serve<F>(listener: UnixListener, handler: F)
+    where
+        F: Fn(Request) -> String + Send + 'static,
+    {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let handled = stream
+                        .set_read_timeout(Some(REPLY_TIMEOUT))
+                        .and_then(|()| handle_connection(&stream, &handler));
+                    if let Err(e) = handled {
+                        warn!("Failed to handle forwarded launch: {e}");
+                    }
+                }
+                Err(e) => error!("Instance socket stopped accepting: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{
+        fs::{File, OpenOptions},
+        io::{self, Write},
+        os::windows::{
+            ffi::OsStrExt,
+            io::{AsRawHandle, FromRawHandle},
+        },
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use log::{error, warn};
+
+    use super::*;
+
+    /// How long a forwarding launch waits for the primary to reply
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Set by `stop`, checked each time a client connects
+    static STOPPED: AtomicBool = AtomicBool::new(false);
+
+    mod ffi {
+        use std::ffi::c_void;
+
+        pub type Handle = *mut c_void;
+
+        pub const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+        pub const PIPE_ACCESS_DUPLEX: u32 = 0x0000_0003;
+        pub const FILE_FLAG_FIRST_PIPE_INSTANCE: u32 = 0x0008_0000;
+        /// Byte mode, blocking, local clients only
+        pub const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x0000_0008;
+        pub const PIPE_UNLIMITED_INSTANCES: u32 = 255;
+        pub const ERROR_FILE_NOT_FOUND: i32 = 2;
+        pub const ERROR_ACCESS_DENIED: i32 = 5;
+        pub const ERROR_PIPE_BUSY: i32 = 231;
+        pub const ERROR_PIPE_CONNECTED: i32 = 535;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            pub fn CreateNamedPipeW(
+                name: *const u16,
+                open_mode: u32,
+                pipe_mode: u32,
+                max_instances: u32,
+                out_buffer_size: u32,
+                in_buffer_size: u32,
+                default_timeout: u32,
+                security: *mut c_void,
+            ) -> Handle;
+            pub fn ConnectNamedPipe(pipe: Handle, overlapped: *mut c_void) -> i32;
+            pub fn WaitNamedPipeW(name: *const u16, timeout_ms: u32) -> i32;
+        }
+    }
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+
+    /// Create an instance of the pipe; `first` fails if the pipe exists
+    ///
+    /// The default security only lets other users open it for reading, so
+    /// only the current user can send requests.
+    fn create_pipe(path: &Path, first: bool) -> io::Result<File> {
+        let name = wide(path);
+        let first_flag = if first {
+            ffi::FILE_FLAG_FIRST_PIPE_INSTANCE
+        } else {
+            0
+        };
+        // SAFETY: `name` is NUL-terminated and outlives the call
+        let handle = unsafe {
+            ffi::CreateNamedPipeW(
+                name.as_ptr(),
+                ffi::PIPE_ACCESS_DUPLEX | first_flag,
+                ffi::PIPE_REJECT_REMOTE_CLIENTS,
+                ffi::PIPE_UNLIMITED_INSTANCES,
+                MAX_MESSAGE_BYTES as u32,
+                MAX_MESSAGE_BYTES as u32,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if handle == ffi::INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the handle is valid and owned by nothing else
+        Ok(unsafe { File::from_raw_handle(handle) })
+    }
+
+    /// Connect as a client, waiting while every instance is taken
+    fn open(path: &Path) -> io::Result<File> {
+        loop {
+            match OpenOptions::new().read(true).write(true).open(path) {
+                Err(e) if e.raw_os_error() == Some(ffi::ERROR_PIPE_BUSY) => {
+                    let name = wide(path);
+                    // SAFETY: `name` is NUL-terminated and outlives the call
+                    let free = unsafe {
+                        ffi::WaitNamedPipeW(name.as_ptr(), REPLY_TIMEOUT.as_millis() as u32)
+                    };
+                    if free == 0 {
+                        return Err(e);
+                    }
+                }
+                opened => return opened,
+            }
+        }
+    }
+
+    pub fn acquire(path: &Path, args: &[String]) -> io::Result<Instance> {
+        match forward(path, args) {
+            Ok(reply) => return Ok(Instance::Secondary(reply)),
+            Err(e) if e.raw_os_error() == Some(ffi::ERROR_FILE_NOT_FOUND) => {}
+            Err(e) => return Err(e),
+        }
+
+        match create_pipe(path, true) {
+            Ok(pipe) => Ok(Instance::Primary(InstanceListener {
+                pipe,
+                path: path.to_path_buf(),
+            })),
+            // Another launch created the pipe between our checks
+            Err(e) if e.raw_os_error() == Some(ffi::ERROR_ACCESS_DENIED) => {
+                forward(path, args).map(Instance::Secondary)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn forward(path: &Path, args: &[String]) -> io::Result<String> {
+        let pipe = open(path)?;
+        let request = encode_request(args);
+        // Pipe reads can't time out, so the reply is awaited on a helper thread
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut writer = &pipe;
+            let reply = writer
+                .write_all(request.as_bytes())
+                .and_then(|()| read_reply(&pipe));
+            let _ = tx.send(reply);
+        });
+        rx.recv_timeout(REPLY_TIMEOUT).unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "The running instance didn't reply",
+            ))
+        })
+    }
+
+    /// Accept clients on fresh pipe instances, answering each on its own thread
+    ///
+    /// A client that never sends a full line would otherwise hold up every
+    /// later one, as pipe reads can't time out.
+    pub fn serve<F>(first: File, path: PathBuf, handler: F)
+    where
+        F: Fn(Request) -> String + Send + 'static,
+    {
+        let handler = Arc::new(Mutex::new(handler));
+        let mut pipe = first;
+        loop {
+            // SAFETY: `pipe` is a server instance no client holds yet
+            let connected = match unsafe {
+                ffi::ConnectNamedPipe(pipe.as_raw_handle(), std::ptr::null_mut())
+            } {
+                0 => match io::Error::last_os_error() {
+                    // The client connected before we started waiting
+                    e if e.raw_os_error() == Some(ffi::ERROR_PIPE_CONNECTED) => Ok(()),
+                    e => Err(e),
+                },
+                _ => Ok(()),
+            };
+            if STOPPED.load(Ordering::Acquire) {
+                return;
+            }
+            let next = match create_pipe(&path, false) {
+                Ok(next) => next,
+                Err(e) => {
+                    error!("Instance pipe stopped accepting: {e}");
+                    return;
+                }
+            };
+            let client = std::mem::replace(&mut pipe, next);
+            if let Err(e) = connected {
+                warn!("Failed to accept forwarded launch: {e}");
+                continue;
+            }
+
+            let handler = handler.clone();
+            std::thread::spawn(move || {
+                let handle = |request: Request| (*handler.lock().unwrap())(request);
+                if let Err(e) = handle_connection(&client, &handle) {
+                    warn!("Failed to handle forwarded launch: {e}");
+                }
+                // Closing without `DisconnectNamedPipe` leaves the last reply
+                // readable by the client
+                drop(client);
+            });
+        }
+    }
+
+    /// Stop taking clients, waking `serve` with a connection of our own
+    pub fn stop(path: &Path) {
+        STOPPED.store(true, Ordering::Release);
+        drop(open(path));
+        info!("Closed instance pipe {}", path.display());
+    }
+}
+
+/// Become the primary instance, or forward `args` to the running one
+#[cfg(unix)]
+pub fn acquire(path: &Path, args: &[String]) -> io::Result<Instance> {
+    unix::acquire(path, args)
+}
+
+/// Become the primary instance, or forward `args` to the running one
+#[cfg(windows)]
+pub fn acquire(path: &Path, args: &[String]) -> io::Result<Instance> {
+    windows::acquire(path, args)
+}
+
+impl InstanceListener {
+    /// Answer forwarded launches on a background thread
+    pub fn serve<F>(self, handler: F)
+    where
+        F: Fn(Request) -> String + Send + 'static,
+    {
+        let _ = SERVED_PATH.set(self.path.clone());
+        #[cfg(unix)]
+        {
+            let listener = self.listener;
+            std::thread::spawn(move || unix::serve(listener, handler));
+        }
+        #[cfg(windows)]
+        {
+            let (pipe, path) = (self.pipe, self.path);
+            std::thread::spawn(move || windows::serve(pipe, path, handler));
+        }
+        info!("Listening for forwarded launches");
+    }
+}
+
+/// Stop taking forwarded launches and control commands before exiting
+///
+/// Removes the socket, or closes the pipe, so later launches start a new
+/// primary instead of waiting on one that is shutting down.
+pub fn stop_serving() {
+    let Some(path) = SERVED_PATH.get() else {
+        return;
+    };
+    #[cfg(unix)]
+    match std::fs::remove_file(path) {
+        Ok(()) => info!("Removed instance socket {}", path.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Failed to remove instance socket {}: {e}", path.display()),
+    }
+    #[cfg(windows)]
+    windows::stop(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    /// A socket path, or pipe name, no other test uses
+    fn test_path() -> (TempDir, PathBuf) {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = tempdir().unwrap();
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = if cfg!(windows) {
+            PathBuf::from(format!(r"\\.\pipe\pasta-test-{}-{n}", std::process::id()))
+        } else {
+            dir.path().join(format!("pasta-{n}.sock"))
+        };
+        (dir, path)
+    }
+
+    /// Connect to `path` as a script would
+    #[cfg(unix)]
+    fn connect(path: &Path) -> std::os::unix::net::UnixStream {
+        std::os::unix::net::UnixStream::connect(path).unwrap()
+    }
+
+    #[cfg(windows)]
+    fn connect(path: &Path) -> std::fs::File {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let line = encode_request(&args(&["--paste", "two words"]));
+        assert!(line.ends_with('\n'));
+        assert_eq!(
+            decode_request(&line).unwrap(),
+            Request::Launch(args(&["--paste", "two words"]))
+        );
+        assert!(decode_request("not json\n").is_err());
+    }
+
+    #[test]
+    fn test_objects_are_control_requests() {
+        assert_eq!(
+            decode_request("{\"command\":\"status\"}\n").unwrap(),
+            Request::Control("{\"command\":\"status\"}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_several_requests_on_one_connection() {
+        let (_dir, path) = test_path();
+        let Instance::Primary(listener) = acquire(&path, &[]).unwrap() else {
+            panic!("first launch should be primary");
+        };
+        listener.serve(|request| match request {
+            Request::Control(line) => format!("control {line}"),
+            Request::Launch(args) => format!("launch {}", args.len()),
+        });
+
+        let mut stream = connect(&path);
+        stream
+            .write_all(b"{\"command\":\"paste\"}\n[\"a\"]\n")
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut reply = String::new();
+        reader.read_line(&mut reply).unwrap();
+        assert_eq!(reply, "control {\"command\":\"paste\"}\n");
+        reply.clear();
+        reader.read_line(&mut reply).unwrap();
+        assert_eq!(reply, "launch 1\n");
+    }
+
+    #[test]
+    fn test_second_launch_is_forwarded() {
+        let (_dir, path) = test_path();
+
+        let Instance::Primary(listener) = acquire(&path, &[]).unwrap() else {
+            panic!("first launch should be primary");
+        };
+        listener.serve(|request| match request {
+            Request::Launch(args) => format!("got {}", args.join(",")),
+            Request::Control(_) => unreachable!(),
+        });
+
+        for launch in ["--paste", "--cancel"] {
+            match acquire(&path, &args(&[launch])).unwrap() {
+                Instance::Secondary(reply) => assert_eq!(reply, format!("got {launch}")),
+                Instance::Primary(_) => panic!("later launches should be forwarded"),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, path) = test_path();
+
+        let _instance = acquire(&path, &[]).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_socket_is_replaced() {
+        let (_dir, path) = test_path();
+        // A crashed instance leaves the socket file behind with nobody listening
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        assert!(matches!(acquire(&path, &[]).unwrap(), Instance::Primary(_)));
+    }
+
+    #[test]
+    fn test_oversized_request_is_rejected() {
+        let (_dir, path) = test_path();
+        let Instance::Primary(listener) = acquire(&path, &[]).unwrap() else {
+            panic!("first launch should be primary");
+        };
+        listener.serve(|_| "handled".to_string());
+
+        let mut stream = connect(&path);
+        let huge = "a".repeat(MAX_MESSAGE_BYTES as usize + 10);
+        // The primary may close the connection before reading everything
+        let _ = stream.write_all(huge.as_bytes());
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply).unwrap();
+        assert_eq!(reply.trim_end(), "Request too large or incomplete");
+    }
+}
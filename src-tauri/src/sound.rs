@@ -0,0 +1,247 @@
+/// Short sounds when typing starts, finishes, is cancelled or fails
+///
+/// For when the target window is full-screen and the tray can't be seen.
+/// System sounds are played by the platform's own player, so nothing is
+/// bundled. Cues are skipped while do-not-disturb is on, where detectable.
+use std::{
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::debug;
+
+use crate::{app_logic::PasteReport, typing_state::TypingState};
+
+/// Which sound to play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    Started,
+    Finished,
+    Cancelled,
+    Failed,
+}
+
+/// Plays cues, faked in tests
+pub trait SoundBackend: Send + Sync {
+    /// Start playing without waiting for the sound to end
+    fn play(&self, cue: Cue);
+}
+
+type Check = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// Turns typing lifecycle events into cues
+pub struct SoundFeedback {
+    backend: Box<dyn SoundBackend>,
+    /// `sound_feedback`, read for every cue so changes apply at once
+    enabled: Check,
+    do_not_disturb: Check,
+    /// Whether the last progress update was mid-operation
+    typing: AtomicBool,
+}
+
+impl SoundFeedback {
+    /// Play system sounds whenever `enabled` says so
+    pub fn new<F>(enabled: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        Self::with_backend(SystemSounds, enabled, do_not_disturb)
+    }
+
+    pub fn with_backend<B, E, D>(backend: B, enabled: E, do_not_disturb: D) -> Self
+    where
+        B: SoundBackend + 'static,
+        E: Fn() -> bool + Send + Sync + 'static,
+        D: Fn() -> bool + Send + Sync + 'static,
+    {
+        Self {
+            backend: Box::new(backend),
+            enabled: Box::new(enabled),
+            do_not_disturb: Box::new(do_not_disturb),
+            typing: AtomicBool::new(false),
+        }
+    }
+
+    /// Cue the start of each operation, fed every progress update
+    pub fn on_progress(&self, state: &TypingState) {
+        let was_typing = self.typing.swap(state.is_typing, Ordering::AcqRel);
+        if state.is_typing && !was_typing {
+            self.cue(Cue::Started);
+        }
+    }
+
+    pub fn on_finished(&self, report: &PasteReport) {
+        self.cue(if report.cancelled {
+            Cue::Cancelled
+        } else {
+            Cue::Finished
+        });
+    }
+
+    pub fn on_failed(&self) {
+        self.cue(Cue::Failed);
+    }
+
+    fn cue(&self, cue: Cue) {
+        if (self.enabled)() && !(self.do_not_disturb)() {
+            self.backend.play(cue);
+        }
+    }
+}
+
+/// The platform's sound player with its stock sounds
+struct SystemSounds;
+
+impl SoundBackend for SystemSounds {
+    fn play(&self, cue: Cue) {
+        let mut command = player(cue);
+        let spawned = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            // Reap the player once it is done so it doesn't linger
+            Ok(mut child) => {
+                std::thread::spawn(move || {
+                    let _ = child.wait();
+                });
+            }
+            Err(e) => debug!("Failed to play {cue:?} sound: {e}"),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn player(cue: Cue) -> Command {
+    let name = match cue {
+        Cue::Started => "Tink",
+        Cue::Finished => "Glass",
+        Cue::Cancelled => "Pop",
+        Cue::Failed => "Basso",
+    };
+    let mut command = Command::new("afplay");
+    command.arg(format!("/System/Library/Sounds/{name}.aiff"));
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn player(cue: Cue) -> Command {
+    let name = match cue {
+        Cue::Started => "Asterisk",
+        Cue::Finished => "Beep",
+        Cue::Cancelled => "Exclamation",
+        Cue::Failed => "Hand",
+    };
+    let mut command = Command::new("powershell");
+    command.args([
+        "-NoProfile",
+        "-Command",
+        // Play returns at once, so wait for the sound before exiting
+        &format!("[System.Media.SystemSounds]::{name}.Play(); Start-Sleep -Milliseconds 800"),
+    ]);
+    command
+}
+
+/// freedesktop sound theme names, played through libcanberra
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn player(cue: Cue) -> Command {
+    let name = match cue {
+        Cue::Started => "message",
+        Cue::Finished => "complete",
+        Cue::Cancelled => "dialog-warning",
+        Cue::Failed => "dialog-error",
+    };
+    let mut command = Command::new("canberra-gtk-play");
+    command.args(["--id", name]);
+    command
+}
+
+/// GNOME's do-not-disturb switch; other desktops and platforms don't expose one
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn do_not_disturb() -> bool {
+    Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success() && output.stdout.starts_with(b"false"))
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn do_not_disturb() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct RecordingBackend(Arc<Mutex<Vec<Cue>>>);
+
+    impl SoundBackend for RecordingBackend {
+        fn play(&self, cue: Cue) {
+            self.0.lock().unwrap().push(cue);
+        }
+    }
+
+    fn feedback(enabled: bool, do_not_disturb: bool) -> (SoundFeedback, Arc<Mutex<Vec<Cue>>>) {
+        let backend = RecordingBackend::default();
+        let played = backend.0.clone();
+        let feedback =
+            SoundFeedback::with_backend(backend, move || enabled, move || do_not_disturb);
+        (feedback, played)
+    }
+
+    fn typing(typed: usize) -> TypingState {
+        TypingState {
+            is_typing: true,
+            typed,
+            total: 10,
+            ..TypingState::default()
+        }
+    }
+
+    #[test]
+    fn test_start_is_cued_once_per_operation() {
+        let (feedback, played) = feedback(true, false);
+        for state in [
+            typing(0),
+            typing(4),
+            typing(8),
+            TypingState::default(),
+            typing(0),
+        ] {
+            feedback.on_progress(&state);
+        }
+        assert_eq!(*played.lock().unwrap(), [Cue::Started, Cue::Started]);
+    }
+
+    #[test]
+    fn test_outcomes_have_distinct_cues() {
+        let (feedback, played) = feedback(true, false);
+        feedback.on_finished(&PasteReport::default());
+        feedback.on_finished(&PasteReport {
+            cancelled: true,
+            ..PasteReport::default()
+        });
+        feedback.on_failed();
+        assert_eq!(
+            *played.lock().unwrap(),
+            [Cue::Finished, Cue::Cancelled, Cue::Failed]
+        );
+    }
+
+    #[test]
+    fn test_disabled_or_do_not_disturb_is_silent() {
+        for (enabled, do_not_disturb) in [(false, false), (true, true)] {
+            let (feedback, played) = feedback(enabled, do_not_disturb);
+            feedback.on_progress(&typing(0));
+            feedback.on_finished(&PasteReport::default());
+            feedback.on_failed();
+            assert!(played.lock().unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,458 @@
+/// Typing statistics persisted to `stats.json`
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::StorageError, typing_state::TypingOutcome};
+
+/// How many days of per-day counts are kept
+const MAX_DAYS: usize = 90;
+
+/// Format of `stats.json`; files without a version are version 1
+///
+/// Version 1 kept only a character count per day.
+const STATS_VERSION: u64 = 2;
+
+/// Counts for a single day, or a range of days added up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DayStats {
+    pub characters: u64,
+    pub operations: u64,
+    pub cancellations: u64,
+}
+
+impl DayStats {
+    fn add(&mut self, other: &DayStats) {
+        self.characters += other.characters;
+        self.operations += other.operations;
+        self.cancellations += other.cancellations;
+    }
+}
+
+/// Accumulated typing statistics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Statistics {
+    pub total_characters: u64,
+    pub operations: u64,
+    pub cancellations: u64,
+    pub total_typing_ms: u64,
+    /// Counts per UTC day, keyed by `YYYY-MM-DD`
+    pub per_day: BTreeMap<String, DayStats>,
+}
+
+impl Statistics {
+    /// Add a finished typing operation that happened on `day`
+    pub fn record(&mut self, outcome: &TypingOutcome, day: String) {
+        let typed = outcome.typed as u64;
+        self.total_characters += typed;
+        self.operations += 1;
+        if outcome.cancelled {
+            self.cancellations += 1;
+        }
+        self.total_typing_ms += outcome.duration.as_millis() as u64;
+
+        let bucket = self.per_day.entry(day).or_default();
+        bucket.characters += typed;
+        bucket.operations += 1;
+        if outcome.cancelled {
+            bucket.cancellations += 1;
+        }
+        while self.per_day.len() > MAX_DAYS {
+            self.per_day.pop_first();
+        }
+    }
+
+    /// Counts added up from `first_day` (`YYYY-MM-DD`) on
+    pub fn since(&self, first_day: &str) -> DayStats {
+        let mut total = DayStats::default();
+        for (_, day) in self.per_day.range(first_day.to_string()..) {
+            total.add(day);
+        }
+        total
+    }
+
+    /// One-line summary, e.g. for the tray
+    pub fn summary(&self) -> String {
+        format!(
+            "{} characters typed in {} {}",
+            self.total_characters,
+            self.operations,
+            if self.operations == 1 {
+                "operation"
+            } else {
+                "operations"
+            }
+        )
+    }
+}
+
+/// What `get_statistics` returns: the totals plus the last seven days
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsReport {
+    #[serde(flatten)]
+    pub totals: Statistics,
+    /// Today and the six days before it
+    pub this_week: DayStats,
+}
+
+/// The file as written, with its format version
+#[derive(Serialize)]
+struct StatsFile<'a> {
+    version: u64,
+    #[serde(flatten)]
+    stats: &'a Statistics,
+}
+
+type Clock = Box<dyn Fn() -> SystemTime + Send + Sync>;
+
+/// Owns the statistics and the file they are persisted to
+pub struct StatsManager {
+    stats: Mutex<Statistics>,
+    stats_path: PathBuf,
+    /// Decides which day an operation counts towards, faked in tests
+    clock: Clock,
+}
+
+impl StatsManager {
+    /// Create a manager backed by a specific file, loading it if it exists
+    pub fn with_path(stats_path: PathBuf) -> Self {
+        Self::with_clock(stats_path, SystemTime::now)
+    }
+
+    /// Like `with_path`, reading the time from `clock`
+    ///
+    /// A file that can't be read is moved aside to `stats.json.bak` and the
+    /// statistics start from zero.
+    pub fn with_clock<C>(stats_path: PathBuf, clock: C) -> Self
+    where
+        C: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        let stats = match Self::load_from(&stats_path) {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!(
+                    "Failed to load statistics from {}: {e}, starting from zero",
+                    stats_path.display()
+                );
+                let backup = stats_path.with_extension("json.bak");
+                if let Err(e) = fs::rename(&stats_path, &backup) {
+                    warn!("Failed to keep a copy at {}: {e}", backup.display());
+                }
+                Statistics::default()
+            }
+        };
+
+        Self {
+            stats: Mutex::new(stats),
+            stats_path,
+            clock: Box::new(clock),
+        }
+    }
+
+    fn load_from(path: &Path) -> Result<Statistics, StorageError> {
+        if !path.exists() {
+            return Ok(Statistics::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        let mut value: serde_json::Value = serde_json::from_str(&contents)?;
+        let version = value
+            .get("version")
+            .map(|v| {
+                v.as_u64()
+                    .ok_or_else(|| StorageError::Format("version is not a number".to_string()))
+            })
+            .transpose()?
+            .unwrap_or(1);
+        match version {
+            1 => migrate_v1(&mut value),
+            STATS_VERSION => {}
+            _ => return Err(StorageError::Format(format!("unknown version {version}"))),
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Get a snapshot of the current statistics
+    pub fn get(&self) -> Statistics {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// The totals along with this week's counts
+    pub fn report(&self) -> StatisticsReport {
+        let totals = self.get();
+        let week_start = date_from_days(days_since_epoch((self.clock)()) - 6);
+        StatisticsReport {
+            this_week: totals.since(&week_start),
+            totals,
+        }
+    }
+
+    /// Add a finished typing operation and persist the result
+    pub fn record(&self, outcome: &TypingOutcome) {
+        let day = date_from_days(days_since_epoch((self.clock)()));
+        let stats = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.record(outcome, day);
+            stats.clone()
+        };
+
+        if let Err(e) = Self::write_to(&self.stats_path, &stats) {
+            error!("Failed to save statistics: {e}");
+        }
+    }
+
+    /// Persist the current statistics
+    pub fn save(&self) -> Result<(), StorageError> {
+        Self::write_to(&self.stats_path, &self.get())
+    }
+
+    /// Zero all statistics and persist the result
+    pub fn reset(&self) -> Result<(), StorageError> {
+        let stats = Statistics::default();
+        Self::write_to(&self.stats_path, &stats)?;
+        *self.stats.lock().unwrap() = stats;
+        info!("Statistics reset");
+        Ok(())
+    }
+
+    fn write_to(path: &Path, stats: &Statistics) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = StatsFile {
+            version: STATS_VERSION,
+            stats,
+        };
+        fs::write(path, serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+/// Turn version 1's per-day character counts into day buckets
+fn migrate_v1(value: &mut serde_json::Value) {
+    let Some(per_day) = value.get_mut("perDay").and_then(|v| v.as_object_mut()) else {
+        return;
+    };
+    for day in per_day.values_mut() {
+        if let Some(characters) = day.as_u64() {
+            *day = serde_json::json!({ "characters": characters });
+        }
+    }
+}
+
+/// Whole UTC days between the Unix epoch and `time`
+fn days_since_epoch(time: SystemTime) -> i64 {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    (secs / 86_400) as i64
+}
+
+/// Convert days since the Unix epoch to a civil `YYYY-MM-DD` date
+fn date_from_days(days: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn outcome(typed: usize, cancelled: bool) -> TypingOutcome {
+        TypingOutcome {
+            typed,
+            total: typed,
+            cancelled,
+            duration: Duration::from_millis(100),
+            secure_input: false,
+            input_rejected: false,
+        }
+    }
+
+    #[test]
+    fn test_date_from_days() {
+        assert_eq!(date_from_days(0), "1970-01-01");
+        assert_eq!(date_from_days(59), "1970-03-01");
+        assert_eq!(date_from_days(11_016), "2000-02-29");
+        assert_eq!(date_from_days(20_454), "2026-01-01");
+    }
+
+    #[test]
+    fn test_record_accumulates() {
+        let mut stats = Statistics::default();
+        stats.record(&outcome(10, false), "2026-01-01".to_string());
+        stats.record(&outcome(5, true), "2026-01-01".to_string());
+        stats.record(&outcome(7, false), "2026-01-02".to_string());
+
+        assert_eq!(stats.total_characters, 22);
+        assert_eq!(stats.operations, 3);
+        assert_eq!(stats.cancellations, 1);
+        assert_eq!(stats.total_typing_ms, 300);
+        assert_eq!(
+            stats.per_day["2026-01-01"],
+            DayStats {
+                characters: 15,
+                operations: 2,
+                cancellations: 1
+            }
+        );
+        assert_eq!(stats.per_day["2026-01-02"].characters, 7);
+        assert_eq!(stats.since("2026-01-02").operations, 1);
+        assert_eq!(stats.since("2025-12-01").characters, 22);
+    }
+
+    #[test]
+    fn test_per_day_is_bounded() {
+        let mut stats = Statistics::default();
+        for day in 0..(MAX_DAYS as i64 + 5) {
+            stats.record(&outcome(1, false), date_from_days(day));
+        }
+        assert_eq!(stats.per_day.len(), MAX_DAYS);
+        assert!(!stats.per_day.contains_key("1970-01-01"));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut stats = Statistics::default();
+        assert_eq!(stats.summary(), "0 characters typed in 0 operations");
+        stats.record(&outcome(12, false), "2026-01-01".to_string());
+        assert_eq!(stats.summary(), "12 characters typed in 1 operation");
+    }
+
+    #[test]
+    fn test_record_persists_and_reloads() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+
+        let manager = StatsManager::with_path(path.clone());
+        manager.record(&outcome(4, false));
+        manager.record(&outcome(6, true));
+
+        let reloaded = StatsManager::with_path(path);
+        assert_eq!(reloaded.get(), manager.get());
+        assert_eq!(reloaded.get().total_characters, 10);
+    }
+
+    #[test]
+    fn test_reset_persists() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+
+        let manager = StatsManager::with_path(path.clone());
+        manager.record(&outcome(4, false));
+        manager.reset().unwrap();
+        assert_eq!(manager.get(), Statistics::default());
+
+        let reloaded = StatsManager::with_path(path);
+        assert_eq!(reloaded.get(), Statistics::default());
+    }
+
+    #[test]
+    fn test_malformed_file_starts_from_zero() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let manager = StatsManager::with_path(path.clone());
+        assert_eq!(manager.get(), Statistics::default());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("stats.json.bak")).unwrap(),
+            "{ not json"
+        );
+
+        manager.record(&outcome(3, false));
+        assert_eq!(StatsManager::with_path(path).get().total_characters, 3);
+    }
+
+    #[test]
+    fn test_unknown_version_starts_from_zero() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        fs::write(&path, r#"{ "version": 99, "totalCharacters": 5 }"#).unwrap();
+
+        assert_eq!(StatsManager::with_path(path).get(), Statistics::default());
+    }
+
+    #[test]
+    fn test_version_1_file_is_migrated() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("stats.json");
+        fs::write(
+            &path,
+            r#"{ "totalCharacters": 12, "operations": 2, "perDay": { "2026-01-01": 12 } }"#,
+        )
+        .unwrap();
+
+        let stats = StatsManager::with_path(path.clone()).get();
+        assert_eq!(stats.total_characters, 12);
+        assert_eq!(stats.per_day["2026-01-01"].characters, 12);
+
+        StatsManager::with_path(path.clone()).save().unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(saved["version"], STATS_VERSION);
+    }
+
+    #[test]
+    fn test_rollover_at_midnight() {
+        let dir = tempdir().unwrap();
+        // 2026-01-01 23:59:59 UTC
+        let last_second = UNIX_EPOCH + Duration::from_secs(20_454 * 86_400 + 86_399);
+        let now = Arc::new(Mutex::new(last_second));
+        let clock = now.clone();
+        let manager = StatsManager::with_clock(dir.path().join("stats.json"), move || {
+            *clock.lock().unwrap()
+        });
+
+        manager.record(&outcome(4, false));
+        *now.lock().unwrap() += Duration::from_secs(2);
+        manager.record(&outcome(6, true));
+
+        let stats = manager.get();
+        assert_eq!(stats.per_day["2026-01-01"].characters, 4);
+        assert_eq!(stats.per_day["2026-01-02"].characters, 6);
+        assert_eq!(stats.per_day["2026-01-02"].cancellations, 1);
+
+        // A week later only the second day still counts
+        *now.lock().unwrap() += Duration::from_secs(6 * 86_400);
+        let report = manager.report();
+        assert_eq!(report.this_week.characters, 6);
+        assert_eq!(report.this_week.operations, 1);
+    }
+
+    #[test]
+    fn test_serialization_uses_camel_case() {
+        let json = serde_json::to_value(Statistics::default()).unwrap();
+        assert_eq!(json["totalCharacters"], 0);
+        assert!(json["perDay"].is_object());
+
+        let report = StatsManager::with_path(tempdir().unwrap().path().join("stats.json")).report();
+        let json = serde_json::to_value(report).unwrap();
+        assert_eq!(json["totalCharacters"], 0);
+        assert_eq!(json["thisWeek"]["operations"], 0);
+    }
+}
@@ -2,11 +2,62 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Theme {
     Light,
     Dark,
 }
 
+/// User override for the theme of Pasta's windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemePreference {
+    /// Follow the system
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+/// Theme information sent to the frontends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeState {
+    /// System theme, if a window was available to ask
+    pub system: Option<Theme>,
+    pub preference: ThemePreference,
+    /// Theme the windows should use
+    pub effective: Theme,
+}
+
+/// Resolve the theme to use from the user's preference and the system theme
+pub fn resolve_theme(preference: ThemePreference, system: Option<Theme>) -> Theme {
+    match preference {
+        ThemePreference::Light => Theme::Light,
+        ThemePreference::Dark => Theme::Dark,
+        ThemePreference::Auto => system.unwrap_or_else(Theme::detect_system_theme),
+    }
+}
+
+impl ThemeState {
+    pub fn new(preference: ThemePreference, system: Option<Theme>) -> Self {
+        Self {
+            system,
+            preference,
+            effective: resolve_theme(preference, system),
+        }
+    }
+}
+
+impl From<tauri::Theme> for Theme {
+    fn from(theme: tauri::Theme) -> Self {
+        match theme {
+            tauri::Theme::Dark => Theme::Dark,
+            _ => Theme::Light,
+        }
+    }
+}
+
 impl Theme {
     /// Detects the current system theme preference
     pub fn detect_system_theme() -> Self {
@@ -67,6 +118,41 @@ mod tests {
         assert_eq!(colors.accent_color, "#0a84ff");
     }
 
+    #[test]
+    fn test_resolve_theme_matrix() {
+        let systems = [None, Some(Theme::Light), Some(Theme::Dark)];
+        for system in systems {
+            assert_eq!(resolve_theme(ThemePreference::Light, system), Theme::Light);
+            assert_eq!(resolve_theme(ThemePreference::Dark, system), Theme::Dark);
+        }
+        assert_eq!(resolve_theme(ThemePreference::Auto, None), Theme::Light);
+        assert_eq!(
+            resolve_theme(ThemePreference::Auto, Some(Theme::Light)),
+            Theme::Light
+        );
+        assert_eq!(
+            resolve_theme(ThemePreference::Auto, Some(Theme::Dark)),
+            Theme::Dark
+        );
+    }
+
+    #[test]
+    fn test_theme_state() {
+        let state = ThemeState::new(ThemePreference::Auto, Some(Theme::Dark));
+        assert_eq!(state.effective, Theme::Dark);
+
+        let json = serde_json::to_value(state).unwrap();
+        assert_eq!(json["system"], "dark");
+        assert_eq!(json["preference"], "auto");
+        assert_eq!(json["effective"], "dark");
+    }
+
+    #[test]
+    fn test_from_tauri_theme() {
+        assert_eq!(Theme::from(tauri::Theme::Dark), Theme::Dark);
+        assert_eq!(Theme::from(tauri::Theme::Light), Theme::Light);
+    }
+
     #[test]
     fn test_theme_equality() {
         assert_eq!(Theme::Light, Theme::Light);
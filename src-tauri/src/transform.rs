@@ -0,0 +1,114 @@
+/// Whitespace clean-up applied to clipboard text before a paste types it
+///
+/// Every step is off by default, so the clipboard is typed as-is unless the
+/// config asks otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransformConfig {
+    /// Drop spaces and tabs at the end of every line
+    pub trim_trailing_whitespace: bool,
+    /// Turn `\r\n` and lone `\r` into `\n`
+    pub normalize_line_endings: bool,
+    /// Drop one line break at the very end, so the last line isn't submitted
+    pub strip_final_newline: bool,
+}
+
+impl TransformConfig {
+    /// Whether any step is turned on
+    pub fn is_noop(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `text` with the steps `config` turns on, in the order line endings,
+/// trailing whitespace, final newline
+///
+/// Interior blank lines are kept either way.
+pub fn apply_transforms(text: &str, config: &TransformConfig) -> String {
+    let mut text = if config.normalize_line_endings {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text.to_string()
+    };
+
+    if config.trim_trailing_whitespace {
+        text = text
+            .split('\n')
+            .map(|line| match line.strip_suffix('\r') {
+                Some(line) => format!("{}\r", line.trim_end()),
+                None => line.trim_end().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    if config.strip_final_newline {
+        if let Some(stripped) = text
+            .strip_suffix("\r\n")
+            .or_else(|| text.strip_suffix('\n'))
+            .or_else(|| text.strip_suffix('\r'))
+        {
+            text.truncate(stripped.len());
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: TransformConfig = TransformConfig {
+        trim_trailing_whitespace: true,
+        normalize_line_endings: true,
+        strip_final_newline: true,
+    };
+
+    #[test]
+    fn test_default_leaves_text_alone() {
+        let text = "a  \r\n\r\nb\t\n";
+        assert!(TransformConfig::default().is_noop());
+        assert_eq!(apply_transforms(text, &TransformConfig::default()), text);
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let config = TransformConfig {
+            normalize_line_endings: true,
+            ..TransformConfig::default()
+        };
+        assert_eq!(apply_transforms("a\r\nb\rc\n", &config), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace() {
+        let config = TransformConfig {
+            trim_trailing_whitespace: true,
+            ..TransformConfig::default()
+        };
+        assert_eq!(apply_transforms("  a \t\n\nb  ", &config), "  a\n\nb");
+        // CRLF is kept when line endings aren't normalized
+        assert_eq!(apply_transforms("a  \r\nb \r\n", &config), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_strip_final_newline() {
+        let config = TransformConfig {
+            strip_final_newline: true,
+            ..TransformConfig::default()
+        };
+        assert_eq!(apply_transforms("a\n\n", &config), "a\n");
+        assert_eq!(apply_transforms("a\r\n", &config), "a");
+        assert_eq!(apply_transforms("a", &config), "a");
+        assert_eq!(apply_transforms("", &config), "");
+    }
+
+    #[test]
+    fn test_all_steps_keep_interior_blank_lines() {
+        assert_eq!(
+            apply_transforms("first  \r\n\r\n  second\t\r\n", &ALL),
+            "first\n\n  second"
+        );
+        assert_eq!(apply_transforms(" \r\n", &ALL), "");
+    }
+}
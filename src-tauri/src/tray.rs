@@ -1,8 +1,23 @@
-use log::{debug, error, info};
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use log::{debug, error, info, warn};
 use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder},
-    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Emitter, Runtime,
+    image::Image,
+    menu::{CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, SubmenuBuilder},
+    tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Emitter, Manager, Runtime,
+};
+
+use crate::{
+    debounce::PasteSource,
+    events::{CancelRequest, PasteRequest},
+    helpers::group_thousands,
+    keyboard::PasteMethod,
+    repeat::{self, Repeat},
+    typing_state::TypingState,
 };
 
 /// Extract tooltip text for reuse and testing
@@ -10,6 +25,143 @@ pub fn get_tray_tooltip() -> &'static str {
     "Pasta - Clipboard to Keyboard"
 }
 
+/// Progress-only tray updates are at least this far apart
+pub const STATUS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Badge on the tray icon while typing
+const TYPING_BADGE: [u8; 4] = [0x34, 0xC7, 0x59, 0xFF];
+/// Badge on the tray icon while paused
+const PAUSED_BADGE: [u8; 4] = [0xFF, 0x9F, 0x0A, 0xFF];
+
+/// What the tray shows about the keyboard worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrayStatus {
+    #[default]
+    Idle,
+    Typing {
+        typed: usize,
+        total: usize,
+    },
+    Paused {
+        typed: usize,
+        total: usize,
+    },
+}
+
+impl From<&TypingState> for TrayStatus {
+    fn from(state: &TypingState) -> Self {
+        let (typed, total) = (state.typed, state.total);
+        match (state.is_typing, state.is_paused) {
+            (false, _) => TrayStatus::Idle,
+            (true, false) => TrayStatus::Typing { typed, total },
+            (true, true) => TrayStatus::Paused { typed, total },
+        }
+    }
+}
+
+impl TrayStatus {
+    /// Tooltip while typing or paused, `None` when idle
+    pub fn tooltip(&self) -> Option<String> {
+        let (verb, typed, total) = match *self {
+            TrayStatus::Idle => return None,
+            TrayStatus::Typing { typed, total } => ("typing", typed, total),
+            TrayStatus::Paused { typed, total } => ("paused at", typed, total),
+        };
+        let percent = (typed * 100).checked_div(total).unwrap_or(0);
+        Some(format!(
+            "Pasta - {verb} {percent}% ({} / {})",
+            group_thousands(typed),
+            group_thousands(total)
+        ))
+    }
+
+    fn same_kind(&self, other: &TrayStatus) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// How much of the tray a status change touches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StatusUpdate {
+    Skip,
+    Tooltip,
+    IconAndTooltip,
+}
+
+/// Rate-limits status changes: a new kind of status is shown at once, more
+/// progress at most every `STATUS_UPDATE_INTERVAL`
+#[derive(Default)]
+struct StatusThrottle {
+    shown: TrayStatus,
+    shown_at: Option<Instant>,
+}
+
+impl StatusThrottle {
+    fn accept(&mut self, status: TrayStatus, now: Instant) -> StatusUpdate {
+        let update = if !status.same_kind(&self.shown) {
+            StatusUpdate::IconAndTooltip
+        } else if status == self.shown
+            || self
+                .shown_at
+                .is_some_and(|at| now.duration_since(at) < STATUS_UPDATE_INTERVAL)
+        {
+            StatusUpdate::Skip
+        } else {
+            StatusUpdate::Tooltip
+        };
+        if update != StatusUpdate::Skip {
+            self.shown = status;
+            self.shown_at = Some(now);
+        }
+        update
+    }
+}
+
+/// `rgba` with a filled dot of `color` in the bottom-right quarter
+fn with_badge(rgba: &[u8], width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+    let mut badged = rgba.to_vec();
+    let radius = width.min(height) as f32 / 4.0;
+    let (cx, cy) = (width as f32 - radius, height as f32 - radius);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                badged[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+    badged
+}
+
+/// The tray icon for each kind of status, built from the app icon
+struct StatusIcons {
+    idle: Image<'static>,
+    typing: Image<'static>,
+    paused: Image<'static>,
+}
+
+impl StatusIcons {
+    fn new(icon: &Image<'_>) -> Self {
+        let (width, height) = (icon.width(), icon.height());
+        let badged =
+            |color| Image::new_owned(with_badge(icon.rgba(), width, height, color), width, height);
+        Self {
+            idle: Image::new_owned(icon.rgba().to_vec(), width, height),
+            typing: badged(TYPING_BADGE),
+            paused: badged(PAUSED_BADGE),
+        }
+    }
+
+    fn for_status(&self, status: TrayStatus) -> &Image<'static> {
+        match status {
+            TrayStatus::Idle => &self.idle,
+            TrayStatus::Typing { .. } => &self.typing,
+            TrayStatus::Paused { .. } => &self.paused,
+        }
+    }
+}
+
 /// Determine the action to take for a tray icon event
 #[derive(Debug, PartialEq)]
 pub enum TrayIconAction {
@@ -29,14 +181,178 @@ pub fn handle_tray_icon_click(
     }
 }
 
-pub struct TrayManager {}
+/// Run `setup`, retrying failures `is_transient` accepts with backoff
+///
+/// Works around a Tauri bug where menus built too early after startup fail.
+/// Delays come from `helpers::get_startup_delay`; once every attempt has
+/// failed, waits `TRAY_SETUP_FALLBACK_DELAY` and tries a last time. Other
+/// failures are returned at once.
+pub fn setup_with_retry<E, S, T, W>(mut setup: S, is_transient: T, mut sleep: W) -> Result<(), E>
+where
+    E: std::fmt::Display,
+    S: FnMut() -> Result<(), E>,
+    T: Fn(&E) -> bool,
+    W: FnMut(Duration),
+{
+    use crate::helpers::{get_startup_delay, TRAY_SETUP_ATTEMPTS, TRAY_SETUP_FALLBACK_DELAY};
+
+    for attempt in 0..TRAY_SETUP_ATTEMPTS {
+        let delay = get_startup_delay(attempt);
+        if !delay.is_zero() {
+            sleep(delay);
+        }
+        match setup() {
+            Ok(()) => return Ok(()),
+            Err(e) if is_transient(&e) => {
+                warn!(
+                    "Tray setup attempt {} of {TRAY_SETUP_ATTEMPTS} failed: {e}",
+                    attempt + 1
+                );
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    warn!("Tray setup kept failing, trying once more after a longer wait");
+    sleep(TRAY_SETUP_FALLBACK_DELAY);
+    setup()
+}
+
+/// Menu and tray build errors come from Tauri and may pass on a retry;
+/// anything else, like a missing icon, won't
+pub fn is_transient_setup_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.downcast_ref::<tauri::Error>().is_some()
+}
+
+/// Show `tooltip` on the tray icon, if there is one
+pub fn set_tooltip<R: Runtime>(app: &AppHandle<R>, tooltip: &str) {
+    if let Some(tray) = app.tray_by_id("main") {
+        if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+            warn!("Failed to set tray tooltip: {e}");
+        }
+    }
+}
+
+/// Rebuild the menu with or without "Cancel scheduled paste"
+pub fn show_scheduled_menu<R: Runtime>(app: &AppHandle<R>, scheduled: bool) {
+    if let Some(tray_manager) = app.try_state::<Arc<TrayManager<R>>>() {
+        tray_manager.update_menu(|menu| menu.scheduled = scheduled);
+    }
+}
+
+/// Keep "Cancel Typing" enabled while a paste counts down
+pub fn show_countdown_menu<R: Runtime>(app: &AppHandle<R>, counting_down: bool) {
+    if let Some(tray_manager) = app.try_state::<Arc<TrayManager<R>>>() {
+        tray_manager.update_menu(|menu| menu.counting_down = counting_down);
+    }
+}
+
+/// Tick the current paste method in the menu
+pub fn show_paste_method_menu<R: Runtime>(app: &AppHandle<R>, paste_method: PasteMethod) {
+    if let Some(tray_manager) = app.try_state::<Arc<TrayManager<R>>>() {
+        tray_manager.update_menu(|menu| menu.paste_method = paste_method);
+    }
+}
+
+/// Check or uncheck "Launch at Login"
+pub fn show_launch_at_login_menu<R: Runtime>(app: &AppHandle<R>, launch_at_login: bool) {
+    if let Some(tray_manager) = app.try_state::<Arc<TrayManager<R>>>() {
+        tray_manager.update_menu(|menu| menu.launch_at_login = launch_at_login);
+    }
+}
+
+/// List the config's snippets, by name, in the "Snippets" submenu
+pub fn show_snippets_menu<R: Runtime>(app: &AppHandle<R>, snippets: Vec<String>) {
+    if let Some(tray_manager) = app.try_state::<Arc<TrayManager<R>>>() {
+        tray_manager.update_menu(|menu| menu.snippets = snippets);
+    }
+}
+
+/// What the menu shows besides its fixed items
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct MenuState {
+    scheduled: bool,
+    typing: bool,
+    counting_down: bool,
+    paste_method: PasteMethod,
+    launch_at_login: bool,
+    snippets: Vec<String>,
+}
+
+/// Builds the tray and keeps its icon to show the typing status on
+pub struct TrayManager<R: Runtime = tauri::Wry> {
+    tray: OnceLock<TrayIcon<R>>,
+    icons: OnceLock<StatusIcons>,
+    throttle: Mutex<StatusThrottle>,
+    menu: Mutex<MenuState>,
+}
 
-impl TrayManager {
+impl<R: Runtime> TrayManager<R> {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            tray: OnceLock::new(),
+            icons: OnceLock::new(),
+            throttle: Mutex::new(StatusThrottle::default()),
+            menu: Mutex::new(MenuState::default()),
+        }
     }
 
-    fn build_tauri_menu<R: Runtime>(
+    /// Rebuild the menu if `change` alters what it shows
+    fn update_menu(&self, change: impl FnOnce(&mut MenuState)) {
+        let menu = {
+            let mut menu = self.menu.lock().unwrap();
+            let before = menu.clone();
+            change(&mut menu);
+            if *menu == before {
+                return;
+            }
+            menu.clone()
+        };
+        let Some(tray) = self.tray.get() else {
+            return;
+        };
+        let structure = crate::app_logic::menu_structure(
+            menu.scheduled,
+            menu.typing || menu.counting_down,
+            menu.paste_method,
+            menu.launch_at_login,
+            &menu.snippets,
+        );
+        let result = self
+            .build_tauri_menu(tray.app_handle(), &structure)
+            .and_then(|menu| tray.set_menu(Some(menu)).map_err(Into::into));
+        if let Err(e) = result {
+            warn!("Failed to update the tray menu: {e}");
+        }
+    }
+
+    /// Show `status` in the icon and tooltip, rate-limited
+    ///
+    /// Going back to idle restores the default tooltip, which also ends any
+    /// scheduled paste countdown shown there.
+    pub fn update_status(&self, status: TrayStatus) {
+        let update = self.throttle.lock().unwrap().accept(status, Instant::now());
+        if update == StatusUpdate::Skip {
+            return;
+        }
+        let Some(tray) = self.tray.get() else {
+            return;
+        };
+        if update == StatusUpdate::IconAndTooltip {
+            self.update_menu(|menu| menu.typing = status != TrayStatus::Idle);
+            if let Some(icons) = self.icons.get() {
+                if let Err(e) = tray.set_icon(Some(icons.for_status(status).clone())) {
+                    warn!("Failed to set tray icon: {e}");
+                }
+            }
+        }
+        let tooltip = status.tooltip();
+        if let Err(e) = tray.set_tooltip(Some(tooltip.as_deref().unwrap_or(get_tray_tooltip()))) {
+            warn!("Failed to set tray tooltip: {e}");
+        }
+    }
+
+    fn build_tauri_menu(
         &self,
         app: &AppHandle<R>,
         structure: &crate::app_logic::MenuStructure,
@@ -47,10 +363,43 @@ impl TrayManager {
 
         for item in &structure.items {
             match item {
-                MenuItem::Action { id, label } => {
-                    let menu_item = MenuItemBuilder::with_id(id, label).build(app)?;
+                MenuItem::Action { id, label, enabled } => {
+                    let menu_item = MenuItemBuilder::with_id(id, label)
+                        .enabled(*enabled)
+                        .build(app)?;
                     menu_builder = menu_builder.item(&menu_item);
                 }
+                MenuItem::Check { id, label, checked } => {
+                    let menu_item = CheckMenuItemBuilder::with_id(id, label)
+                        .checked(*checked)
+                        .build(app)?;
+                    menu_builder = menu_builder.item(&menu_item);
+                }
+                MenuItem::Submenu { label, items } => {
+                    let mut submenu = SubmenuBuilder::new(app, label);
+                    for item in items {
+                        match item {
+                            MenuItem::Action { id, label, enabled } => {
+                                submenu = submenu.item(
+                                    &MenuItemBuilder::with_id(id, label)
+                                        .enabled(*enabled)
+                                        .build(app)?,
+                                );
+                            }
+                            MenuItem::Check { id, label, checked } => {
+                                submenu = submenu.item(
+                                    &CheckMenuItemBuilder::with_id(id, label)
+                                        .checked(*checked)
+                                        .build(app)?,
+                                );
+                            }
+                            MenuItem::Separator => submenu = submenu.separator(),
+                            // One level of nesting is all the tray uses
+                            MenuItem::Submenu { .. } => {}
+                        }
+                    }
+                    menu_builder = menu_builder.item(&submenu.build()?);
+                }
                 MenuItem::Separator => {
                     menu_builder = menu_builder.separator();
                 }
@@ -60,18 +409,24 @@ impl TrayManager {
         Ok(menu_builder.build()?)
     }
 
-    pub fn setup<R: Runtime>(&self, app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn setup(&self, app: &AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
         info!("Setting up tray with default configuration");
 
         // Get menu structure from business logic
-        let menu_structure = crate::app_logic::create_menu_structure();
+        let menu_structure = crate::app_logic::create_menu_structure(false);
 
         // Convert to Tauri menu
         let menu = self.build_tauri_menu(app, &menu_structure)?;
 
+        let icon = app
+            .default_window_icon()
+            .ok_or("No default window icon for the tray")?
+            .clone();
+        let icons = StatusIcons::new(&icon);
+
         // Create tray icon with menu
-        let _tray = TrayIconBuilder::with_id("main")
-            .icon(app.default_window_icon().unwrap().clone())
+        let tray = TrayIconBuilder::with_id("main")
+            .icon(icon)
             .menu(&menu)
             .show_menu_on_left_click(true)
             .tooltip(get_tray_tooltip())
@@ -86,13 +441,111 @@ impl TrayManager {
                     match action {
                         MenuAction::Paste => {
                             info!("Paste menu item clicked");
-                            app.emit("paste_clipboard", ()).unwrap();
+                            if let Err(e) = app.emit("paste_clipboard", PasteRequest::default()) {
+                                error!("Failed to emit paste_clipboard event: {e:?}");
+                            }
+                        }
+                        MenuAction::PasteRepeated(count) => {
+                            info!("Paste ×{count} menu item clicked");
+                            let request = PasteRequest {
+                                repeat: Some(Repeat::new(count, repeat::TRAY_SEPARATOR)),
+                                ..PasteRequest::default()
+                            };
+                            if let Err(e) = app.emit("paste_clipboard", request) {
+                                error!("Failed to emit paste_clipboard event: {e:?}");
+                            }
+                        }
+                        MenuAction::SetPasteMethod(paste_method) => {
+                            info!("Paste method {paste_method:?} menu item clicked");
+                            let Some(state) = app.try_state::<crate::AppState>() else {
+                                return;
+                            };
+                            match state
+                                .config_manager
+                                .update(|config| config.paste_method = paste_method)
+                            {
+                                Ok(()) => show_paste_method_menu(app, paste_method),
+                                Err(e) => error!("Failed to save the paste method: {e}"),
+                            }
+                        }
+                        MenuAction::ToggleLaunchAtLogin => {
+                            info!("Launch at login menu item clicked");
+                            let Some(state) = app.try_state::<crate::AppState>() else {
+                                return;
+                            };
+                            let enabled = !state.config_manager.get().launch_at_login;
+                            if let Err(e) = crate::set_launch_at_login_for(&state, enabled) {
+                                error!("Failed to change launch at login: {e}");
+                            }
+                            // The native item flipped itself, show what actually happened
+                            show_launch_at_login_menu(
+                                app,
+                                state.config_manager.get().launch_at_login,
+                            );
+                        }
+                        MenuAction::PasteLater(secs) => {
+                            info!("Paste in {secs}s menu item clicked");
+                            if let Err(e) = crate::start_scheduled_paste(app, secs) {
+                                error!("Failed to schedule paste: {e}");
+                                crate::emit_paste_failed(app, e);
+                            }
+                        }
+                        MenuAction::TypeSnippet(index) => {
+                            let Some(state) = app.try_state::<crate::AppState>() else {
+                                return;
+                            };
+                            // The menu lists the snippets in the config's order
+                            let Some(name) = state
+                                .config_manager
+                                .get()
+                                .snippets
+                                .keys()
+                                .nth(index)
+                                .cloned()
+                            else {
+                                return;
+                            };
+                            info!("Snippet {name:?} menu item clicked");
+                            if let Err(e) =
+                                crate::start_snippet_paste(app, &name, PasteSource::Tray)
+                            {
+                                error!("Failed to type snippet: {e}");
+                                crate::emit_paste_failed(app, e);
+                            }
+                        }
+                        MenuAction::CancelScheduled => {
+                            info!("Cancel scheduled paste menu item clicked");
+                            if let Some(state) = app.try_state::<crate::AppState>() {
+                                state.cancel_scheduled();
+                            }
                         }
                         MenuAction::CancelTyping => {
                             info!("Cancel typing menu item clicked");
-                            app.emit("cancel_typing", ()).unwrap();
+                            if let Err(e) = app.emit("cancel_typing", CancelRequest::default()) {
+                                error!("Failed to emit cancel_typing event: {e:?}");
+                            }
+                        }
+                        MenuAction::PauseTyping => {
+                            info!("Pause typing menu item clicked");
+                            if let Some(state) = app.try_state::<crate::AppState>() {
+                                state.pause_typing();
+                            }
+                        }
+                        MenuAction::ResumeTyping => {
+                            info!("Resume typing menu item clicked");
+                            if let Some(state) = app.try_state::<crate::AppState>() {
+                                state.resume_typing();
+                            }
+                        }
+                        MenuAction::OpenSettings => {
+                            info!("Settings menu item clicked");
+                            if let Err(e) = crate::window::show_settings_window(app) {
+                                error!("Failed to show settings window: {e:?}");
+                            }
                         }
                         MenuAction::Quit => {
+                            info!("Quit menu item clicked");
+                            crate::shutdown_app(app);
                             app.exit(0);
                         }
                         MenuAction::None => {}
@@ -109,7 +562,10 @@ impl TrayManager {
                         info!("Tray icon clicked - button: {button:?}, state: {button_state:?}");
 
                         // Always emit cancel typing event on any click
-                        match tray.app_handle().emit("cancel_typing", ()) {
+                        match tray
+                            .app_handle()
+                            .emit("cancel_typing", CancelRequest::default())
+                        {
                             Ok(_) => info!("Cancel typing event emitted successfully"),
                             Err(e) => error!("Failed to emit cancel typing event: {e:?}"),
                         }
@@ -133,8 +589,9 @@ impl TrayManager {
             })
             .build(app)?;
 
-        // The tray icon is automatically managed by Tauri
-        // We don't need to explicitly store it
+        // Kept for `update_status`
+        let _ = self.icons.set(icons);
+        let _ = self.tray.set(tray);
 
         Ok(())
     }
@@ -143,10 +600,69 @@ impl TrayManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::{TRAY_SETUP_ATTEMPTS, TRAY_SETUP_FALLBACK_DELAY};
+
+    /// Fails `failures` times with `error`, recording every wait
+    fn run_retry(failures: u32, error: &'static str) -> (Result<(), &'static str>, u32, Vec<u64>) {
+        let mut calls = 0;
+        let mut waits = Vec::new();
+        let result = setup_with_retry(
+            || {
+                calls += 1;
+                if calls <= failures {
+                    Err(error)
+                } else {
+                    Ok(())
+                }
+            },
+            |e| *e == "transient",
+            |delay| waits.push(delay.as_millis() as u64),
+        );
+        (result, calls, waits)
+    }
+
+    #[test]
+    fn test_setup_succeeds_without_waiting() {
+        assert_eq!(run_retry(0, "transient"), (Ok(()), 1, vec![]));
+    }
+
+    #[test]
+    fn test_setup_retries_with_backoff() {
+        assert_eq!(run_retry(3, "transient"), (Ok(()), 4, vec![25, 50, 100]));
+    }
+
+    #[test]
+    fn test_setup_falls_back_then_gives_up() {
+        let (result, calls, waits) = run_retry(u32::MAX, "transient");
+        assert_eq!(result, Err("transient"));
+        assert_eq!(calls, TRAY_SETUP_ATTEMPTS + 1);
+        assert_eq!(
+            waits.last(),
+            Some(&(TRAY_SETUP_FALLBACK_DELAY.as_millis() as u64))
+        );
+
+        // The fallback attempt can still succeed
+        let (result, calls, _) = run_retry(TRAY_SETUP_ATTEMPTS, "transient");
+        assert_eq!((result, calls), (Ok(()), TRAY_SETUP_ATTEMPTS + 1));
+    }
+
+    #[test]
+    fn test_permanent_setup_errors_are_not_retried() {
+        assert_eq!(run_retry(u32::MAX, "no icon"), (Err("no icon"), 1, vec![]));
+    }
+
+    #[test]
+    fn test_transient_setup_errors() {
+        let missing_icon: Box<dyn std::error::Error> = "No default window icon for the tray".into();
+        assert!(!is_transient_setup_error(missing_icon.as_ref()));
+        assert!(is_transient_setup_error(
+            &tauri::Error::FailedToReceiveMessage
+        ));
+    }
 
     #[test]
     fn test_tray_manager_creation() {
-        let tray_manager = TrayManager::new();
+        let tray_manager: TrayManager = TrayManager::new();
         // Just verify it can be created
         let _ = tray_manager;
     }
@@ -154,11 +670,12 @@ mod tests {
     #[test]
     fn test_menu_has_paste_item() {
         // This test verifies that our menu structure includes the paste item
-        let menu_items = vec!["paste", "cancel_typing", "quit"];
+        let menu_items = vec!["paste", "cancel_typing", "settings", "quit"];
 
         // Verify expected menu item IDs exist
         assert!(menu_items.contains(&"paste"));
         assert!(menu_items.contains(&"cancel_typing"));
+        assert!(menu_items.contains(&"settings"));
         assert!(menu_items.contains(&"quit"));
     }
 
@@ -194,6 +711,90 @@ mod tests {
         assert!(tooltip.contains("Clipboard"));
     }
 
+    #[test]
+    fn test_status_tooltip() {
+        let state = TypingState {
+            is_typing: true,
+            typed: 2300,
+            total: 10_000,
+            ..TypingState::default()
+        };
+        assert_eq!(
+            TrayStatus::from(&state).tooltip().as_deref(),
+            Some("Pasta - typing 23% (2,300 / 10,000)")
+        );
+        let paused = TypingState {
+            is_paused: true,
+            ..state
+        };
+        assert_eq!(
+            TrayStatus::from(&paused).tooltip().as_deref(),
+            Some("Pasta - paused at 23% (2,300 / 10,000)")
+        );
+        let idle = TypingState {
+            is_typing: false,
+            ..state
+        };
+        assert_eq!(TrayStatus::from(&idle).tooltip(), None);
+
+        let empty = TypingState {
+            total: 0,
+            typed: 0,
+            ..state
+        };
+        assert_eq!(
+            TrayStatus::from(&empty).tooltip().as_deref(),
+            Some("Pasta - typing 0% (0 / 0)")
+        );
+    }
+
+    #[test]
+    fn test_status_throttle() {
+        let mut throttle = StatusThrottle::default();
+        let start = Instant::now();
+        let typing = |typed| TrayStatus::Typing { typed, total: 1000 };
+
+        assert_eq!(throttle.accept(TrayStatus::Idle, start), StatusUpdate::Skip);
+        assert_eq!(
+            throttle.accept(typing(0), start),
+            StatusUpdate::IconAndTooltip
+        );
+        // More progress waits for the interval
+        assert_eq!(
+            throttle.accept(typing(200), start + Duration::from_millis(100)),
+            StatusUpdate::Skip
+        );
+        assert_eq!(
+            throttle.accept(typing(400), start + STATUS_UPDATE_INTERVAL),
+            StatusUpdate::Tooltip
+        );
+        // A new kind of status doesn't
+        let paused = TrayStatus::Paused {
+            typed: 400,
+            total: 1000,
+        };
+        assert_eq!(
+            throttle.accept(paused, start + STATUS_UPDATE_INTERVAL),
+            StatusUpdate::IconAndTooltip
+        );
+        assert_eq!(
+            throttle.accept(TrayStatus::Idle, start + STATUS_UPDATE_INTERVAL),
+            StatusUpdate::IconAndTooltip
+        );
+    }
+
+    #[test]
+    fn test_badge_covers_only_the_corner() {
+        let icon = vec![0u8; 16 * 16 * 4];
+        let badged = with_badge(&icon, 16, 16, TYPING_BADGE);
+        let pixel = |x: usize, y: usize| &badged[(y * 16 + x) * 4..(y * 16 + x) * 4 + 4];
+
+        assert_eq!(pixel(13, 13), TYPING_BADGE);
+        assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(15, 0), [0, 0, 0, 0]);
+        assert_eq!(pixel(7, 7), [0, 0, 0, 0]);
+    }
+
     #[test]
     fn test_menu_event_ids() {
         // Test that all expected menu event IDs are defined
@@ -0,0 +1,338 @@
+/// Harness for typing tests through a recording backend instead of the keyboard
+///
+/// Pauses advance a virtual clock rather than sleeping, so long texts type
+/// at once while delays and chunking stay measurable. Cancellation can be
+/// triggered at an exact character count, like a user hitting cancel, and
+/// so can pausing.
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use enigo::{Direction, Key};
+
+use crate::{
+    keyboard::{KeyBackend, KeyboardEmulator, MODIFIER_KEYS},
+    secure_input::SecureInputProbe,
+    typing_state::{TypingState, TypingTracker},
+};
+
+/// Something the worker asked the backend to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Text(String),
+    Key(Key, Direction),
+    Pause(Duration),
+}
+
+/// An `Op` at its virtual time since the backend was created
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub at: Duration,
+    pub op: Op,
+}
+
+#[derive(Default)]
+struct Shared {
+    events: Vec<Event>,
+    now: Duration,
+    /// The tracker's state just before a `pause_after` pause was resumed
+    while_paused: Option<TypingState>,
+}
+
+/// What the backend received, readable while and after the worker types
+#[derive(Clone, Default)]
+pub struct Recording(Arc<Mutex<Shared>>);
+
+/// Records keystrokes and pauses, never touching the real keyboard
+pub struct RecordingBackend {
+    recording: Recording,
+    typed: usize,
+    /// Set the flag once this many characters were typed
+    cancel: Option<(usize, Arc<AtomicBool>)>,
+    /// Pause the worker's tracker once this many characters were typed
+    pause: Option<PauseAfter>,
+    /// Reject every keystroke once this many characters were typed
+    reject_from: Option<usize>,
+}
+
+struct PauseAfter {
+    chars: usize,
+    tracker: Arc<OnceLock<Arc<TypingTracker>>>,
+    resume_after: Duration,
+}
+
+impl RecordingBackend {
+    pub fn new() -> (Self, Recording) {
+        let recording = Recording::default();
+        let backend = Self {
+            recording: recording.clone(),
+            typed: 0,
+            cancel: None,
+            pause: None,
+            reject_from: None,
+        };
+        (backend, recording)
+    }
+
+    /// Set `flag` right after character `chars` is typed
+    pub fn cancel_after(mut self, chars: usize, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some((chars, flag));
+        self
+    }
+
+    /// Fail every keystroke after character `chars`, like a display that went away
+    pub fn reject_from(mut self, chars: usize) -> Self {
+        self.reject_from = Some(chars);
+        self
+    }
+
+    fn rejecting(&self) -> bool {
+        self.reject_from.is_some_and(|chars| self.typed >= chars)
+    }
+
+    fn record(&mut self, op: Op) {
+        let mut shared = self.recording.0.lock().unwrap();
+        let at = shared.now;
+        if let Op::Pause(duration) = op {
+            shared.now += duration;
+        }
+        shared.events.push(Event { at, op });
+    }
+
+    fn typed(&mut self, count: usize) {
+        self.typed += count;
+        if let Some((after, flag)) = &self.cancel {
+            if self.typed >= *after {
+                flag.store(true, Ordering::Relaxed);
+            }
+        }
+        if self
+            .pause
+            .as_ref()
+            .is_some_and(|pause| self.typed >= pause.chars)
+        {
+            let pause = self.pause.take().unwrap();
+            let tracker = pause.tracker.get().expect("tracker not set").clone();
+            tracker.set_paused(true);
+            // Resumed from another thread, like a user clicking the tray
+            let recording = self.recording.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(pause.resume_after);
+                recording.0.lock().unwrap().while_paused = Some(tracker.snapshot());
+                tracker.set_paused(false);
+            });
+        }
+    }
+}
+
+impl KeyBackend for RecordingBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+        if self.rejecting() && direction == Direction::Click {
+            return Err("input rejected".to_string());
+        }
+        self.record(Op::Key(key, direction));
+        if direction == Direction::Click {
+            self.typed(1);
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), String> {
+        if self.rejecting() {
+            return Err("input rejected".to_string());
+        }
+        self.record(Op::Text(text.to_string()));
+        self.typed(text.chars().count());
+        Ok(())
+    }
+
+    fn pause(&mut self, duration: Duration) {
+        self.record(Op::Pause(duration));
+    }
+}
+
+/// A keyboard emulator typing into `backend`
+pub fn keyboard_with(backend: RecordingBackend) -> Arc<KeyboardEmulator> {
+    Arc::new(KeyboardEmulator::with_backend(move || Ok(backend)).unwrap())
+}
+
+/// Secure input that is off for the first `off_checks` checks, then on
+pub struct SecureInputAfter {
+    off_checks: usize,
+    checks: AtomicUsize,
+    owner: &'static str,
+}
+
+impl SecureInputAfter {
+    pub fn new(off_checks: usize, owner: &'static str) -> Self {
+        Self {
+            off_checks,
+            checks: AtomicUsize::new(0),
+            owner,
+        }
+    }
+}
+
+impl SecureInputProbe for SecureInputAfter {
+    fn enabled(&self) -> bool {
+        self.checks.fetch_add(1, Ordering::Relaxed) >= self.off_checks
+    }
+
+    fn owner(&self) -> Option<String> {
+        Some(self.owner.to_string())
+    }
+}
+
+/// A keyboard emulator typing into a fresh recording, with `secure_input`
+pub fn recording_keyboard_with_probe(
+    secure_input: SecureInputAfter,
+) -> (Arc<KeyboardEmulator>, Recording) {
+    let (backend, recording) = RecordingBackend::new();
+    let keyboard =
+        KeyboardEmulator::with_backend_and_probe(move || Ok(backend), Arc::new(secure_input));
+    (Arc::new(keyboard.unwrap()), recording)
+}
+
+/// A keyboard emulator that pauses right after character `chars` and is
+/// resumed `resume_after` later, in real time
+pub fn pausing_keyboard(
+    chars: usize,
+    resume_after: Duration,
+) -> (Arc<KeyboardEmulator>, Recording) {
+    let (mut backend, recording) = RecordingBackend::new();
+    let tracker = Arc::new(OnceLock::new());
+    backend.pause = Some(PauseAfter {
+        chars,
+        tracker: tracker.clone(),
+        resume_after,
+    });
+    let keyboard = keyboard_with(backend);
+    let _ = tracker.set(keyboard.tracker().clone());
+    (keyboard, recording)
+}
+
+/// A keyboard emulator typing into a fresh recording
+pub fn recording_keyboard() -> (Arc<KeyboardEmulator>, Recording) {
+    let (backend, recording) = RecordingBackend::new();
+    (keyboard_with(backend), recording)
+}
+
+/// Smallest, largest and mean of a set of delays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DelayStats {
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+}
+
+impl Recording {
+    pub fn events(&self) -> Vec<Event> {
+        self.0.lock().unwrap().events.clone()
+    }
+
+    /// The tracker's state seen while `pausing_keyboard` was paused
+    pub fn while_paused(&self) -> Option<TypingState> {
+        self.0.lock().unwrap().while_paused
+    }
+
+    /// Virtual time spent so far
+    pub fn elapsed(&self) -> Duration {
+        self.0.lock().unwrap().now
+    }
+
+    /// The text as it would have appeared, with Return and Tab as `\n` and `\t`
+    pub fn typed_text(&self) -> String {
+        let mut text = String::new();
+        for event in self.events() {
+            match event.op {
+                Op::Text(chunk) => text.push_str(&chunk),
+                Op::Key(Key::Return, Direction::Click) => text.push('\n'),
+                Op::Key(Key::Tab, Direction::Click) => text.push('\t'),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    pub fn assert_typed(&self, expected: &str) {
+        let typed = self.typed_text();
+        assert!(
+            typed == expected,
+            "typed {} characters, expected {}; first difference at {:?}",
+            typed.chars().count(),
+            expected.chars().count(),
+            typed
+                .chars()
+                .zip(expected.chars())
+                .position(|(a, b)| a != b)
+        );
+    }
+
+    /// Characters typed before each pause between chunks
+    ///
+    /// A chunk pause is the one right after a keystroke's own delay.
+    pub fn chunk_boundaries(&self) -> Vec<usize> {
+        let mut boundaries = Vec::new();
+        let mut typed = 0;
+        let mut last_was_pause = false;
+        for event in self.events() {
+            match event.op {
+                Op::Pause(_) if last_was_pause => boundaries.push(typed),
+                Op::Text(text) => typed += text.chars().count(),
+                Op::Key(_, Direction::Click) => typed += 1,
+                _ => {}
+            }
+            last_was_pause = matches!(event.op, Op::Pause(_));
+        }
+        boundaries
+    }
+
+    /// The delay after each keystroke, chunk pauses excluded
+    pub fn key_delays(&self) -> Vec<Duration> {
+        let events = self.events();
+        events
+            .windows(2)
+            .filter_map(|pair| match (&pair[0].op, &pair[1].op) {
+                (Op::Text(_) | Op::Key(_, Direction::Click), Op::Pause(delay)) => Some(*delay),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `None` if nothing was typed
+    pub fn delay_stats(&self) -> Option<DelayStats> {
+        let delays = self.key_delays();
+        let total: Duration = delays.iter().sum();
+        Some(DelayStats {
+            min: *delays.iter().min()?,
+            max: *delays.iter().max()?,
+            mean: total / delays.len() as u32,
+        })
+    }
+
+    /// Assert every keystroke delay is within `tolerance` of `expected`
+    pub fn assert_delays_near(&self, expected: Duration, tolerance: Duration) {
+        let stats = self.delay_stats().expect("nothing was typed");
+        let low = expected.saturating_sub(tolerance);
+        let high = expected + tolerance;
+        assert!(
+            stats.min >= low && stats.max <= high,
+            "delays {stats:?} outside {expected:?} ± {tolerance:?}"
+        );
+    }
+
+    /// Whether the recording ends with every modifier released
+    pub fn released_modifiers(&self) -> bool {
+        let releases: Vec<Op> = MODIFIER_KEYS
+            .iter()
+            .map(|key| Op::Key(*key, Direction::Release))
+            .collect();
+        let events = self.events();
+        let ops: Vec<Op> = events.into_iter().map(|event| event.op).collect();
+        ops.ends_with(&releases)
+    }
+}
@@ -0,0 +1,520 @@
+/// Bookkeeping for the keyboard worker, readable without locks
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+/// Snapshot of what the keyboard worker is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingState {
+    pub is_typing: bool,
+    pub is_paused: bool,
+    /// Characters typed so far in the current operation
+    pub typed: usize,
+    /// Characters in the current operation
+    pub total: usize,
+    /// Typing operations waiting behind the current one
+    pub queue_len: usize,
+    /// Start of the current operation in milliseconds since the Unix epoch
+    pub started_at: Option<u64>,
+}
+
+/// What a cancel request actually interrupted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReport {
+    /// Whether an operation was running or queued when the flag was set
+    pub was_typing: bool,
+    /// Characters of the running operation that won't be typed
+    pub chars_remaining: Option<usize>,
+}
+
+impl CancelReport {
+    /// Derive the report from the state at the moment of cancelling
+    pub fn from_state(state: &TypingState) -> Self {
+        Self {
+            was_typing: state.is_typing || state.queue_len > 0,
+            chars_remaining: state
+                .is_typing
+                .then(|| state.total.saturating_sub(state.typed)),
+        }
+    }
+}
+
+/// A cancel as acknowledged by the keyboard worker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelConfirmation {
+    /// Whether anything was stopped, false if it finished first or nothing ran
+    pub cancelled: bool,
+    /// Characters of the running operation typed before it stopped
+    pub chars_typed: usize,
+}
+
+/// Summary of a typing operation once the worker is done with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypingOutcome {
+    pub typed: usize,
+    pub total: usize,
+    pub cancelled: bool,
+    pub duration: Duration,
+    /// Typing stopped because an app turned on secure input
+    pub secure_input: bool,
+    /// Typing stopped because the system kept rejecting keystrokes
+    pub input_rejected: bool,
+}
+
+type ProgressListener = Box<dyn Fn(&TypingState) + Send + Sync>;
+type FinishListener = Box<dyn Fn(&TypingOutcome) + Send + Sync>;
+
+/// Shared between the keyboard emulator, its worker thread and the app state
+///
+/// Every field is an atomic so `snapshot` never blocks the worker. The
+/// listener is only invoked at operation boundaries and between chunks.
+#[derive(Default)]
+pub struct TypingTracker {
+    is_typing: AtomicBool,
+    is_paused: AtomicBool,
+    typed: AtomicUsize,
+    total: AtomicUsize,
+    queue_len: AtomicUsize,
+    /// Zero when idle
+    started_at: AtomicU64,
+    listener: Mutex<Option<ProgressListener>>,
+    finish_listener: Mutex<Option<FinishListener>>,
+    /// Told about the next finished operation, see `next_outcome`
+    outcome_waiters: Mutex<Vec<oneshot::Sender<TypingOutcome>>>,
+}
+
+impl TypingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the callback that publishes progress (e.g. as a Tauri event)
+    pub fn set_listener<F>(&self, listener: F)
+    where
+        F: Fn(&TypingState) + Send + Sync + 'static,
+    {
+        *self.listener.lock().unwrap() = Some(Box::new(listener));
+    }
+
+    /// Register the callback told about every finished operation (e.g. statistics)
+    pub fn set_finish_listener<F>(&self, listener: F)
+    where
+        F: Fn(&TypingOutcome) + Send + Sync + 'static,
+    {
+        *self.finish_listener.lock().unwrap() = Some(Box::new(listener));
+    }
+
+    /// Receive the outcome of the next operation to finish
+    ///
+    /// Sent once the tracker is idle again, so a caller that asks for it
+    /// before reading `snapshot` can't miss the end of the operation it saw.
+    pub fn next_outcome(&self) -> oneshot::Receiver<TypingOutcome> {
+        let (tx, rx) = oneshot::channel();
+        self.outcome_waiters.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Read the current state without blocking
+    pub fn snapshot(&self) -> TypingState {
+        let started_at = self.started_at.load(Ordering::Acquire);
+        TypingState {
+            is_typing: self.is_typing.load(Ordering::Acquire),
+            is_paused: self.is_paused.load(Ordering::Acquire),
+            typed: self.typed.load(Ordering::Acquire),
+            total: self.total.load(Ordering::Acquire),
+            queue_len: self.queue_len.load(Ordering::Acquire),
+            started_at: (started_at != 0).then_some(started_at),
+        }
+    }
+
+    /// A typing operation was queued for the worker
+    pub fn enqueued(&self) {
+        self.queue_len.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// A queued operation was dropped without being started
+    pub fn dequeued(&self) {
+        let _ = self
+            .queue_len
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+    }
+
+    /// Queue an operation only if nothing is queued or typing
+    ///
+    /// The check and the enqueue are a single step, so of several concurrent
+    /// callers at most one succeeds.
+    pub fn try_enqueue_idle(&self) -> bool {
+        if self
+            .queue_len
+            .compare_exchange(0, 1, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return false;
+        }
+        if self.is_typing.load(Ordering::Acquire) {
+            self.dequeued();
+            return false;
+        }
+        true
+    }
+
+    /// The worker picked up an operation of `total` characters
+    pub fn start(&self, total: usize) {
+        // A pause aimed at the previous operation doesn't carry over
+        self.is_paused.store(false, Ordering::Release);
+        self.typed.store(0, Ordering::Release);
+        self.total.store(total, Ordering::Release);
+        self.started_at.store(now_millis(), Ordering::Release);
+        // Busy before leaving the queue, so `try_enqueue_idle` never sees a gap
+        self.is_typing.store(true, Ordering::Release);
+        self.dequeued();
+        self.notify();
+    }
+
+    /// Start an operation that is finished when the guard is finished or dropped
+    ///
+    /// Dropping the guard without finishing, e.g. while a panic unwinds the
+    /// worker, reports the operation as cancelled, so the tracker never stays busy.
+    pub fn begin(&self, total: usize) -> OperationGuard<'_> {
+        self.start(total);
+        OperationGuard {
+            tracker: self,
+            finished: false,
+        }
+    }
+
+    /// Pause or resume the running operation
+    ///
+    /// Returns whether that changed anything, i.e. false while idle or when
+    /// the operation already was paused or running.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        if !self.is_typing.load(Ordering::Acquire) {
+            return false;
+        }
+        let changed = self.is_paused.swap(paused, Ordering::AcqRel) != paused;
+        if changed {
+            self.notify();
+        }
+        changed
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::Acquire)
+    }
+
+    /// `count` more characters were typed
+    pub fn advance(&self, count: usize) {
+        self.typed.fetch_add(count, Ordering::AcqRel);
+    }
+
+    /// The current operation finished or was cancelled
+    pub fn finish(&self, cancelled: bool) -> TypingOutcome {
+        let started_at = self.started_at.load(Ordering::Acquire);
+        let outcome = TypingOutcome {
+            typed: self.typed.load(Ordering::Acquire),
+            total: self.total.load(Ordering::Acquire),
+            cancelled,
+            duration: Duration::from_millis(now_millis().saturating_sub(started_at)),
+            secure_input: false,
+            input_rejected: false,
+        };
+
+        // Report before going idle so pollers never see an unrecorded operation
+        if let Some(listener) = self.finish_listener.lock().unwrap().as_ref() {
+            listener(&outcome);
+        }
+
+        self.is_typing.store(false, Ordering::Release);
+        self.is_paused.store(false, Ordering::Release);
+        self.started_at.store(0, Ordering::Release);
+        self.notify();
+        for waiter in self.outcome_waiters.lock().unwrap().drain(..) {
+            let _ = waiter.send(outcome);
+        }
+        outcome
+    }
+
+    /// Publish the current snapshot to the listener, if any
+    pub fn notify(&self) {
+        if let Some(listener) = self.listener.lock().unwrap().as_ref() {
+            listener(&self.snapshot());
+        }
+    }
+}
+
+/// A running operation, see `TypingTracker::begin`
+pub struct OperationGuard<'a> {
+    tracker: &'a TypingTracker,
+    finished: bool,
+}
+
+impl OperationGuard<'_> {
+    /// The operation finished or was cancelled
+    pub fn finish(mut self, cancelled: bool) -> TypingOutcome {
+        self.finished = true;
+        self.tracker.finish(cancelled)
+    }
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.tracker.finish(true);
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(1)
+        .max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_idle_snapshot() {
+        let tracker = TypingTracker::new();
+        assert_eq!(tracker.snapshot(), TypingState::default());
+    }
+
+    #[test]
+    fn test_operation_lifecycle() {
+        let tracker = TypingTracker::new();
+        tracker.enqueued();
+        tracker.enqueued();
+        assert_eq!(tracker.snapshot().queue_len, 2);
+
+        tracker.start(10);
+        let state = tracker.snapshot();
+        assert!(state.is_typing);
+        assert_eq!(state.total, 10);
+        assert_eq!(state.typed, 0);
+        assert_eq!(state.queue_len, 1);
+        assert!(state.started_at.is_some());
+
+        tracker.advance(4);
+        assert_eq!(tracker.snapshot().typed, 4);
+
+        tracker.finish(false);
+        let state = tracker.snapshot();
+        assert!(!state.is_typing);
+        assert_eq!(state.started_at, None);
+        assert_eq!(state.typed, 4);
+    }
+
+    #[test]
+    fn test_next_outcome_is_sent_once_idle() {
+        let tracker = TypingTracker::new();
+        tracker.start(10);
+        tracker.advance(3);
+        let mut first = tracker.next_outcome();
+        let mut second = tracker.next_outcome();
+        assert!(first.try_recv().is_err());
+
+        tracker.finish(true);
+        for waiter in [&mut first, &mut second] {
+            let outcome = waiter.try_recv().unwrap();
+            assert!(outcome.cancelled);
+            assert_eq!(outcome.typed, 3);
+        }
+
+        // Waiters are told once, not about later operations
+        tracker.start(5);
+        tracker.finish(false);
+        assert!(tracker.outcome_waiters.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dequeue_does_not_underflow() {
+        let tracker = TypingTracker::new();
+        tracker.dequeued();
+        assert_eq!(tracker.snapshot().queue_len, 0);
+    }
+
+    #[test]
+    fn test_listener_receives_snapshots() {
+        let tracker = TypingTracker::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        tracker.set_listener(move |state| seen_clone.lock().unwrap().push(*state));
+
+        tracker.start(3);
+        tracker.advance(3);
+        tracker.notify();
+        tracker.finish(false);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert!(seen[0].is_typing);
+        assert_eq!(seen[1].typed, 3);
+        assert!(!seen[2].is_typing);
+    }
+
+    #[test]
+    fn test_finish_listener_receives_outcome() {
+        let tracker = TypingTracker::new();
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let outcomes_clone = outcomes.clone();
+        tracker.set_finish_listener(move |outcome| outcomes_clone.lock().unwrap().push(*outcome));
+
+        tracker.start(8);
+        tracker.advance(5);
+        tracker.finish(true);
+
+        let outcomes = outcomes.lock().unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!((outcomes[0].typed, outcomes[0].total), (5, 8));
+        assert!(outcomes[0].cancelled);
+    }
+
+    #[test]
+    fn test_cancel_report_when_idle() {
+        let report = CancelReport::from_state(&TypingState::default());
+        assert!(!report.was_typing);
+        assert_eq!(report.chars_remaining, None);
+    }
+
+    #[test]
+    fn test_cancel_report_mid_typing() {
+        let tracker = TypingTracker::new();
+        tracker.start(10);
+        tracker.advance(4);
+
+        let report = CancelReport::from_state(&tracker.snapshot());
+        assert!(report.was_typing);
+        assert_eq!(report.chars_remaining, Some(6));
+    }
+
+    #[test]
+    fn test_cancel_report_with_only_queued_work() {
+        let tracker = TypingTracker::new();
+        tracker.enqueued();
+
+        let report = CancelReport::from_state(&tracker.snapshot());
+        assert!(report.was_typing);
+        assert_eq!(report.chars_remaining, None);
+    }
+
+    #[test]
+    fn test_try_enqueue_idle() {
+        let tracker = TypingTracker::new();
+        assert!(tracker.try_enqueue_idle());
+        assert_eq!(tracker.snapshot().queue_len, 1);
+
+        // Queued work makes the tracker busy
+        assert!(!tracker.try_enqueue_idle());
+
+        // So does an operation in progress, without leaving anything queued
+        tracker.start(3);
+        assert!(!tracker.try_enqueue_idle());
+        assert_eq!(tracker.snapshot().queue_len, 0);
+
+        tracker.finish(false);
+        assert!(tracker.try_enqueue_idle());
+    }
+
+    #[test]
+    fn test_try_enqueue_idle_has_one_winner() {
+        let tracker = Arc::new(TypingTracker::new());
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let tracker = tracker.clone();
+                std::thread::spawn(move || tracker.try_enqueue_idle())
+            })
+            .collect();
+
+        let winners = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|&won| won)
+            .count();
+        assert_eq!(winners, 1);
+        assert_eq!(tracker.snapshot().queue_len, 1);
+    }
+
+    #[test]
+    fn test_guard_finishes_once() {
+        let tracker = TypingTracker::new();
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let outcomes_clone = outcomes.clone();
+        tracker.set_finish_listener(move |outcome| outcomes_clone.lock().unwrap().push(*outcome));
+
+        let operation = tracker.begin(3);
+        assert!(tracker.snapshot().is_typing);
+        tracker.advance(3);
+        operation.finish(false);
+
+        assert!(!tracker.snapshot().is_typing);
+        let outcomes = outcomes.lock().unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(!outcomes[0].cancelled);
+    }
+
+    #[test]
+    fn test_guard_clears_state_on_panic() {
+        let tracker = TypingTracker::new();
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let outcomes_clone = outcomes.clone();
+        tracker.set_finish_listener(move |outcome| outcomes_clone.lock().unwrap().push(*outcome));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _operation = tracker.begin(10);
+            tracker.advance(4);
+            panic!("keyboard backend failed");
+        }));
+
+        assert!(result.is_err());
+        let state = tracker.snapshot();
+        assert!(!state.is_typing);
+        assert!(!state.is_paused);
+        let outcomes = outcomes.lock().unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].cancelled);
+        assert_eq!(outcomes[0].typed, 4);
+    }
+
+    #[test]
+    fn test_pause_only_while_typing() {
+        let tracker = TypingTracker::new();
+        assert!(!tracker.set_paused(true));
+        assert!(!tracker.is_paused());
+
+        let operation = tracker.begin(10);
+        assert!(tracker.set_paused(true));
+        assert!(!tracker.set_paused(true));
+        assert!(tracker.snapshot().is_paused);
+        assert!(tracker.set_paused(false));
+        assert!(!tracker.set_paused(false));
+
+        // Finishing, cancelled or not, clears the pause
+        tracker.set_paused(true);
+        operation.finish(true);
+        assert!(!tracker.is_paused());
+        assert!(!tracker.set_paused(false));
+    }
+
+    #[test]
+    fn test_serialization_uses_camel_case() {
+        let json = serde_json::to_value(TypingState::default()).unwrap();
+        assert_eq!(json["isTyping"], false);
+        assert_eq!(json["queueLen"], 0);
+        assert!(json["startedAt"].is_null());
+    }
+}
@@ -0,0 +1,194 @@
+/// Typing test: compare what was sent to the keyboard with what arrived
+use serde::Serialize;
+
+use crate::error::PastaError;
+
+/// Sample typed when the user doesn't provide one
+pub const DEFAULT_SAMPLE: &str = "The quick brown fox jumps over the lazy dog.\n\tSphinx of black quartz, judge my vow! 0123456789";
+
+/// Longest sample the typing test accepts, keeps the comparison cheap
+pub const MAX_SAMPLE_CHARS: usize = 2000;
+
+/// How closely the received text matches the sent text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingAccuracyReport {
+    pub sent_chars: usize,
+    pub received_chars: usize,
+    /// Characters that arrived in the right order
+    pub matched: usize,
+    /// Sent characters that never arrived
+    pub dropped: usize,
+    /// Received characters that were never sent
+    pub inserted: usize,
+    /// Inserted characters that repeat the character before them
+    pub duplicated: usize,
+    /// Char offset in the sent text of the first mismatch
+    pub first_mismatch: Option<usize>,
+    pub exact: bool,
+}
+
+/// Pick the text to type: the user's sample, or the built-in one if none was given
+pub fn resolve_sample(sample: Option<String>) -> Result<String, PastaError> {
+    match sample {
+        Some(sample) if sample.chars().count() > MAX_SAMPLE_CHARS => Err(PastaError::TooLarge {
+            chars: sample.chars().count(),
+            max: MAX_SAMPLE_CHARS,
+        }),
+        Some(sample) if !sample.is_empty() => Ok(sample),
+        _ => Ok(DEFAULT_SAMPLE.to_string()),
+    }
+}
+
+/// Align `received` against `sent` and classify the differences
+///
+/// Uses a longest-common-subsequence alignment, so a single dropped or
+/// duplicated key doesn't make every following character a mismatch.
+/// Inputs are truncated to `MAX_SAMPLE_CHARS`.
+pub fn compare_typed_output(sent: &str, received: &str) -> TypingAccuracyReport {
+    let sent: Vec<char> = sent.chars().take(MAX_SAMPLE_CHARS).collect();
+    let received: Vec<char> = received.chars().take(MAX_SAMPLE_CHARS).collect();
+    let (n, m) = (sent.len(), received.len());
+
+    // lcs[i][j] = LCS length of sent[i..] and received[j..]
+    let mut lcs = vec![vec![0u16; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if sent[i] == received[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut matched = 0;
+    let mut dropped = 0;
+    let mut inserted = 0;
+    let mut duplicated = 0;
+    let mut first_mismatch = None;
+
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && sent[i] == received[j] {
+            matched += 1;
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            inserted += 1;
+            if j > 0 && received[j] == received[j - 1] {
+                duplicated += 1;
+            }
+            first_mismatch.get_or_insert(i);
+            j += 1;
+        } else {
+            dropped += 1;
+            first_mismatch.get_or_insert(i);
+            i += 1;
+        }
+    }
+
+    TypingAccuracyReport {
+        sent_chars: n,
+        received_chars: m,
+        matched,
+        dropped,
+        inserted,
+        duplicated,
+        first_mismatch,
+        exact: dropped == 0 && inserted == 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_sample() {
+        assert_eq!(resolve_sample(None).unwrap(), DEFAULT_SAMPLE);
+        assert_eq!(resolve_sample(Some(String::new())).unwrap(), DEFAULT_SAMPLE);
+        assert_eq!(resolve_sample(Some("abc".to_string())).unwrap(), "abc");
+        assert_eq!(
+            resolve_sample(Some("a".repeat(MAX_SAMPLE_CHARS + 1))),
+            Err(PastaError::TooLarge {
+                chars: MAX_SAMPLE_CHARS + 1,
+                max: MAX_SAMPLE_CHARS,
+            })
+        );
+    }
+
+    #[test]
+    fn test_exact_match() {
+        let report = compare_typed_output(DEFAULT_SAMPLE, DEFAULT_SAMPLE);
+        assert!(report.exact);
+        assert_eq!(report.matched, DEFAULT_SAMPLE.chars().count());
+        assert_eq!(report.first_mismatch, None);
+    }
+
+    #[test]
+    fn test_dropped_characters() {
+        let report = compare_typed_output("hello world", "helo wrld");
+        assert!(!report.exact);
+        assert_eq!(report.dropped, 2);
+        assert_eq!(report.inserted, 0);
+        assert_eq!(report.matched, 9);
+        assert_eq!(report.first_mismatch, Some(3));
+    }
+
+    #[test]
+    fn test_duplicated_characters() {
+        let report = compare_typed_output("abc", "abbcc");
+        assert_eq!(report.dropped, 0);
+        assert_eq!(report.inserted, 2);
+        assert_eq!(report.duplicated, 2);
+        assert_eq!(report.matched, 3);
+    }
+
+    #[test]
+    fn test_inserted_but_not_duplicated() {
+        let report = compare_typed_output("abc", "axbc");
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.duplicated, 0);
+        assert_eq!(report.first_mismatch, Some(1));
+    }
+
+    #[test]
+    fn test_substitution_counts_as_drop_and_insert() {
+        let report = compare_typed_output("cat", "cut");
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.matched, 2);
+    }
+
+    #[test]
+    fn test_nothing_received() {
+        let report = compare_typed_output("abc", "");
+        assert_eq!(report.dropped, 3);
+        assert_eq!(report.received_chars, 0);
+        assert_eq!(report.first_mismatch, Some(0));
+    }
+
+    #[test]
+    fn test_unicode_and_whitespace() {
+        let report = compare_typed_output("a\tb\n😀", "a\tb\n😀😀");
+        assert_eq!(report.matched, 5);
+        assert_eq!(report.duplicated, 1);
+    }
+
+    #[test]
+    fn test_inputs_are_truncated() {
+        let long = "a".repeat(MAX_SAMPLE_CHARS + 10);
+        let report = compare_typed_output(&long, &long);
+        assert_eq!(report.sent_chars, MAX_SAMPLE_CHARS);
+        assert!(report.exact);
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let json = serde_json::to_value(compare_typed_output("a", "a")).unwrap();
+        assert_eq!(json["sentChars"], 1);
+        assert_eq!(json["exact"], true);
+        assert!(json["firstMismatch"].is_null());
+    }
+}
@@ -0,0 +1,263 @@
+/// Checks GitHub for a newer release; never downloads or installs anything
+///
+/// The release is fetched with the system `curl`, which every supported
+/// platform ships, so no HTTP client is bundled. Any failure along the way
+/// reports `UpdateStatus::Unknown` rather than an error.
+use std::{
+    cmp::Ordering,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Latest published release, drafts and pre-releases excluded
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/utensils/pasta/releases/latest";
+
+/// How long a check may take before it counts as unknown
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the background check runs when `update_check` is on
+pub const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Result of a check, sent to the settings window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UpdateStatus {
+    Available {
+        version: String,
+        url: String,
+    },
+    UpToDate,
+    /// The check failed, e.g. offline or rate limited
+    Unknown,
+}
+
+impl UpdateStatus {
+    pub fn message(&self) -> String {
+        match self {
+            UpdateStatus::Available { version, .. } => format!("Pasta {version} is available"),
+            UpdateStatus::UpToDate => "Pasta is up to date".to_string(),
+            UpdateStatus::Unknown => "Couldn't check for updates".to_string(),
+        }
+    }
+}
+
+/// A `major.minor.patch[-pre][+build]` version, ordered by semver rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    /// Dot-separated pre-release identifiers, empty for a release
+    pre: Vec<String>,
+}
+
+impl Version {
+    /// Parse a version or a release tag such as `v0.4.0`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix('v').unwrap_or(text);
+        let text = text.split('+').next()?;
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, pre.split('.').map(str::to_string).collect()),
+            None => (text, Vec::new()),
+        };
+        let mut numbers = core.split('.').map(|part| part.parse::<u64>().ok());
+        let version = Self {
+            major: numbers.next()??,
+            minor: numbers.next()??,
+            patch: numbers.next()??,
+            pre,
+        };
+        if numbers.next().is_some() || version.pre.iter().any(String::is_empty) {
+            return None;
+        }
+        Some(version)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // A pre-release comes before its release
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => compare_pre(&self.pre, &other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Numeric identifiers compare as numbers and sort before alphanumeric ones
+fn compare_pre(a: &[String], b: &[String]) -> Ordering {
+    for (a, b) in a.iter().zip(b) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// The fields of a GitHub release that matter here
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+}
+
+/// Compare the release in `response` against `current`
+pub fn status_from_response(current: &str, response: &str) -> UpdateStatus {
+    let Ok(release) = serde_json::from_str::<Release>(response) else {
+        return UpdateStatus::Unknown;
+    };
+    match (Version::parse(current), Version::parse(&release.tag_name)) {
+        (Some(current), Some(latest)) if latest > current => {
+            let version = release.tag_name.trim();
+            UpdateStatus::Available {
+                version: version.strip_prefix('v').unwrap_or(version).to_string(),
+                url: release.html_url,
+            }
+        }
+        (Some(_), Some(_)) => UpdateStatus::UpToDate,
+        _ => UpdateStatus::Unknown,
+    }
+}
+
+/// Fetch the latest release and compare it against this build
+///
+/// Blocks for up to `FETCH_TIMEOUT`.
+pub fn check_for_updates() -> UpdateStatus {
+    match fetch_latest_release() {
+        Some(response) => status_from_response(env!("CARGO_PKG_VERSION"), &response),
+        None => UpdateStatus::Unknown,
+    }
+}
+
+fn fetch_latest_release() -> Option<String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--fail", "--location", "--max-time"])
+        .arg(FETCH_TIMEOUT.as_secs().to_string())
+        .args(["--header", "Accept: application/vnd.github+json"])
+        .args(["--user-agent", concat!("pasta/", env!("CARGO_PKG_VERSION"))])
+        .arg(LATEST_RELEASE_URL)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASE: &str = r#"{
+        "url": "https://api.github.com/repos/utensils/pasta/releases/1",
+        "html_url": "https://github.com/utensils/pasta/releases/tag/v0.4.0",
+        "tag_name": "v0.4.0",
+        "name": "Pasta 0.4.0",
+        "draft": false,
+        "prerelease": false,
+        "assets": []
+    }"#;
+
+    const RATE_LIMITED: &str = r#"{
+        "message": "API rate limit exceeded for 127.0.0.1.",
+        "documentation_url": "https://docs.github.com/rest/overview/resources-in-the-rest-api#rate-limiting"
+    }"#;
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(v("v1.2.3"), v("1.2.3"));
+        assert_eq!(v("1.2.3+build.5"), v("1.2.3"));
+        assert_eq!(v("1.2.3-rc.1").pre, ["rc", "1"]);
+        for bad in ["", "1.2", "1.2.3.4", "1.x.3", "1.2.3-", "1.2.3-rc..1"] {
+            assert_eq!(Version::parse(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_semver_ordering() {
+        // Each version sorts before the next, per the semver spec example
+        let ordered = [
+            "0.2.1",
+            "0.10.0",
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_newer_release_is_available() {
+        assert_eq!(
+            status_from_response("0.2.1", RELEASE),
+            UpdateStatus::Available {
+                version: "0.4.0".to_string(),
+                url: "https://github.com/utensils/pasta/releases/tag/v0.4.0".to_string(),
+            }
+        );
+        assert_eq!(
+            status_from_response("0.2.1", RELEASE).message(),
+            "Pasta 0.4.0 is available"
+        );
+    }
+
+    #[test]
+    fn test_same_or_older_release_is_up_to_date() {
+        assert_eq!(
+            status_from_response("0.4.0", RELEASE),
+            UpdateStatus::UpToDate
+        );
+        assert_eq!(
+            status_from_response("0.5.0-rc.1", RELEASE),
+            UpdateStatus::UpToDate
+        );
+    }
+
+    #[test]
+    fn test_unusable_responses_are_unknown() {
+        for response in [
+            RATE_LIMITED,
+            "",
+            "<html>",
+            r#"{"tag_name": "nightly", "html_url": ""}"#,
+        ] {
+            assert_eq!(
+                status_from_response("0.2.1", response),
+                UpdateStatus::Unknown
+            );
+        }
+    }
+}
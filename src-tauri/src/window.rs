@@ -0,0 +1,638 @@
+/// Settings window management
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use log::{debug, error, info};
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, Runtime, WebviewUrl,
+    WebviewWindow, WebviewWindowBuilder, WindowEvent,
+};
+
+use crate::{
+    config::{ConfigDto, SettingsCloseBehavior, WindowState},
+    confirm::PastePreview,
+    theme::{Theme, ThemeState},
+    AppState,
+};
+
+/// Label of the settings window
+pub const SETTINGS_WINDOW_LABEL: &str = "settings";
+
+/// Label of the first-run onboarding window
+pub const ONBOARDING_WINDOW_LABEL: &str = "onboarding";
+
+/// Label of the window asking whether to type a paste
+pub const CONFIRM_WINDOW_LABEL: &str = "confirm_paste";
+
+/// How long the window must stay still before its geometry is saved
+const WINDOW_STATE_SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Coalesces bursts of move/resize events into a single save
+static WINDOW_STATE_SAVES: Debouncer = Debouncer::new();
+
+/// Generation-counter debouncer: only the latest trigger is acted upon
+#[derive(Default)]
+pub struct Debouncer {
+    generation: AtomicU64,
+}
+
+impl Debouncer {
+    pub const fn new() -> Self {
+        Self {
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Register a new trigger and return its generation
+    pub fn trigger(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Whether no newer trigger happened since `generation`
+    pub fn is_latest(&self, generation: u64) -> bool {
+        self.generation.load(Ordering::SeqCst) == generation
+    }
+}
+
+/// Position and size of a monitor in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn overlap_area(state: &WindowState, monitor: &MonitorBounds) -> i64 {
+    let left = i64::from(state.x).max(i64::from(monitor.x));
+    let top = i64::from(state.y).max(i64::from(monitor.y));
+    let right = (i64::from(state.x) + i64::from(state.width))
+        .min(i64::from(monitor.x) + i64::from(monitor.width));
+    let bottom = (i64::from(state.y) + i64::from(state.height))
+        .min(i64::from(monitor.y) + i64::from(monitor.height));
+
+    (right - left).max(0) * (bottom - top).max(0)
+}
+
+/// Fit saved window geometry onto the current monitors.
+///
+/// The window is placed on the monitor it overlaps most (the first monitor
+/// if it overlaps none, e.g. after a display was unplugged), shrunk to fit
+/// that monitor and moved so it is entirely visible.
+pub fn clamp_window_state(state: WindowState, monitors: &[MonitorBounds]) -> Option<WindowState> {
+    let monitor = monitors
+        .iter()
+        .max_by_key(|monitor| overlap_area(&state, monitor))
+        .filter(|monitor| overlap_area(&state, monitor) > 0)
+        .or_else(|| monitors.first())?;
+
+    let width = state.width.min(monitor.width);
+    let height = state.height.min(monitor.height);
+
+    let max_x = i64::from(monitor.x) + i64::from(monitor.width - width);
+    let max_y = i64::from(monitor.y) + i64::from(monitor.height - height);
+    let x = i64::from(state.x).clamp(i64::from(monitor.x), max_x) as i32;
+    let y = i64::from(state.y).clamp(i64::from(monitor.y), max_y) as i32;
+
+    Some(WindowState {
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+/// Whether the settings window is meant to be visible.
+/// Activation policy updates read this when they run on the main thread, so the
+/// most recent show/hide request always wins even if the window is closed right
+/// after being opened.
+static SETTINGS_VISIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the onboarding window is open
+static ONBOARDING_VISIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Whether any Pasta window is meant to be visible (macOS dock icon)
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn any_window_visible() -> bool {
+    SETTINGS_VISIBLE.load(Ordering::SeqCst) || ONBOARDING_VISIBLE.load(Ordering::SeqCst)
+}
+
+/// Activation policy name for the given settings window visibility (macOS)
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub fn desired_activation_policy(settings_visible: bool) -> &'static str {
+    if settings_visible {
+        "Regular"
+    } else {
+        "Accessory"
+    }
+}
+
+/// Create the settings window, initially hidden
+pub fn create_settings_window<R: Runtime>(
+    app: &AppHandle<R>,
+) -> Result<WebviewWindow<R>, Box<dyn std::error::Error>> {
+    info!("Creating settings window");
+
+    let window = WebviewWindowBuilder::new(
+        app,
+        SETTINGS_WINDOW_LABEL,
+        WebviewUrl::App("index.html".into()),
+    )
+    .title("Pasta Settings")
+    .inner_size(400.0, 300.0)
+    .visible(false)
+    .build()?;
+
+    restore_window_state(app, &window);
+
+    let app_handle = app.clone();
+    window.on_window_event(move |event| match event {
+        WindowEvent::CloseRequested { api, .. } => {
+            let action = close_request_action(current_close_behavior(&app_handle));
+            if action.prevent_close {
+                api.prevent_close();
+            }
+            if action.save_geometry_now {
+                // No more move/resize events will arrive to trigger the debounced save
+                save_window_state(&app_handle);
+            }
+            handle_window_close_request(&app_handle, action);
+        }
+        WindowEvent::Destroyed => {
+            debug!("Settings window destroyed");
+            SETTINGS_VISIBLE.store(false, Ordering::SeqCst);
+            sync_activation_policy(&app_handle);
+        }
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            schedule_window_state_save(&app_handle);
+        }
+        WindowEvent::ThemeChanged(theme) => {
+            emit_theme_changed(&app_handle, Some(Theme::from(*theme)));
+        }
+        _ => {}
+    });
+
+    Ok(window)
+}
+
+/// Apply the saved geometry, clamped to the monitors currently attached
+fn restore_window_state<R: Runtime>(app: &AppHandle<R>, window: &WebviewWindow<R>) {
+    let Some(saved) = app
+        .try_state::<AppState>()
+        .and_then(|state| state.config_manager.get().window_state)
+    else {
+        return;
+    };
+
+    let monitors: Vec<MonitorBounds> = match window.available_monitors() {
+        Ok(monitors) => monitors
+            .iter()
+            .map(|monitor| MonitorBounds {
+                x: monitor.position().x,
+                y: monitor.position().y,
+                width: monitor.size().width,
+                height: monitor.size().height,
+            })
+            .collect(),
+        Err(e) => {
+            error!("Failed to list monitors: {e:?}");
+            return;
+        }
+    };
+
+    if let Some(state) = clamp_window_state(saved, &monitors) {
+        debug!("Restoring settings window geometry: {state:?}");
+        if let Err(e) = window.set_size(PhysicalSize::new(state.width, state.height)) {
+            error!("Failed to restore settings window size: {e:?}");
+        }
+        if let Err(e) = window.set_position(PhysicalPosition::new(state.x, state.y)) {
+            error!("Failed to restore settings window position: {e:?}");
+        }
+    }
+}
+
+/// Save the window geometry once it has stopped moving
+fn schedule_window_state_save<R: Runtime>(app: &AppHandle<R>) {
+    let generation = WINDOW_STATE_SAVES.trigger();
+    let app = app.clone();
+
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(WINDOW_STATE_SAVE_DELAY).await;
+        if WINDOW_STATE_SAVES.is_latest(generation) {
+            save_window_state(&app);
+        }
+    });
+}
+
+fn save_window_state<R: Runtime>(app: &AppHandle<R>) {
+    let (Some(window), Some(state)) = (
+        app.get_webview_window(SETTINGS_WINDOW_LABEL),
+        app.try_state::<AppState>(),
+    ) else {
+        return;
+    };
+
+    // Don't remember the geometry of a minimized or hidden window
+    if window.is_minimized().unwrap_or(false) || !window.is_visible().unwrap_or(false) {
+        return;
+    }
+
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.outer_size()) else {
+        return;
+    };
+
+    let window_state = WindowState {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    debug!("Saving settings window geometry: {window_state:?}");
+
+    if let Err(e) = state
+        .config_manager
+        .update(|config| config.window_state = Some(window_state))
+    {
+        error!("Failed to save settings window geometry: {e}");
+    }
+}
+
+/// Show the settings window, creating it if needed, and bring it to the front
+pub fn show_settings_window<R: Runtime>(
+    app: &AppHandle<R>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let window = match app.get_webview_window(SETTINGS_WINDOW_LABEL) {
+        Some(window) => window,
+        None => create_settings_window(app)?,
+    };
+
+    SETTINGS_VISIBLE.store(true, Ordering::SeqCst);
+    sync_activation_policy(app);
+
+    window.show()?;
+    window.unminimize()?;
+    window.set_focus()?;
+
+    if let Some(state) = app.try_state::<AppState>() {
+        let snapshot = ConfigDto::from(state.config_manager.get());
+        if let Err(e) = app.emit("settings_opened", snapshot) {
+            error!("Failed to emit settings_opened event: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// How a close request on the settings window is carried out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CloseRequestAction {
+    /// Keep the window alive and hide it instead
+    pub prevent_close: bool,
+    /// Save the geometry immediately because the window is about to go away
+    pub save_geometry_now: bool,
+}
+
+/// Decide what closing the settings window does for the configured behavior
+pub fn close_request_action(behavior: SettingsCloseBehavior) -> CloseRequestAction {
+    match behavior {
+        SettingsCloseBehavior::Hide => CloseRequestAction {
+            prevent_close: true,
+            save_geometry_now: false,
+        },
+        SettingsCloseBehavior::Destroy => CloseRequestAction {
+            prevent_close: false,
+            save_geometry_now: true,
+        },
+    }
+}
+
+fn current_close_behavior<R: Runtime>(app: &AppHandle<R>) -> SettingsCloseBehavior {
+    app.try_state::<AppState>()
+        .map(|state| state.config_manager.get().settings_close_behavior)
+        .unwrap_or_default()
+}
+
+/// Hide the settings window, or let it be destroyed, per the close behavior
+pub fn handle_window_close_request<R: Runtime>(app: &AppHandle<R>, action: CloseRequestAction) {
+    SETTINGS_VISIBLE.store(false, Ordering::SeqCst);
+    if action.prevent_close {
+        debug!("Settings window close requested, hiding");
+        if let Some(window) = app.get_webview_window(SETTINGS_WINDOW_LABEL) {
+            if let Err(e) = window.hide() {
+                error!("Failed to hide settings window: {e:?}");
+            }
+        }
+    } else {
+        debug!("Settings window close requested, destroying");
+    }
+    sync_activation_policy(app);
+}
+
+/// Show the onboarding window, creating it if needed
+pub fn show_onboarding_window<R: Runtime>(
+    app: &AppHandle<R>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let window = match app.get_webview_window(ONBOARDING_WINDOW_LABEL) {
+        Some(window) => window,
+        None => {
+            info!("Creating onboarding window");
+            let window = WebviewWindowBuilder::new(
+                app,
+                ONBOARDING_WINDOW_LABEL,
+                WebviewUrl::App("onboarding.html".into()),
+            )
+            .title("Welcome to Pasta")
+            .inner_size(460.0, 520.0)
+            .center()
+            .visible(false)
+            .build()?;
+
+            let app_handle = app.clone();
+            window.on_window_event(move |event| match event {
+                WindowEvent::Destroyed => {
+                    ONBOARDING_VISIBLE.store(false, Ordering::SeqCst);
+                    sync_activation_policy(&app_handle);
+                }
+                WindowEvent::ThemeChanged(theme) => {
+                    emit_theme_changed(&app_handle, Some(Theme::from(*theme)));
+                }
+                _ => {}
+            });
+            window
+        }
+    };
+
+    ONBOARDING_VISIBLE.store(true, Ordering::SeqCst);
+    sync_activation_policy(app);
+
+    window.show()?;
+    window.set_focus()?;
+    Ok(())
+}
+
+/// Close the onboarding window once the user is done with it
+pub fn close_onboarding_window<R: Runtime>(app: &AppHandle<R>) {
+    ONBOARDING_VISIBLE.store(false, Ordering::SeqCst);
+    if let Some(window) = app.get_webview_window(ONBOARDING_WINDOW_LABEL) {
+        if let Err(e) = window.close() {
+            error!("Failed to close onboarding window: {e:?}");
+        }
+    }
+    sync_activation_policy(app);
+}
+
+/// Show what a held paste would type, creating the window if needed
+///
+/// Closing the window any way other than Type drops the paste.
+pub fn show_confirm_window<R: Runtime>(
+    app: &AppHandle<R>,
+    preview: &PastePreview,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let window = match app.get_webview_window(CONFIRM_WINDOW_LABEL) {
+        Some(window) => window,
+        None => {
+            info!("Creating paste confirmation window");
+            let window = WebviewWindowBuilder::new(
+                app,
+                CONFIRM_WINDOW_LABEL,
+                WebviewUrl::App("confirm.html".into()),
+            )
+            .title("Type this paste?")
+            .inner_size(420.0, 320.0)
+            .center()
+            .always_on_top(true)
+            .visible(false)
+            .build()?;
+
+            let app_handle = app.clone();
+            window.on_window_event(move |event| match event {
+                WindowEvent::Destroyed => {
+                    if let Some(state) = app_handle.try_state::<AppState>() {
+                        if state.pending_paste.take().is_some() {
+                            info!("Paste confirmation window closed, paste dropped");
+                        }
+                    }
+                }
+                WindowEvent::ThemeChanged(theme) => {
+                    emit_theme_changed(&app_handle, Some(Theme::from(*theme)));
+                }
+                _ => {}
+            });
+            window
+        }
+    };
+
+    window.show()?;
+    window.set_focus()?;
+    // An open window loaded an earlier paste, a new one asks on load
+    window.emit("paste_pending", preview)?;
+    Ok(())
+}
+
+/// Close the paste confirmation window, if open
+pub fn close_confirm_window<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window(CONFIRM_WINDOW_LABEL) {
+        if let Err(e) = window.close() {
+            error!("Failed to close paste confirmation window: {e:?}");
+        }
+    }
+}
+
+/// System theme as reported by any open window
+/// Pasta may have no windows open, in which case the theme is unknown
+pub fn system_theme<R: Runtime>(app: &AppHandle<R>) -> Option<Theme> {
+    app.webview_windows()
+        .values()
+        .find_map(|window| window.theme().ok())
+        .map(Theme::from)
+}
+
+/// Combine the configured theme preference with the system theme
+pub fn theme_state<R: Runtime>(app: &AppHandle<R>, system: Option<Theme>) -> ThemeState {
+    let preference = app
+        .try_state::<AppState>()
+        .map(|state| state.config_manager.get().theme)
+        .unwrap_or_default();
+    ThemeState::new(preference, system)
+}
+
+/// Tell the frontends which theme to use
+pub fn emit_theme_changed<R: Runtime>(app: &AppHandle<R>, system: Option<Theme>) {
+    let state = theme_state(app, system);
+    debug!("Theme changed: {state:?}");
+    if let Err(e) = app.emit("theme_changed", state) {
+        error!("Failed to emit theme_changed event: {e:?}");
+    }
+}
+
+/// Show the dock icon while a window is visible and hide it again afterwards (macOS)
+fn sync_activation_policy<R: Runtime>(app: &AppHandle<R>) {
+    #[cfg(target_os = "macos")]
+    {
+        let app_handle = app.clone();
+        let result = app.run_on_main_thread(move || {
+            let policy = match desired_activation_policy(any_window_visible()) {
+                "Regular" => tauri::ActivationPolicy::Regular,
+                _ => tauri::ActivationPolicy::Accessory,
+            };
+            let _ = app_handle.set_activation_policy(policy);
+        });
+        if let Err(e) = result {
+            error!("Failed to update activation policy: {e:?}");
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    let _ = app;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_window_label() {
+        assert_eq!(SETTINGS_WINDOW_LABEL, "settings");
+    }
+
+    #[test]
+    fn test_onboarding_window_label() {
+        assert_eq!(ONBOARDING_WINDOW_LABEL, "onboarding");
+        assert_ne!(ONBOARDING_WINDOW_LABEL, SETTINGS_WINDOW_LABEL);
+        assert_ne!(CONFIRM_WINDOW_LABEL, SETTINGS_WINDOW_LABEL);
+        assert_ne!(CONFIRM_WINDOW_LABEL, ONBOARDING_WINDOW_LABEL);
+    }
+
+    #[test]
+    fn test_close_action_hide() {
+        let action = close_request_action(SettingsCloseBehavior::Hide);
+        assert!(action.prevent_close);
+        assert!(!action.save_geometry_now);
+    }
+
+    #[test]
+    fn test_close_action_destroy() {
+        let action = close_request_action(SettingsCloseBehavior::Destroy);
+        assert!(!action.prevent_close);
+        assert!(action.save_geometry_now);
+    }
+
+    #[test]
+    fn test_close_action_default_keeps_hiding() {
+        assert!(close_request_action(SettingsCloseBehavior::default()).prevent_close);
+    }
+
+    #[test]
+    fn test_desired_activation_policy_visible() {
+        assert_eq!(desired_activation_policy(true), "Regular");
+    }
+
+    #[test]
+    fn test_desired_activation_policy_hidden() {
+        assert_eq!(desired_activation_policy(false), "Accessory");
+    }
+
+    fn monitor(x: i32, y: i32, width: u32, height: u32) -> MonitorBounds {
+        MonitorBounds {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn window(x: i32, y: i32, width: u32, height: u32) -> WindowState {
+        WindowState {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_clamp_keeps_visible_window() {
+        let monitors = [monitor(0, 0, 1920, 1080)];
+        let saved = window(100, 200, 400, 300);
+        assert_eq!(clamp_window_state(saved, &monitors), Some(saved));
+    }
+
+    #[test]
+    fn test_clamp_keeps_window_on_secondary_monitor() {
+        let monitors = [monitor(0, 0, 1920, 1080), monitor(-1280, 0, 1280, 1024)];
+        let saved = window(-1000, 100, 400, 300);
+        assert_eq!(clamp_window_state(saved, &monitors), Some(saved));
+    }
+
+    #[test]
+    fn test_clamp_moves_window_from_removed_monitor() {
+        // Saved on a left-hand display that is no longer attached
+        let monitors = [monitor(0, 0, 1920, 1080)];
+        let saved = window(-1000, 100, 400, 300);
+        assert_eq!(
+            clamp_window_state(saved, &monitors),
+            Some(window(0, 100, 400, 300))
+        );
+    }
+
+    #[test]
+    fn test_clamp_pulls_back_partially_offscreen_window() {
+        let monitors = [monitor(0, 0, 1920, 1080)];
+        let saved = window(1800, 1000, 400, 300);
+        assert_eq!(
+            clamp_window_state(saved, &monitors),
+            Some(window(1520, 780, 400, 300))
+        );
+    }
+
+    #[test]
+    fn test_clamp_shrinks_oversized_window() {
+        let monitors = [monitor(0, 0, 1280, 720)];
+        let saved = window(0, 0, 2560, 1440);
+        assert_eq!(
+            clamp_window_state(saved, &monitors),
+            Some(window(0, 0, 1280, 720))
+        );
+    }
+
+    #[test]
+    fn test_clamp_picks_monitor_with_most_overlap() {
+        let monitors = [monitor(0, 0, 1920, 1080), monitor(1920, 0, 1920, 1080)];
+        // Straddles both, mostly on the right-hand monitor
+        let saved = window(1820, 100, 400, 300);
+        assert_eq!(
+            clamp_window_state(saved, &monitors),
+            Some(window(1920, 100, 400, 300))
+        );
+    }
+
+    #[test]
+    fn test_clamp_without_monitors() {
+        assert_eq!(clamp_window_state(window(0, 0, 400, 300), &[]), None);
+    }
+
+    #[test]
+    fn test_debouncer_only_latest_is_current() {
+        let debouncer = Debouncer::new();
+        let first = debouncer.trigger();
+        let second = debouncer.trigger();
+
+        assert!(!debouncer.is_latest(first));
+        assert!(debouncer.is_latest(second));
+    }
+
+    #[test]
+    fn test_latest_visibility_wins() {
+        // Simulate a close arriving right after an open: the policy applied
+        // later must reflect the close
+        let visible = AtomicBool::new(false);
+        visible.store(true, Ordering::SeqCst);
+        visible.store(false, Ordering::SeqCst);
+        assert_eq!(
+            desired_activation_policy(visible.load(Ordering::SeqCst)),
+            "Accessory"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::sync::{atomic::AtomicBool, Arc};
 
 use pasta_tray_lib::{
+    config::ConfigManager,
     create_app_state, initialize_components,
     keyboard::{KeyboardEmulator, TypingSpeed},
 };
@@ -124,8 +125,50 @@ fn test_full_app_initialization() {
     assert!(Arc::strong_count(&keyboard_emulator) > 0);
 
     // Create app state
-    let app_state = create_app_state(keyboard_emulator.clone());
+    let config_dir = tempfile::tempdir().unwrap();
+    let config_manager = Arc::new(ConfigManager::with_path(
+        config_dir.path().join("config.toml"),
+    ));
+    let app_state = create_app_state(keyboard_emulator.clone(), config_manager);
 
     // Verify app state is properly created
     assert!(!app_state.is_cancelled());
 }
+
+/// Run the app binary as a command line client
+fn pasta(args: &[&str]) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_pasta-tray"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+#[ignore = "Needs a running Pasta instance - run with --ignored flag"]
+fn test_cli_status_json_from_running_instance() {
+    let output = pasta(&["status", "--json"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let state: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(state["isTyping"].is_boolean());
+    assert!(state["queueLen"].is_number());
+}
+
+#[test]
+#[ignore = "Needs a running Pasta instance - run with --ignored flag"]
+fn test_cli_cancel_when_idle() {
+    let output = pasta(&["cancel"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Nothing was typing"
+    );
+}
+
+#[test]
+#[ignore = "Starts the app binary - run with --ignored flag"]
+fn test_cli_usage_error() {
+    let output = pasta(&["frobnicate"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: pasta"));
+}